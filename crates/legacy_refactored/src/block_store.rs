@@ -3,9 +3,8 @@ use std::marker::PhantomData;
 use hotshot::traits::BlockPayload;
 use hotshot_builder_api::v0_1::block_info::AvailableBlockInfo;
 use hotshot_types::traits::signature_key::BuilderSignatureKey;
-use hotshot_types::vid::{VidCommitment, VidPrecomputeData};
 use marketplace_builder_shared::error::Error;
-use marketplace_builder_shared::utils::{BuilderKeys, WaitAndKeep};
+use marketplace_builder_shared::utils::BuilderKeys;
 use marketplace_builder_shared::{
     block::BuilderStateId, coordinator::tiered_view_map::TieredViewMap,
 };
@@ -14,12 +13,14 @@ use marketplace_builder_shared::block::BlockId;
 
 use hotshot_types::traits::node_implementation::NodeType;
 
+use crate::vid_pool::VidJob;
+
 // It holds all the necessary information for a block
 #[derive(Debug, Clone)]
 pub struct BlockInfo<Types: NodeType> {
     pub block_payload: Types::BlockPayload,
     pub metadata: <<Types as NodeType>::BlockPayload as BlockPayload<Types>>::Metadata,
-    pub vid_data: WaitAndKeep<(VidCommitment, VidPrecomputeData)>,
+    pub vid_data: VidJob,
     pub block_size: u64,
     pub offered_fee: u64,
     // Could we have included more transactions with this block, but chose not to?
@@ -53,7 +54,7 @@ impl<Types: NodeType> BlockInfo<Types> {
 #[derive(Default)]
 pub struct BlockStore<Types: NodeType> {
     pub(crate) blocks: TieredViewMap<BlockId<Types>, BlockInfo<Types>>,
-    pub(crate) block_cache: TieredViewMap<BuilderStateId<Types>, BlockId<Types>>,
+    pub(crate) block_cache: TieredViewMap<BuilderStateId<Types>, Vec<BlockId<Types>>>,
 }
 
 impl<Types: NodeType> BlockStore<Types> {
@@ -61,19 +62,36 @@ impl<Types: NodeType> BlockStore<Types> {
         Self::default()
     }
 
+    /// Store candidate blocks built by `built_by`, each under its own [`BlockId`].
+    /// Blocks previously built by `built_by` stay claimable until pruned, as a proposer
+    /// might still be holding an earlier response. Their VID jobs stay idle unless claimed.
     pub fn update(
         &mut self,
         built_by: BuilderStateId<Types>,
-        block_id: BlockId<Types>,
-        block_info: BlockInfo<Types>,
+        blocks: impl IntoIterator<Item = (BlockId<Types>, BlockInfo<Types>)>,
     ) {
-        self.blocks.insert(block_id.clone(), block_info);
-        self.block_cache.insert(built_by, block_id);
+        let block_ids = blocks
+            .into_iter()
+            .map(|(block_id, block_info)| {
+                // Keep the existing block if we've built the same one before,
+                // as its VID precomputation might be underway already
+                if self.blocks.get(&block_id).is_none() {
+                    self.blocks.insert(block_id.clone(), block_info);
+                }
+                block_id
+            })
+            .collect();
+        self.block_cache.insert(built_by, block_ids);
     }
 
-    pub fn get_cached(&self, builder_id: &BuilderStateId<Types>) -> Option<&BlockInfo<Types>> {
-        let block_id = self.block_cache.get(builder_id)?;
-        self.blocks.get(block_id)
+    /// Candidate blocks last built by `builder_id`
+    pub fn get_cached(&self, builder_id: &BuilderStateId<Types>) -> Vec<&BlockInfo<Types>> {
+        self.block_cache
+            .get(builder_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|block_id| self.blocks.get(block_id))
+            .collect()
     }
 
     pub fn get_block(&self, block_id: &BlockId<Types>) -> Option<&BlockInfo<Types>> {
@@ -0,0 +1,120 @@
+//! Policies deciding when the builder offers empty blocks.
+//!
+//! Without transactions to include, the builder normally doesn't respond with any blocks.
+//! Offering empty blocks anyway can speed up finalization of previous blocks, as every
+//! block proposed moves consensus forward. [`EmptyBlockPolicy`] decides when that's done.
+
+use std::{
+    fmt::Debug,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use hotshot_types::traits::node_implementation::NodeType;
+use marketplace_builder_shared::state::BuilderState;
+
+/// Default number of views after a non-empty block that [`AfterNonEmpty`] allows empty blocks for.
+///
+/// This value governs the ability for the Builder to prioritize finalizing
+/// transactions by producing empty blocks rather than avoiding the creation
+/// of them, following the proposal that contains transactions.
+pub const ALLOW_EMPTY_BLOCK_PERIOD: u64 = 3;
+
+/// A policy deciding when the builder offers empty blocks
+pub trait EmptyBlockPolicy<Types: NodeType>: Debug + Send + Sync + 'static {
+    /// Whether a block may be built on top of `builder_state` even if
+    /// there are no transactions to include in it
+    fn allow_empty_block(&self, builder_state: &BuilderState<Types>) -> bool;
+
+    /// Called whenever the builder responds to `available_blocks` request for `view` with blocks
+    fn on_block_served(&self, _view: Types::View) {}
+}
+
+/// Never offer empty blocks
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Never;
+
+impl<Types: NodeType> EmptyBlockPolicy<Types> for Never {
+    fn allow_empty_block(&self, _builder_state: &BuilderState<Types>) -> bool {
+        false
+    }
+}
+
+/// Always offer a block, even if it's empty
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Always;
+
+impl<Types: NodeType> EmptyBlockPolicy<Types> for Always {
+    fn allow_empty_block(&self, _builder_state: &BuilderState<Types>) -> bool {
+        true
+    }
+}
+
+/// Offer empty blocks if the parent block had transactions included and fewer than
+/// [`Self::period`] views have passed since the last non-empty block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AfterNonEmpty {
+    /// Number of views after a non-empty block to allow empty blocks for
+    pub period: u64,
+}
+
+impl Default for AfterNonEmpty {
+    fn default() -> Self {
+        Self {
+            period: ALLOW_EMPTY_BLOCK_PERIOD,
+        }
+    }
+}
+
+impl<Types: NodeType> EmptyBlockPolicy<Types> for AfterNonEmpty {
+    fn allow_empty_block(&self, builder_state: &BuilderState<Types>) -> bool {
+        let parent = &builder_state.parent_block_references;
+        parent.tx_count != 0
+            && parent
+                .last_nonempty_view
+                .map(|nonempty_view| {
+                    nonempty_view.saturating_sub(*parent.view_number) < self.period
+                })
+                .unwrap_or(false)
+    }
+}
+
+/// Offer an empty block if no blocks were served for [`Self::timeout`]
+#[derive(Debug)]
+pub struct IdleTimeout {
+    timeout: Duration,
+    last_served: Mutex<Instant>,
+}
+
+impl IdleTimeout {
+    /// Create a policy allowing empty blocks once no blocks
+    /// were served for `timeout`, starting from now
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_served: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Time without blocks served after which an empty block is offered
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn last_served(&self) -> std::sync::MutexGuard<'_, Instant> {
+        // Poisoning isn't a concern, as the timestamp is always valid
+        self.last_served
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<Types: NodeType> EmptyBlockPolicy<Types> for IdleTimeout {
+    fn allow_empty_block(&self, _builder_state: &BuilderState<Types>) -> bool {
+        self.last_served().elapsed() >= self.timeout
+    }
+
+    fn on_block_served(&self, _view: Types::View) {
+        *self.last_served() = Instant::now();
+    }
+}
@@ -8,9 +8,10 @@
 //! 1. Serves a user's request to submit a private transaction
 #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
 
-pub mod block_size_limits;
 pub mod block_store;
+pub mod empty_block;
 pub mod service;
+pub mod vid_pool;
 
 // tracking the testing
 #[cfg(test)]
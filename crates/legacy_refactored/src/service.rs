@@ -1,10 +1,10 @@
 use hotshot::types::Event;
 use hotshot_builder_api::v0_1::{
     block_info::{AvailableBlockData, AvailableBlockHeaderInput, AvailableBlockInfo},
-    builder::{define_api, submit_api, BuildError, Error as BuilderApiError, TransactionStatus},
+    builder::{define_api, BuildError, Error as BuilderApiError, TransactionStatus},
     data_source::{AcceptsTxnSubmits, BuilderDataSource},
 };
-use hotshot_types::traits::block_contents::{precompute_vid_commitment, Transaction};
+use hotshot_types::traits::block_contents::Transaction;
 use hotshot_types::traits::EncodeBytes;
 use hotshot_types::{
     event::EventType,
@@ -16,32 +16,54 @@ use hotshot_types::{
     utils::BuilderCommitment,
     vid::VidCommitment,
 };
-use marketplace_builder_shared::coordinator::BuilderStateLookup;
+use marketplace_builder_shared::block_size_limits::{BlockSizeController, BlockSizeLimits};
+use marketplace_builder_shared::coordinator::{
+    offers::OfferPolicy, proposal_validation::LeaderOracle, BuilderStateLookup,
+};
 use marketplace_builder_shared::error::Error;
+use marketplace_builder_shared::fee_ledger::{
+    self, FeeLedger, FeeLedgerDataSource, FeeSubject, FEE_LEDGER_MODULE,
+};
+use marketplace_builder_shared::inclusion_estimate::{
+    self, InclusionEstimate, InclusionEstimateDataSource, InclusionEstimator,
+    INCLUSION_ESTIMATE_MODULE,
+};
+use marketplace_builder_shared::receipt::{
+    self, InclusionReceipt, InclusionReceiptDataSource, ReceiptStore, INCLUSION_RECEIPT_MODULE,
+};
+use marketplace_builder_shared::shutdown::{Shutdown, ShutdownPhase};
 use marketplace_builder_shared::state::BuilderState;
-use marketplace_builder_shared::utils::{BuilderKeys, WaitAndKeep};
+use marketplace_builder_shared::submit_limit::{
+    self, PrivateMempoolDataSource, SubmitLimiter, SubmitLimiterMetrics, SubmitLimits,
+    ANONYMOUS_CLIENT, PRIVATE_MEMPOOL_MODULE,
+};
+use marketplace_builder_shared::utils::{BuilderKeys, GapCallback};
 use tide_disco::app::AppError;
 use tokio::spawn;
 use tokio::time::{sleep, timeout};
 use tracing::{error, info, instrument, trace, warn};
-use vbs::version::StaticVersion;
 
 use marketplace_builder_shared::{
     block::{BlockId, BuilderStateId, ReceivedTransaction, TransactionSource},
     coordinator::BuilderStateCoordinator,
 };
 
-use crate::block_size_limits::BlockSizeLimits;
 use crate::block_store::{BlockInfo, BlockStore};
+use crate::empty_block::{AfterNonEmpty, EmptyBlockPolicy};
+use crate::vid_pool::{VidLatencyCallback, VidPoolMetrics, VidPriority, VidWorkerPool};
 pub use async_broadcast::{broadcast, RecvError, TryRecvError};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use committable::Commitment;
-use futures::{future::BoxFuture, Stream};
+use futures::{
+    future::{BoxFuture, Future},
+    Stream,
+};
 use futures::{
     stream::{FuturesOrdered, FuturesUnordered, StreamExt},
     TryStreamExt,
 };
+use std::collections::HashSet;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -65,13 +87,15 @@ const RETRY_LOOP_RESOLUTION: u32 = 10;
 /// more than [`GlobalState::max_api_waiting_time`] / `VID_RESPONSE_TARGET_MARGIN_DIVISOR`
 const VID_RESPONSE_TARGET_MARGIN_DIVISOR: u32 = 10;
 
-/// [`ALLOW_EMPTY_BLOCK_PERIOD`] is a constant that is used to determine the
-/// number of future views that we will allow building empty blocks for.
-///
-/// This value governs the ability for the Builder to prioritize finalizing
-/// transactions by producing empty blocks rather than avoiding the creation
-/// of them, following the proposal that contains transactions.
-pub(crate) const ALLOW_EMPTY_BLOCK_PERIOD: u64 = 3;
+/// Shape of a candidate block offered in response to `available_blocks`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockCandidate {
+    /// Maximum size of the block. Never exceeds the builder's current block size limit,
+    /// which is used if unset.
+    pub max_block_size: Option<u64>,
+    /// Base fee for the block, overriding [`BuilderConfig::base_fee`]
+    pub base_fee: Option<u64>,
+}
 
 /// Configuration to initialize the builder
 #[derive(Debug, Clone)]
@@ -96,8 +120,35 @@ pub struct BuilderConfig<Types: NodeType> {
     pub txn_channel_capacity: usize,
     /// Capacity of cache storing information for transaction status API
     pub tx_status_cache_capacity: usize,
+    /// If set, proposals from senders other than the leader for the proposal's view
+    /// will be rejected
+    pub leader_oracle: Option<LeaderOracle<Types>>,
     /// Base fee; the sequencing fee for a block is calculated as block size × base fee
     pub base_fee: u64,
+    /// Whether transactions already offered in blocks for other views
+    /// may be offered again before those views are decided
+    pub offer_policy: OfferPolicy,
+    /// Candidate blocks to offer for every `available_blocks` request, for example
+    /// a small block that is fast to compute VID for and a full one.
+    /// If empty, a single block at the current block size limit is offered.
+    pub block_candidates: Vec<BlockCandidate>,
+    /// Maximum number of VID precomputations to run at once.
+    /// Defaults to the number of CPUs.
+    pub max_concurrent_vid: Option<usize>,
+    /// How block size limits are adjusted. With [`BlockSizeController::LatencyModel`],
+    /// blocks are sized for VID to be precomputed within [`Self::max_api_waiting_time`]
+    /// minus the model's safety margin.
+    pub block_size_controller: BlockSizeController,
+    /// Decides when to offer empty blocks. Defaults to [`AfterNonEmpty`].
+    pub empty_block_policy: Arc<dyn EmptyBlockPolicy<Types>>,
+    /// Whether to issue signed receipts for transactions in claimed blocks.
+    /// Receipts are kept for up to [`Self::tx_status_cache_capacity`] transactions.
+    pub inclusion_receipts: bool,
+    /// Limits on private mempool submissions, see [`SubmitLimits`]
+    pub submit_limits: SubmitLimits,
+    /// Period for which queue statistics and latencies inclusion estimates are based on
+    /// are reused, see [`InclusionEstimator`]
+    pub estimate_refresh_period: Duration,
 }
 
 #[cfg(test)]
@@ -116,6 +167,16 @@ impl<Types: NodeType> BuilderConfig<Types> {
             txn_channel_capacity: TEST_CHANNEL_BUFFER_SIZE,
             tx_status_cache_capacity: TEST_TX_STATUS_CACHE_CAPACITY,
             base_fee: TEST_BASE_FEE,
+            leader_oracle: None,
+            offer_policy: OfferPolicy::default(),
+            block_candidates: Vec::new(),
+            max_concurrent_vid: None,
+            block_size_controller: BlockSizeController::default(),
+            empty_block_policy: Arc::new(AfterNonEmpty::default()),
+            inclusion_receipts: false,
+            submit_limits: SubmitLimits::default(),
+            // Tests expect estimates to reflect the queue immediately
+            estimate_refresh_period: Duration::ZERO,
         }
     }
 }
@@ -128,7 +189,7 @@ pub struct GlobalState<Types: NodeType> {
     /// Stores blocks built by this builder
     pub(crate) block_store: RwLock<BlockStore<Types>>,
     /// Limits on block size. See [`BlockSizeLimits`] documentation for more details.
-    pub(crate) block_size_limits: BlockSizeLimits,
+    pub(crate) block_size_limits: Arc<BlockSizeLimits>,
     /// Number of DA nodes used in VID computation
     pub(crate) num_nodes: AtomicUsize,
     /// Instance state, used to construct new blocks
@@ -139,6 +200,20 @@ pub struct GlobalState<Types: NodeType> {
     pub(crate) maximize_txn_capture_timeout: Duration,
     /// See [`BuilderConfig::base_fee`]
     pub(crate) base_fee: u64,
+    /// See [`BuilderConfig::block_candidates`]
+    pub(crate) block_candidates: Vec<BlockCandidate>,
+    /// Workers precomputing VID for claimed blocks
+    pub(crate) vid_pool: VidWorkerPool,
+    /// See [`BuilderConfig::empty_block_policy`]
+    pub(crate) empty_block_policy: Arc<dyn EmptyBlockPolicy<Types>>,
+    /// Transactions of claimed blocks, if [`BuilderConfig::inclusion_receipts`] is set
+    pub(crate) receipts: Option<ReceiptStore<Types>>,
+    /// Graceful shutdown of the builder, see [`Self::shutdown`]
+    pub(crate) shutdown: Shutdown,
+    /// Enforces [`BuilderConfig::submit_limits`]
+    pub(crate) submit_limiter: SubmitLimiter,
+    /// Inputs of inclusion estimates, see [`BuilderConfig::estimate_refresh_period`]
+    pub(crate) estimator: InclusionEstimator,
 }
 
 impl<Types: NodeType> GlobalState<Types>
@@ -154,28 +229,65 @@ where
         protocol_max_block_size: u64,
         num_nodes: usize,
     ) -> Arc<Self> {
+        let mut coordinator = BuilderStateCoordinator::new(
+            config.txn_channel_capacity,
+            config.txn_garbage_collect_duration,
+            config.tx_status_cache_capacity,
+        )
+        .with_offer_policy(config.offer_policy);
+        if let Some(leader_oracle) = config.leader_oracle {
+            coordinator = coordinator.with_leader_oracle(leader_oracle);
+        }
+        let block_size_limits = Arc::new(BlockSizeLimits::with_controller(
+            protocol_max_block_size,
+            config.max_block_size_increment_period,
+            config.block_size_controller,
+            config.max_api_waiting_time,
+        ));
+        // Feed VID latency measurements to the latency model, if there is one
+        let vid_pool = VidWorkerPool::with_latency_callback(
+            config.max_concurrent_vid.unwrap_or_else(num_cpus::get),
+            block_size_limits.uses_latency_model().then(|| {
+                let block_size_limits = Arc::clone(&block_size_limits);
+                Arc::new(
+                    move |block_size: u64, num_nodes: usize, duration: Duration| {
+                        block_size_limits.record_vid_latency(block_size, num_nodes, duration)
+                    },
+                ) as VidLatencyCallback
+            }),
+        );
         Arc::new(Self {
-            coordinator: Arc::new(BuilderStateCoordinator::new(
-                config.txn_channel_capacity,
-                config.txn_garbage_collect_duration,
-                config.tx_status_cache_capacity,
-            )),
+            coordinator: Arc::new(coordinator),
             block_store: RwLock::new(BlockStore::new()),
-            block_size_limits: BlockSizeLimits::new(
-                protocol_max_block_size,
-                config.max_block_size_increment_period,
-            ),
+            block_size_limits,
             num_nodes: num_nodes.into(),
             builder_keys: config.builder_keys,
             max_api_waiting_time: config.max_api_waiting_time,
             maximize_txn_capture_timeout: config.maximize_txn_capture_timeout,
             instance_state,
             base_fee: config.base_fee,
+            block_candidates: if config.block_candidates.is_empty() {
+                vec![BlockCandidate::default()]
+            } else {
+                config.block_candidates
+            },
+            vid_pool,
+            empty_block_policy: config.empty_block_policy,
+            receipts: config
+                .inclusion_receipts
+                .then(|| ReceiptStore::new(config.tx_status_cache_capacity)),
+            shutdown: Shutdown::new(),
+            submit_limiter: SubmitLimiter::new(config.submit_limits),
+            estimator: InclusionEstimator::new(
+                config.estimate_refresh_period,
+                config.maximize_txn_capture_timeout,
+            ),
         })
     }
 
     /// Spawns an event loop handling HotShot events from the provided stream.
-    /// Returns a handle for the spawned task.
+    /// Returns a handle for the spawned task, which resolves once the builder
+    /// is shut down or the stream ends.
     pub fn start_event_loop(
         self: Arc<Self>,
         event_stream: impl Stream<Item = Event<Types>> + Unpin + Send + 'static,
@@ -183,17 +295,126 @@ where
         spawn(self.event_loop(event_stream))
     }
 
+    /// Handle to the shutdown of this builder, see [`Self::shutdown`]
+    pub fn shutdown_handle(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    /// Shut the builder down gracefully. New private submissions are rejected with
+    /// [`Error::ShuttingDown`], while public transactions from HotShot's mempool are
+    /// still accepted, in-flight `claim_block` and `claim_block_header_input`
+    /// requests are allowed to finish, and then the event loop is stopped.
+    /// Returns transactions that were pending at that point, in the order they were
+    /// received, so that they can be persisted or submitted elsewhere.
+    pub async fn shutdown(&self) -> Vec<Types::Transaction> {
+        info!("Shutting down, waiting for in-flight requests");
+        self.shutdown.begin();
+        self.shutdown.drained().await;
+
+        let pending = self.coordinator.pending_transactions().await;
+        info!(num_pending = pending.len(), "Stopping event loop");
+        self.shutdown.stop();
+
+        pending
+            .into_iter()
+            .map(|txn| txn.transaction.clone())
+            .collect()
+    }
+
+    /// Spawns a task that shuts the builder down once `signal` resolves, e.g.
+    /// `CancellationToken::cancelled_owned`. See [`Self::shutdown`] for details.
+    /// Returns a handle resolving to transactions that were pending.
+    pub fn shutdown_on(
+        self: Arc<Self>,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> JoinHandle<Vec<Types::Transaction>> {
+        spawn(async move {
+            signal.await;
+            self.shutdown().await
+        })
+    }
+
+    /// Current utilization of the VID precomputation worker pool
+    pub fn vid_pool_metrics(&self) -> VidPoolMetrics {
+        self.vid_pool.metrics()
+    }
+
+    /// Current state of the private mempool submission limiter
+    pub fn submit_limiter_metrics(&self) -> SubmitLimiterMetrics {
+        self.submit_limiter.metrics()
+    }
+
+    /// Submit transactions to the private mempool on behalf of `client`, enforcing
+    /// [`BuilderConfig::submit_limits`] for it. Submissions through the private mempool API
+    /// are attributed to the client's IP address, see [`submit_limit::remote_client`].
+    pub async fn submit_txns_from(
+        &self,
+        client: &str,
+        txns: Vec<Types::Transaction>,
+    ) -> Result<Vec<Commitment<Types::Transaction>>, Error<Types>> {
+        if self.shutdown.is_shutting_down() {
+            return Err(Error::ShuttingDown);
+        }
+        let txns: Vec<_> = txns
+            .into_iter()
+            .map(|txn| ReceivedTransaction::new(txn, TransactionSource::Private))
+            .collect();
+        let num_bytes = txns.iter().map(|txn| txn.min_block_size).sum();
+        self.submit_limiter
+            .check::<Types>(client, txns.len(), num_bytes)
+            .inspect_err(|err| warn!(%client, %err, "Rejected private mempool submission"))?;
+        txns.into_iter()
+            .map(|txn| async {
+                let commit = txn.commit;
+                self.handle_transaction(txn).await?;
+                Ok(commit)
+            })
+            .collect::<FuturesOrdered<_>>()
+            .try_collect()
+            .await
+    }
+
+    /// Estimate fee and time until inclusion for a transaction of `transaction_size` bytes
+    /// submitted now, based on the queue of the highest view builder state, current
+    /// maximum block size and recent inclusion latencies, refreshed at most once per
+    /// [`BuilderConfig::estimate_refresh_period`]
+    pub async fn estimate_inclusion(&self, transaction_size: u64) -> InclusionEstimate {
+        let inputs = self.estimator.inputs(&self.coordinator).await;
+        InclusionEstimate::new(
+            transaction_size,
+            inputs.queue,
+            self.block_size_limits.max_block_size(),
+            self.base_fee.saturating_mul(transaction_size),
+            inputs.latency,
+        )
+    }
+
+    /// Returns a callback re-anchoring builder states once the events stream
+    /// reports views lost while reconnecting, see
+    /// [`BuilderStateCoordinator::event_gap_handler`]
+    pub fn event_gap_handler(&self) -> GapCallback {
+        self.coordinator.event_gap_handler()
+    }
+
     /// Internal implementation of the event loop, drives the underlying coordinator
     /// and runs hooks
     async fn event_loop(
         self: Arc<Self>,
-        mut event_stream: impl Stream<Item = Event<Types>> + Unpin + Send + 'static,
+        event_stream: impl Stream<Item = Event<Types>> + Unpin + Send + 'static,
     ) -> anyhow::Result<()> {
+        let mut event_stream =
+            event_stream.take_until(Box::pin(self.shutdown.reached(ShutdownPhase::Stopped)));
         loop {
             let Some(event) = event_stream.next().await else {
+                if self.shutdown.phase() == ShutdownPhase::Stopped {
+                    info!("Event loop stopped");
+                    return Ok(());
+                }
                 anyhow::bail!("Event stream ended");
             };
 
+            self.coordinator.handle_pending_event_gap().await;
+
             match event.event {
                 EventType::Error { error } => {
                     error!("Error event in HotShot: {:?}", error);
@@ -223,35 +444,64 @@ where
                     let this = Arc::clone(&self);
                     spawn(async move { this.block_store.write().await.prune(prune_cutoff) });
                 }
-                EventType::DaProposal { proposal, .. } => {
+                EventType::ViewTimeout { view_number } => {
                     let coordinator = Arc::clone(&self.coordinator);
-                    spawn(async move { coordinator.handle_da_proposal(proposal.data).await });
+                    spawn(async move { coordinator.handle_view_timeout(view_number).await });
                 }
-                EventType::QuorumProposal { proposal, .. } => {
+                EventType::DaProposal { proposal, sender } => {
                     let coordinator = Arc::clone(&self.coordinator);
-                    spawn(async move { coordinator.handle_quorum_proposal(proposal.data).await });
+                    spawn(async move {
+                        coordinator
+                            .handle_signed_da_proposal(proposal, sender)
+                            .await
+                    });
+                }
+                EventType::QuorumProposal { proposal, sender } => {
+                    let coordinator = Arc::clone(&self.coordinator);
+                    spawn(async move {
+                        coordinator
+                            .handle_signed_quorum_proposal(proposal, sender)
+                            .await
+                    });
                 }
                 _ => {}
             }
         }
     }
 
-    /// Consumes `self` and returns a `tide_disco` [`App`] with builder and private mempool APIs registered
+    /// Consumes `self` and returns a `tide_disco` [`App`] with builder, private mempool,
+    /// fee ledger, inclusion receipt and inclusion estimate APIs registered.
+    /// Private mempool is served by [`submit_limit::define_api`] instead of HotShot's
+    /// `txn_submit` API, so that submission limits apply to every client separately.
+    /// Serve the app with [`RetryAfterListener`](marketplace_builder_shared::utils::RetryAfterListener)
+    /// for rate-limited requests to get a `Retry-After` header.
     pub fn into_app(
         self: Arc<Self>,
     ) -> Result<App<ProxyGlobalState<Types>, BuilderApiError>, AppError> {
         let proxy = ProxyGlobalState(self);
         let builder_api = define_api::<ProxyGlobalState<Types>, Types>(&Default::default())?;
 
-        // TODO: Replace StaticVersion with proper constant when added in HotShot
-        let private_mempool_api =
-            submit_api::<ProxyGlobalState<Types>, Types, StaticVersion<0, 1>>(&Default::default())?;
-
         let mut app: App<ProxyGlobalState<Types>, BuilderApiError> = App::with_state(proxy);
 
         app.register_module(hotshot_types::constants::LEGACY_BUILDER_MODULE, builder_api)?;
 
-        app.register_module("txn_submit", private_mempool_api)?;
+        app.register_module(
+            PRIVATE_MEMPOOL_MODULE,
+            submit_limit::define_api::<ProxyGlobalState<Types>, Types, BuilderApiError>()?,
+        )?;
+
+        app.register_module(
+            FEE_LEDGER_MODULE,
+            fee_ledger::define_api::<ProxyGlobalState<Types>, Types, BuilderApiError>()?,
+        )?;
+        app.register_module(
+            INCLUSION_RECEIPT_MODULE,
+            receipt::define_api::<ProxyGlobalState<Types>, Types, BuilderApiError>()?,
+        )?;
+        app.register_module(
+            INCLUSION_ESTIMATE_MODULE,
+            inclusion_estimate::define_api::<ProxyGlobalState<Types>, BuilderApiError>()?,
+        )?;
 
         Ok(app)
     }
@@ -291,14 +541,20 @@ where
         }
     }
 
-    /// Build a block with provided builder state
+    /// Build candidate blocks for `view` with provided builder state,
+    /// one for each of [`BuilderConfig::block_candidates`]. Candidates
+    /// with the same contents are only built once, with the fee of the first one.
     ///
-    /// Returns None if there are no transactions to include
-    /// and we aren't prioritizing finalization for this builder state
-    pub(crate) async fn build_block(
+    /// Returns an empty list if there are no transactions to include
+    /// and we aren't prioritizing finalization for this builder state.
+    /// Transactions excluded by [`BuilderConfig::offer_policy`] aren't included,
+    /// nor are transactions of blocks claimed for other views.
+    /// Built blocks aren't considered offered until they're returned, see [`Self::record_offered`].
+    pub(crate) async fn build_blocks(
         &self,
         builder_state: Arc<BuilderState<Types>>,
-    ) -> Result<Option<BlockInfo<Types>>, Error<Types>> {
+        view: Types::View,
+    ) -> Result<Vec<BlockInfo<Types>>, Error<Types>> {
         let timeout_after = Instant::now() + self.maximize_txn_capture_timeout;
         let sleep_interval = self.maximize_txn_capture_timeout / RETRY_LOOP_RESOLUTION;
 
@@ -313,21 +569,89 @@ where
             sleep(sleep_interval).await
         }
 
-        // If the parent block had transactions included and [`ALLOW_EMPTY_BLOCK_PERIOD`] views has not
-        // passed since, we will allow building empty blocks. This is done to allow for faster finalization
-        // of previous blocks that have had transactions included in them.
-        let should_prioritize_finalization = builder_state.parent_block_references.tx_count != 0
-            && builder_state
-                .parent_block_references
-                .last_nonempty_view
-                .map(|nonempty_view| {
-                    nonempty_view.saturating_sub(*builder_state.parent_block_references.view_number)
-                        < ALLOW_EMPTY_BLOCK_PERIOD
-                })
-                .unwrap_or(false);
+        // Building empty blocks allows for faster finalization of previous blocks,
+        // see [`BuilderConfig::empty_block_policy`]
+        let should_prioritize_finalization =
+            self.empty_block_policy.allow_empty_block(&builder_state);
+
+        let mut excluded = self.coordinator.excluded_offers(view).await;
+        excluded.extend(self.coordinator.tentatively_included(view).await);
+
+        let mut blocks: Vec<BlockInfo<Types>> = Vec::with_capacity(self.block_candidates.len());
+        let mut block_hashes = HashSet::with_capacity(self.block_candidates.len());
+        for candidate in &self.block_candidates {
+            let Some((payload, metadata, truncated)) = self
+                .build_payload(
+                    &builder_state,
+                    candidate,
+                    &excluded,
+                    should_prioritize_finalization,
+                )
+                .await?
+            else {
+                continue;
+            };
+            if !block_hashes.insert(payload.builder_commitment(&metadata)) {
+                // Same contents as one of the previous candidates
+                continue;
+            }
+
+            let encoded_txns: Vec<u8> = payload.encode().to_vec();
+            let block_size: u64 = encoded_txns.len() as u64;
+            let offered_fee: u64 = candidate.base_fee.unwrap_or(self.base_fee) * block_size;
+
+            // Get the number of nodes stored while processing the `claim_block_with_num_nodes` request
+            // or upon initialization.
+            let num_nodes = self.num_nodes.load(Ordering::Relaxed);
+
+            // VID precomputation doesn't start until the block is claimed
+            let vid_data = self.vid_pool.job(encoded_txns, num_nodes);
+
+            info!(
+                builder_id = %builder_state.id(),
+                txn_count = payload.num_transactions(&metadata),
+                block_size,
+                offered_fee,
+                "Built a block",
+            );
 
-        let builder: &Arc<BuilderState<Types>> = &builder_state;
-        let max_block_size = self.block_size_limits.max_block_size();
+            blocks.push(BlockInfo {
+                block_payload: payload,
+                block_size,
+                metadata,
+                vid_data,
+                offered_fee,
+                truncated,
+            });
+        }
+
+        Ok(blocks)
+    }
+
+    /// Build the payload of a single candidate block with provided builder state,
+    /// returning it along with its metadata and whether it was truncated.
+    ///
+    /// Returns None if there are no transactions to include
+    /// and we aren't prioritizing finalization.
+    async fn build_payload(
+        &self,
+        builder: &Arc<BuilderState<Types>>,
+        candidate: &BlockCandidate,
+        excluded: &HashSet<Commitment<Types::Transaction>>,
+        should_prioritize_finalization: bool,
+    ) -> Result<
+        Option<(
+            Types::BlockPayload,
+            <Types::BlockPayload as BlockPayload<Types>>::Metadata,
+            bool,
+        )>,
+        Error<Types>,
+    > {
+        let max_block_size = candidate
+            .max_block_size
+            .map_or(self.block_size_limits.max_block_size(), |size| {
+                size.min(self.block_size_limits.max_block_size())
+            });
 
         let transactions_to_include = {
             let txn_queue = builder.txn_queue.read().await;
@@ -337,6 +661,7 @@ where
             }
             txn_queue
                 .iter()
+                .filter(|tx| !excluded.contains(&tx.commit))
                 .scan(0, |total_size, tx| {
                     let prev_size = *total_size;
                     *total_size += tx.min_block_size;
@@ -355,6 +680,11 @@ where
                 })
                 .collect::<Vec<_>>()
         };
+        if transactions_to_include.is_empty() && !should_prioritize_finalization {
+            // Everything in queue was already offered for other views
+            return Ok(None);
+        }
+        let first_included = transactions_to_include.first().map(|tx| tx.commit);
 
         let (payload, metadata) =
             match <Types::BlockPayload as BlockPayload<Types>>::from_transactions(
@@ -385,45 +715,22 @@ where
         // the sequencer indirectly, by observing that we passed some transactions
         // to `<Types::BlockPayload as BlockPayload<Types>>::from_transactions`, but
         // it returned an empty block.
-        // Thus we deduce that the first transaction in our queue is too big to *ever*
+        // Thus we deduce that the first transaction we tried to include is too big to *ever*
         // be included, because it alone goes over sequencer's block size limit.
         if truncated {
-            builder.txn_queue.write().await.pop_front();
+            if let Some(commit) = first_included {
+                builder
+                    .txn_queue
+                    .write()
+                    .await
+                    .prune(std::iter::once(&commit));
+            }
             if !should_prioritize_finalization {
                 return Ok(None);
             }
         }
 
-        let encoded_txns: Vec<u8> = payload.encode().to_vec();
-        let block_size: u64 = encoded_txns.len() as u64;
-        let offered_fee: u64 = self.base_fee * block_size;
-
-        // Get the number of nodes stored while processing the `claim_block_with_num_nodes` request
-        // or upon initialization.
-        let num_nodes = self.num_nodes.load(Ordering::Relaxed);
-
-        let fut = async move {
-            let join_handle = tokio::task::spawn_blocking(move || {
-                precompute_vid_commitment(&encoded_txns, num_nodes)
-            });
-            join_handle.await.unwrap()
-        };
-
-        info!(
-            builder_id = %builder.id(),
-            txn_count = actual_txn_count,
-            block_size,
-            "Built a block",
-        );
-
-        Ok(Some(BlockInfo {
-            block_payload: payload,
-            block_size,
-            metadata,
-            vid_data: WaitAndKeep::new(Box::pin(fut)),
-            offered_fee,
-            truncated,
-        }))
+        Ok(Some((payload, metadata, truncated)))
     }
 
     #[instrument(skip_all,
@@ -463,50 +770,107 @@ where
         };
 
         let Some(builder) = builder else {
-            if let Some(cached_block) = self.block_store.read().await.get_cached(&state_id) {
-                return Ok(vec![cached_block.signed_response(&self.builder_keys)?]);
-            } else {
-                return Err(Error::NotFound);
-            };
+            return self.cached_responses(&state_id).await;
         };
 
         let build_block_timeout = self
             .max_api_waiting_time
             .saturating_sub(start.elapsed())
             .div_f32(1.1);
-        match timeout(build_block_timeout, self.build_block(builder))
-            .await
-            .map_err(|_| Error::ApiTimeout)
+        match timeout(
+            build_block_timeout,
+            self.build_blocks(builder, state_id.parent_view),
+        )
+        .await
+        .map_err(|_| Error::ApiTimeout)
         {
+            // Success, but no blocks: we don't have transactions and aren't prioritizing finalization
+            Ok(Ok(blocks)) if blocks.is_empty() => Ok(vec![]),
             // Success
-            Ok(Ok(Some(info))) => {
-                let block_id = BlockId {
-                    hash: info.block_payload.builder_commitment(&info.metadata),
-                    view: state_id.parent_view,
-                };
-
-                let response = info.signed_response(&self.builder_keys)?;
+            Ok(Ok(blocks)) => {
+                let mut responses = Vec::with_capacity(blocks.len());
+                let mut stored = Vec::with_capacity(blocks.len());
+                for info in blocks {
+                    let block_id = BlockId {
+                        hash: info.block_payload.builder_commitment(&info.metadata),
+                        view: state_id.parent_view,
+                    };
+
+                    responses.push(info.signed_response(&self.builder_keys)?);
+                    stored.push((block_id, info));
+                }
 
                 {
                     let mut mutable_state = self.block_store.write().await;
-                    mutable_state.update(state_id, block_id, info);
+                    mutable_state.update(state_id, stored);
                 }
 
-                Ok(vec![response])
-            }
-            // Success, but no block: we don't have transactions and aren't prioritizing finalization
-            Ok(Ok(None)) => Ok(vec![]),
-            // Error building block, try to respond with a cached one as last-ditch attempt
-            Ok(Err(e)) | Err(e) => {
-                if let Some(cached_block) = self.block_store.read().await.get_cached(&state_id) {
-                    Ok(vec![cached_block.signed_response(&self.builder_keys)?])
-                } else {
-                    Err(e)
-                }
+                Ok(responses)
             }
+            // Error building blocks, try to respond with cached ones as last-ditch attempt
+            Ok(Err(e)) | Err(e) => match self.cached_responses(&state_id).await {
+                Err(Error::NotFound) => Err(e),
+                cached => cached,
+            },
+        }
+    }
+
+    /// Record transactions of `blocks` built for `state_id` as offered for its view,
+    /// the one the leader sent in its request, and their fees in the fee ledger under the same view.
+    /// Called once blocks are returned to the leader, so that blocks built for requests
+    /// that timed out neither exclude their transactions nor show up as offered fees.
+    async fn record_offered(
+        &self,
+        state_id: &BuilderStateId<Types>,
+        blocks: &[AvailableBlockInfo<Types>],
+    ) {
+        let offered: Vec<_> = {
+            let block_store = self.block_store.read().await;
+            blocks
+                .iter()
+                .filter_map(|info| {
+                    block_store.get_block(&BlockId {
+                        hash: info.block_hash.clone(),
+                        view: state_id.parent_view,
+                    })
+                })
+                .flat_map(|block| block.block_payload.transaction_commitments(&block.metadata))
+                .collect()
+        };
+        self.coordinator
+            .record_offered(state_id.parent_view, offered)
+            .await;
+
+        for info in blocks {
+            self.coordinator
+                .fee_ledger()
+                .record_offered(
+                    *state_id.parent_view,
+                    FeeSubject::Block(info.block_hash.clone()),
+                    info.offered_fee,
+                    self.builder_keys.0.clone(),
+                )
+                .await;
         }
     }
 
+    /// Signed responses for blocks previously built for `state_id`.
+    /// Returns [`Error::NotFound`] if there are none.
+    async fn cached_responses(
+        &self,
+        state_id: &BuilderStateId<Types>,
+    ) -> Result<Vec<AvailableBlockInfo<Types>>, Error<Types>> {
+        let block_store = self.block_store.read().await;
+        let cached_blocks = block_store.get_cached(state_id);
+        if cached_blocks.is_empty() {
+            return Err(Error::NotFound);
+        }
+        cached_blocks
+            .into_iter()
+            .map(|block| block.signed_response(&self.builder_keys))
+            .collect()
+    }
+
     #[instrument(skip_all,
         fields(block_id = %block_id)
     )]
@@ -528,7 +892,7 @@ where
                 .get_block(&block_id)
                 .ok_or(Error::NotFound)?;
 
-            block_info.vid_data.start();
+            block_info.vid_data.start(VidPriority::Claimed);
             (
                 block_info.block_payload.clone(),
                 block_info.metadata.clone(),
@@ -547,6 +911,26 @@ where
             )
             .map_err(Error::Signing)?;
 
+        let transaction_commitments = block_payload.transaction_commitments(&metadata);
+        if let Some(receipts) = &self.receipts {
+            receipts.record_claimed(
+                *block_id.view,
+                response_block_hash.clone(),
+                transaction_commitments.clone(),
+            );
+        }
+
+        // Other builder states shouldn't offer the same transactions
+        // until the leader's proposals for this block arrive. Claims are
+        // keyed by the view the leader sent in its request.
+        self.coordinator
+            .mark_tentatively_included(
+                block_id.view,
+                response_block_hash.clone(),
+                transaction_commitments,
+            )
+            .await;
+
         let block_data = AvailableBlockData::<Types> {
             block_payload,
             metadata,
@@ -589,7 +973,9 @@ where
             vid_data = block_info.vid_data.clone();
         };
 
-        let (vid_commitment, vid_precompute_data) = vid_data.resolve().await;
+        // Job is only cancelled if the block was pruned in the meantime
+        let (vid_commitment, vid_precompute_data) =
+            vid_data.resolve().await.ok_or(Error::NotFound)?;
 
         // sign over the vid commitment
         let signature_over_vid_commitment =
@@ -658,11 +1044,17 @@ where
 
         let available_blocks = timeout(
             self.max_api_waiting_time,
-            self.available_blocks_implementation(state_id),
+            self.available_blocks_implementation(state_id.clone()),
         )
         .await
         .map_err(|_| Error::<Types>::ApiTimeout)??;
 
+        if !available_blocks.is_empty() {
+            self.empty_block_policy
+                .on_block_served(Types::View::new(parent_view));
+        }
+        self.record_offered(&state_id, &available_blocks).await;
+
         Ok(available_blocks)
     }
 
@@ -674,6 +1066,7 @@ where
         sender: Types::SignatureKey,
         signature: &<<Types as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     ) -> Result<AvailableBlockData<Types>, BuildError> {
+        let _in_flight = self.shutdown.track();
         // verify the signature
         if !sender.validate(signature, block_hash.as_ref()) {
             warn!("Signature validation failed");
@@ -727,6 +1120,7 @@ where
         sender: Types::SignatureKey,
         signature: &<<Types as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     ) -> Result<AvailableBlockHeaderInput<Types>, BuildError> {
+        let _in_flight = self.shutdown.track();
         let start = Instant::now();
         // verify the signature
         if !sender.validate(signature, block_hash.as_ref()) {
@@ -759,6 +1153,11 @@ where
             self.block_size_limits.try_increment_block_size(truncated);
         }
 
+        self.coordinator
+            .fee_ledger()
+            .mark_claimed(view_number, block_hash)
+            .await;
+
         Ok(info)
     }
 
@@ -783,16 +1182,7 @@ where
         &self,
         txns: Vec<<Types as NodeType>::Transaction>,
     ) -> Result<Vec<Commitment<<Types as NodeType>::Transaction>>, BuildError> {
-        txns.into_iter()
-            .map(|txn| ReceivedTransaction::new(txn, TransactionSource::Private))
-            .map(|txn| async {
-                let commit = txn.commit;
-                self.0.handle_transaction(txn).await?;
-                Ok(commit)
-            })
-            .collect::<FuturesOrdered<_>>()
-            .try_collect()
-            .await
+        Ok(self.0.submit_txns_from(ANONYMOUS_CLIENT, txns).await?)
     }
 
     async fn txn_status(
@@ -803,6 +1193,50 @@ where
     }
 }
 
+impl<Types: NodeType> FeeLedgerDataSource<Types> for ProxyGlobalState<Types> {
+    fn fee_ledger(&self) -> &FeeLedger<Types> {
+        self.coordinator.fee_ledger()
+    }
+}
+
+impl<Types: NodeType> InclusionReceiptDataSource<Types> for ProxyGlobalState<Types> {
+    fn inclusion_receipt(
+        &self,
+        transaction: &Commitment<Types::Transaction>,
+    ) -> Result<Option<InclusionReceipt<Types>>, Error<Types>> {
+        match &self.receipts {
+            Some(receipts) => receipts.receipt(transaction, &self.builder_keys),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl<Types: NodeType> PrivateMempoolDataSource<Types> for ProxyGlobalState<Types> {
+    async fn submit_private(
+        &self,
+        client: &str,
+        txns: Vec<Types::Transaction>,
+    ) -> Result<Vec<Commitment<Types::Transaction>>, Error<Types>> {
+        self.0.submit_txns_from(client, txns).await
+    }
+
+    fn transaction_status(&self, txn_hash: &Commitment<Types::Transaction>) -> TransactionStatus {
+        self.coordinator.tx_status(txn_hash)
+    }
+
+    fn submit_limiter_metrics(&self) -> SubmitLimiterMetrics {
+        self.0.submit_limiter_metrics()
+    }
+}
+
+#[async_trait]
+impl<Types: NodeType> InclusionEstimateDataSource for ProxyGlobalState<Types> {
+    async fn estimate_inclusion(&self, transaction_size: u64) -> InclusionEstimate {
+        self.0.estimate_inclusion(transaction_size).await
+    }
+}
+
 #[async_trait]
 impl<Types: NodeType> ReadState for ProxyGlobalState<Types> {
     type State = ProxyGlobalState<Types>;
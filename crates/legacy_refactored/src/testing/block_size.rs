@@ -7,14 +7,13 @@ use hotshot_types::data::ViewNumber;
 use hotshot_types::traits::node_implementation::ConsensusTime;
 use hotshot_types::vid::VidCommitment;
 use marketplace_builder_shared::block::{BlockId, BuilderStateId};
+use marketplace_builder_shared::block_size_limits::BlockSizeLimits;
 use marketplace_builder_shared::testing::consensus::SimulatedChainState;
 use marketplace_builder_shared::testing::constants::TEST_NUM_NODES_IN_VID_COMPUTATION;
 use tracing_test::traced_test;
 
-use crate::block_size_limits::BlockSizeLimits;
 use crate::service::{BuilderConfig, GlobalState};
 use crate::testing::TestServiceWrapper;
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -46,13 +45,9 @@ async fn block_size_increment() {
     );
 
     // Manually set the limits
-    global_state.block_size_limits.mutable_state.store(
-        crate::block_size_limits::MutableState {
-            max_block_size: BlockSizeLimits::MAX_BLOCK_SIZE_FLOOR,
-            last_block_size_increment: coarsetime::Instant::now().as_ticks(),
-        },
-        Ordering::Relaxed,
-    );
+    global_state
+        .block_size_limits
+        .set_max_block_size(BlockSizeLimits::MAX_BLOCK_SIZE_FLOOR);
 
     let (event_stream_sender, event_stream) = broadcast(1024);
     let test_service =
@@ -69,11 +64,7 @@ async fn block_size_increment() {
     for round in 0..num_rounds {
         // We should still be climbing
         assert_ne!(
-            global_state
-                .block_size_limits
-                .mutable_state
-                .load(Ordering::Relaxed)
-                .max_block_size,
+            global_state.block_size_limits.max_block_size(),
             PROTOCOL_MAX_BLOCK_SIZE,
             "On round {round}/{num_rounds} we shouldn't be back to PROTOCOL_MAX_BLOCK_SIZE yet"
         );
@@ -108,11 +99,7 @@ async fn block_size_increment() {
 
     // We should've returned to protocol max block size
     assert_eq!(
-        global_state
-            .block_size_limits
-            .mutable_state
-            .load(Ordering::Relaxed)
-            .max_block_size,
+        global_state.block_size_limits.max_block_size(),
         PROTOCOL_MAX_BLOCK_SIZE
     )
 }
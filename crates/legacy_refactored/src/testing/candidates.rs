@@ -0,0 +1,214 @@
+use async_broadcast::broadcast;
+use committable::Committable;
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_example_types::state_types::TestInstanceState;
+use hotshot_types::data::ViewNumber;
+use hotshot_types::traits::node_implementation::ConsensusTime;
+use marketplace_builder_shared::block::BlockId;
+use marketplace_builder_shared::coordinator::offers::OfferPolicy;
+use marketplace_builder_shared::coordinator::BuilderStateLookup;
+use marketplace_builder_shared::testing::consensus::SimulatedChainState;
+use marketplace_builder_shared::testing::constants::{
+    TEST_BASE_FEE, TEST_NUM_NODES_IN_VID_COMPUTATION, TEST_PROTOCOL_MAX_BLOCK_SIZE,
+};
+use tracing_test::traced_test;
+
+use crate::service::{BlockCandidate, BuilderConfig, GlobalState};
+use crate::testing::TestServiceWrapper;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builder configured with several block candidates should offer and store each of them
+#[tokio::test]
+#[traced_test]
+async fn test_multiple_candidates() {
+    const TX_SIZE: usize = 100;
+    const NUM_TXNS: usize = 10;
+
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            block_candidates: vec![
+                BlockCandidate {
+                    max_block_size: Some(TX_SIZE as u64 * 3),
+                    base_fee: None,
+                },
+                BlockCandidate {
+                    max_block_size: None,
+                    base_fee: Some(TEST_BASE_FEE * 2),
+                },
+                // Same contents as the first candidate, shouldn't be offered again
+                BlockCandidate {
+                    max_block_size: Some(TX_SIZE as u64 * 3),
+                    base_fee: Some(TEST_BASE_FEE * 3),
+                },
+            ],
+            ..BuilderConfig::test()
+        },
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    test_service
+        .submit_transactions_private(
+            (0..NUM_TXNS)
+                .map(|i| TestTransaction::new(vec![i as u8; TX_SIZE]))
+                .collect(),
+        )
+        .await
+        .unwrap();
+    let state_id = chain_state.simulate_consensus_round(None).await;
+
+    let blocks = test_service.get_available_blocks(&state_id).await.unwrap();
+    let [small, full] = blocks.as_slice() else {
+        panic!("Expected two candidates, got {}", blocks.len());
+    };
+    assert!(small.block_size < full.block_size);
+    assert_eq!(small.offered_fee, TEST_BASE_FEE * small.block_size);
+    assert_eq!(full.offered_fee, TEST_BASE_FEE * 2 * full.block_size);
+
+    assert_eq!(
+        global_state
+            .block_store
+            .read()
+            .await
+            .get_cached(&state_id)
+            .len(),
+        2
+    );
+
+    // Either candidate can be claimed
+    for block in [small, full] {
+        test_service
+            .claim_block_header_input(&BlockId {
+                hash: block.block_hash.clone(),
+                view: state_id.parent_view,
+            })
+            .await
+            .unwrap();
+    }
+}
+
+/// Offers should be recorded for the view sent in the request, once blocks are returned
+#[tokio::test]
+#[traced_test]
+async fn test_offers_recorded_once_returned() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            offer_policy: OfferPolicy::ExcludeUntilDecidedOrExpired {
+                ttl: Duration::from_secs(60),
+            },
+            ..BuilderConfig::test()
+        },
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    let transaction = TestTransaction::new(vec![1; 8]);
+    test_service
+        .submit_transactions_private(vec![transaction.clone()])
+        .await
+        .unwrap();
+    let state_id = chain_state.simulate_consensus_round(None).await;
+    let coordinator = &global_state.coordinator;
+    let other_view = ViewNumber::new(*state_id.parent_view + 1);
+
+    // Building blocks alone, e.g. for a request that then times out, doesn't offer them
+    let BuilderStateLookup::Found(builder_state) =
+        coordinator.lookup_builder_state(&state_id).await
+    else {
+        panic!("Builder state not found");
+    };
+    let blocks = global_state
+        .build_blocks(builder_state, state_id.parent_view)
+        .await
+        .unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert!(coordinator.excluded_offers(other_view).await.is_empty());
+
+    let blocks = test_service.get_available_blocks(&state_id).await.unwrap();
+    assert_eq!(blocks.len(), 1);
+
+    assert!(coordinator
+        .excluded_offers(other_view)
+        .await
+        .contains(&transaction.commit()));
+    assert!(coordinator
+        .excluded_offers(state_id.parent_view)
+        .await
+        .is_empty());
+}
+
+/// Blocks offered earlier for the same state should stay claimable after the
+/// builder state offers different ones, as the proposer might've picked one of them
+#[tokio::test]
+#[traced_test]
+async fn test_superseded_candidate_claimable() {
+    let global_state = GlobalState::new(
+        BuilderConfig::test(),
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    test_service
+        .submit_transactions_private(vec![TestTransaction::new(vec![1; 8])])
+        .await
+        .unwrap();
+    let state_id = chain_state.simulate_consensus_round(None).await;
+
+    let blocks = test_service.get_available_blocks(&state_id).await.unwrap();
+    let [superseded] = blocks.as_slice() else {
+        panic!("Expected a single block, got {}", blocks.len());
+    };
+
+    // New transaction makes the builder state offer a different block
+    test_service
+        .submit_transactions_private(vec![TestTransaction::new(vec![2; 8])])
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(1), async {
+        loop {
+            let blocks = test_service.get_available_blocks(&state_id).await.unwrap();
+            if blocks
+                .iter()
+                .all(|block| block.block_hash != superseded.block_hash)
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Builder state didn't offer a new block");
+
+    test_service
+        .claim_block_header_input(&BlockId {
+            hash: superseded.block_hash.clone(),
+            view: state_id.parent_view,
+        })
+        .await
+        .unwrap();
+}
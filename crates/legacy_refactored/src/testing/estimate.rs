@@ -0,0 +1,102 @@
+use async_broadcast::broadcast;
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_example_types::state_types::TestInstanceState;
+use hotshot_types::traits::block_contents::Transaction;
+use marketplace_builder_shared::testing::constants::{
+    TEST_BASE_FEE, TEST_NUM_NODES_IN_VID_COMPUTATION, TEST_PROTOCOL_MAX_BLOCK_SIZE,
+};
+use tracing_test::traced_test;
+
+use crate::service::{BuilderConfig, GlobalState};
+use crate::testing::TestServiceWrapper;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Inclusion estimate should account for transactions queued ahead
+#[tokio::test]
+#[traced_test]
+async fn test_inclusion_estimate() {
+    const TX_SIZE: usize = 100;
+    const NUM_TXNS: usize = 10;
+
+    let global_state = GlobalState::new(
+        BuilderConfig::test(),
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let estimate = global_state.estimate_inclusion(TX_SIZE as u64).await;
+    assert_eq!(estimate.queue_len, 0);
+    assert_eq!(estimate.expected_views, Some(1));
+    assert_eq!(estimate.estimated_fee, TEST_BASE_FEE * TX_SIZE as u64);
+    assert_eq!(estimate.latency, None);
+
+    let transactions: Vec<_> = (0..NUM_TXNS)
+        .map(|i| TestTransaction::new(vec![i as u8; TX_SIZE]))
+        .collect();
+    let queue_bytes: u64 = transactions.iter().map(|tx| tx.minimum_block_size()).sum();
+    test_service
+        .submit_transactions_private(transactions)
+        .await
+        .unwrap();
+
+    let estimate = global_state.estimate_inclusion(TX_SIZE as u64).await;
+    assert_eq!(estimate.queue_len, NUM_TXNS);
+    assert_eq!(estimate.queue_bytes, queue_bytes);
+    assert_eq!(
+        estimate.expected_views,
+        Some((queue_bytes + TX_SIZE as u64).div_ceil(estimate.max_block_size))
+    );
+
+    // Transactions that don't fit in a block won't be included
+    let estimate = global_state
+        .estimate_inclusion(estimate.max_block_size + 1)
+        .await;
+    assert_eq!(estimate.expected_views, None);
+}
+
+/// Inclusion estimate inputs should be reused within the refresh period
+#[tokio::test]
+#[traced_test]
+async fn test_inclusion_estimate_cached() {
+    const TX_SIZE: usize = 100;
+    const NUM_TXNS: usize = 10;
+
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            estimate_refresh_period: Duration::from_secs(60),
+            ..BuilderConfig::test()
+        },
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let estimate = global_state.estimate_inclusion(TX_SIZE as u64).await;
+    assert_eq!(estimate.queue_len, 0);
+
+    let transactions: Vec<_> = (0..NUM_TXNS)
+        .map(|i| TestTransaction::new(vec![i as u8; TX_SIZE]))
+        .collect();
+    test_service
+        .submit_transactions_private(transactions)
+        .await
+        .unwrap();
+
+    // Queue isn't scanned again until the refresh period passes,
+    // but the rest of the estimate is still specific to the request
+    let estimate = global_state.estimate_inclusion(2 * TX_SIZE as u64).await;
+    assert_eq!(estimate.queue_len, 0);
+    assert_eq!(estimate.estimated_fee, TEST_BASE_FEE * 2 * TX_SIZE as u64);
+}
@@ -0,0 +1,120 @@
+use async_broadcast::broadcast;
+use hotshot::types::{Event, EventType};
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_example_types::node_types::{TestTypes, TestVersions};
+use hotshot_example_types::state_types::TestInstanceState;
+use hotshot_types::data::ViewNumber;
+use hotshot_types::simple_certificate::QuorumCertificate;
+use hotshot_types::traits::node_implementation::ConsensusTime;
+use marketplace_builder_shared::block::BlockId;
+use marketplace_builder_shared::fee_ledger::{FeeStatus, FeeSubject};
+use marketplace_builder_shared::testing::consensus::SimulatedChainState;
+use marketplace_builder_shared::testing::constants::{
+    TEST_NUM_NODES_IN_VID_COMPUTATION, TEST_PROTOCOL_MAX_BLOCK_SIZE,
+};
+use marketplace_builder_shared::testing::mock;
+use tracing_test::traced_test;
+
+use crate::service::{BuilderConfig, GlobalState};
+use crate::testing::TestServiceWrapper;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Status of the single fee record for `view`, which should be for `subject`
+async fn fee_status(
+    global_state: &GlobalState<TestTypes>,
+    view: u64,
+    subject: &FeeSubject<TestTypes>,
+) -> FeeStatus {
+    let records = global_state
+        .coordinator
+        .fee_ledger()
+        .report(view..=view)
+        .await;
+    let [record] = records.as_slice() else {
+        panic!("Expected a single fee record, got {}", records.len());
+    };
+    assert_eq!(&record.subject, subject);
+    record.status
+}
+
+/// Block fees should be recorded for the view the leader requested the block for,
+/// and go from offered to claimed to charged as the block is claimed and then
+/// decided in the view it's proposed in
+#[tokio::test]
+#[traced_test]
+async fn test_block_fee_charged() {
+    const NUM_TXNS: usize = 5;
+
+    let global_state = GlobalState::new(
+        BuilderConfig::test(),
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let mut chain_state = SimulatedChainState::new(event_stream_sender.clone());
+
+    let transactions: Vec<_> = (0..NUM_TXNS)
+        .map(|i| TestTransaction::new(vec![i as u8; 8]))
+        .collect();
+    test_service
+        .submit_transactions_private(transactions.clone())
+        .await
+        .unwrap();
+    let state_id = chain_state.simulate_consensus_round(None).await;
+    let requested_view = *state_id.parent_view;
+
+    let blocks = test_service.get_available_blocks(&state_id).await.unwrap();
+    let [block] = blocks.as_slice() else {
+        panic!("Expected a single block, got {}", blocks.len());
+    };
+    let subject = FeeSubject::Block(block.block_hash.clone());
+    assert_eq!(
+        fee_status(&global_state, requested_view, &subject).await,
+        FeeStatus::Offered
+    );
+
+    test_service
+        .claim_block_header_input(&BlockId {
+            hash: block.block_hash.clone(),
+            view: state_id.parent_view,
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        fee_status(&global_state, requested_view, &subject).await,
+        FeeStatus::Claimed
+    );
+
+    // Leader proposes the block, and it's decided
+    let decided_view = requested_view + 1;
+    let leaf_chain = mock::decide_leaf_chain_with_transactions(decided_view, transactions).await;
+    let qc = QuorumCertificate::genesis::<TestVersions>(&Default::default(), &Default::default())
+        .await
+        .to_qc2();
+    event_stream_sender
+        .broadcast(Event {
+            view_number: ViewNumber::new(decided_view),
+            event: EventType::Decide {
+                leaf_chain,
+                qc: Arc::new(qc),
+                block_size: None,
+            },
+        })
+        .await
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while fee_status(&global_state, requested_view, &subject).await != FeeStatus::Charged {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Block fee wasn't charged on decide");
+}
@@ -8,9 +8,12 @@ use marketplace_builder_shared::testing::constants::{
     TEST_NUM_NODES_IN_VID_COMPUTATION, TEST_PROTOCOL_MAX_BLOCK_SIZE,
 };
 
-use crate::service::{BuilderConfig, GlobalState, ALLOW_EMPTY_BLOCK_PERIOD};
+use crate::empty_block::{Always, EmptyBlockPolicy, IdleTimeout, Never, ALLOW_EMPTY_BLOCK_PERIOD};
+use crate::service::{BuilderConfig, GlobalState};
 use crate::testing::TestServiceWrapper;
+use hotshot_example_types::node_types::TestTypes;
 use std::sync::Arc;
+use std::time::Duration;
 
 // How many times consensus will re-try getting available blocks
 const NUM_RETRIES: usize = 5;
@@ -174,3 +177,120 @@ async fn test_eager_block_rate() {
         }
     }
 }
+
+/// Start a builder with given empty block policy, returning the test service
+/// and chain state to simulate consensus rounds with
+async fn setup(
+    empty_block_policy: Arc<dyn EmptyBlockPolicy<TestTypes>>,
+) -> (TestServiceWrapper, SimulatedChainState) {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            empty_block_policy,
+            ..BuilderConfig::test()
+        },
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    global_state.start_event_loop(event_stream);
+
+    (test_service, SimulatedChainState::new(event_stream_sender))
+}
+
+/// With [`Never`] policy, builder doesn't propose empty blocks
+/// even if a proposal included transactions recently
+#[tokio::test]
+#[traced_test]
+async fn test_never_policy() {
+    let (test_service, mut chain_state) = setup(Arc::new(Never)).await;
+
+    for _ in 0..=ALLOW_EMPTY_BLOCK_PERIOD {
+        let builder_state_id = chain_state
+            .simulate_consensus_round(Some(vec![TestTransaction::default()]))
+            .await;
+
+        for _ in 0..NUM_RETRIES {
+            let available_blocks = test_service
+                .get_available_blocks(&builder_state_id)
+                .await
+                .unwrap();
+            assert!(
+                available_blocks.is_empty(),
+                "Builder shouldn't ever be building empty blocks"
+            );
+        }
+    }
+}
+
+/// With [`Always`] policy, builder proposes empty blocks
+/// even without any transactions included recently
+#[tokio::test]
+#[traced_test]
+async fn test_always_policy() {
+    let (test_service, mut chain_state) = setup(Arc::new(Always)).await;
+
+    for _ in 0..=ALLOW_EMPTY_BLOCK_PERIOD {
+        let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+        for _ in 0..NUM_RETRIES {
+            let available_blocks = test_service
+                .get_available_blocks(&builder_state_id)
+                .await
+                .unwrap();
+            assert_eq!(
+                available_blocks.first().unwrap().block_size,
+                0,
+                "Builder should always be building empty blocks"
+            );
+        }
+    }
+}
+
+/// With [`IdleTimeout`] policy, builder proposes an empty block
+/// only once no blocks were served for the timeout
+#[tokio::test]
+#[traced_test]
+async fn test_idle_timeout_policy() {
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    let (test_service, mut chain_state) = setup(Arc::new(IdleTimeout::new(IDLE_TIMEOUT))).await;
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    // Builder was just started
+    let available_blocks = test_service
+        .get_available_blocks(&builder_state_id)
+        .await
+        .unwrap();
+    assert!(
+        available_blocks.is_empty(),
+        "Builder shouldn't be building empty blocks before the timeout"
+    );
+
+    for _ in 0..2 {
+        tokio::time::sleep(IDLE_TIMEOUT).await;
+
+        let available_blocks = test_service
+            .get_available_blocks(&builder_state_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            available_blocks.first().unwrap().block_size,
+            0,
+            "Builder should be building an empty block: no blocks were served for a while"
+        );
+
+        // We've just served a block
+        let available_blocks = test_service
+            .get_available_blocks(&builder_state_id)
+            .await
+            .unwrap();
+        assert!(
+            available_blocks.is_empty(),
+            "Builder shouldn't be building empty blocks right after serving one"
+        );
+    }
+}
@@ -30,8 +30,16 @@ use crate::service::{GlobalState, ProxyGlobalState};
 
 mod basic;
 mod block_size;
+mod candidates;
+mod estimate;
+mod fee_ledger;
 mod finalization;
 mod integration;
+mod receipt;
+mod shutdown;
+mod submit_limit;
+mod tentative;
+mod vid_pool;
 
 const MOCK_LEADER_KEYS: LazyCell<BuilderKeys<TestTypes>> =
     LazyCell::new(|| BLSPubKey::generated_from_seed_indexed([0; 32], 0));
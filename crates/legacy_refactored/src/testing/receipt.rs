@@ -0,0 +1,72 @@
+use async_broadcast::broadcast;
+use committable::Committable;
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_example_types::state_types::TestInstanceState;
+use marketplace_builder_shared::receipt::InclusionReceiptDataSource;
+use marketplace_builder_shared::testing::consensus::SimulatedChainState;
+use marketplace_builder_shared::testing::constants::{
+    TEST_NUM_NODES_IN_VID_COMPUTATION, TEST_PROTOCOL_MAX_BLOCK_SIZE,
+};
+use tracing_test::traced_test;
+
+use crate::service::{BuilderConfig, GlobalState};
+use crate::testing::TestServiceWrapper;
+use std::sync::Arc;
+
+/// Transactions of claimed blocks should get signed receipts if enabled
+#[tokio::test]
+#[traced_test]
+async fn test_inclusion_receipts() {
+    const NUM_TXNS: usize = 5;
+
+    for inclusion_receipts in [true, false] {
+        let global_state = GlobalState::new(
+            BuilderConfig {
+                inclusion_receipts,
+                ..BuilderConfig::test()
+            },
+            TestInstanceState::default(),
+            TEST_PROTOCOL_MAX_BLOCK_SIZE,
+            TEST_NUM_NODES_IN_VID_COMPUTATION,
+        );
+
+        let (event_stream_sender, event_stream) = broadcast(1024);
+        let test_service =
+            TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+        Arc::clone(&global_state).start_event_loop(event_stream);
+
+        let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+        let transactions: Vec<_> = (0..NUM_TXNS)
+            .map(|i| TestTransaction::new(vec![i as u8; 8]))
+            .collect();
+        test_service
+            .submit_transactions_private(transactions.clone())
+            .await
+            .unwrap();
+        let state_id = chain_state.simulate_consensus_round(None).await;
+
+        // No receipts until the block is claimed
+        let proxy = &test_service.proxy_global_state;
+        assert!(proxy
+            .inclusion_receipt(&transactions[0].commit())
+            .unwrap()
+            .is_none());
+
+        assert_eq!(test_service.get_transactions(&state_id).await, transactions);
+
+        for (position, transaction) in transactions.iter().enumerate() {
+            let receipt = proxy.inclusion_receipt(&transaction.commit()).unwrap();
+            if !inclusion_receipts {
+                assert!(receipt.is_none());
+                continue;
+            }
+            let receipt = receipt.unwrap();
+            assert!(receipt.verify());
+            assert_eq!(receipt.body.builder, global_state.builder_keys.0);
+            // Receipts carry the view the leader sent when claiming the block
+            assert_eq!(receipt.body.view, *state_id.parent_view);
+            assert_eq!(receipt.body.position, position as u64);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_broadcast::broadcast;
+use committable::Committable;
+use hotshot::types::{Event, EventType};
+use hotshot_builder_api::v0_1::builder::TransactionStatus;
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_example_types::state_types::TestInstanceState;
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use marketplace_builder_shared::error::Error;
+use marketplace_builder_shared::shutdown::ShutdownPhase;
+use marketplace_builder_shared::testing::constants::{
+    TEST_NUM_NODES_IN_VID_COMPUTATION, TEST_PROTOCOL_MAX_BLOCK_SIZE,
+};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+use tracing_test::traced_test;
+
+use crate::service::{BuilderConfig, GlobalState};
+use crate::testing::{assert_eq_generic_err, TestServiceWrapper};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Shutdown should reject new private submissions, wait for in-flight requests,
+/// export pending transactions and stop the event loop
+#[tokio::test]
+#[traced_test]
+async fn test_graceful_shutdown() {
+    const NUM_TXNS: usize = 5;
+
+    let global_state = GlobalState::new(
+        BuilderConfig::test(),
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    let event_loop = Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let transactions: Vec<_> = (0..NUM_TXNS)
+        .map(|i| TestTransaction::new(vec![i as u8; 8]))
+        .collect();
+    test_service
+        .submit_transactions_private(transactions.clone())
+        .await
+        .unwrap();
+
+    let (signal_sender, signal) = oneshot::channel::<()>();
+    let shutdown = Arc::clone(&global_state).shutdown_on(async move {
+        let _ = signal.await;
+    });
+
+    // An in-flight request holds up the shutdown
+    let in_flight = global_state.shutdown_handle().track();
+    signal_sender.send(()).unwrap();
+    timeout(
+        TIMEOUT,
+        global_state
+            .shutdown_handle()
+            .reached(ShutdownPhase::Draining),
+    )
+    .await
+    .unwrap();
+
+    let err = test_service
+        .submit_transactions_private(vec![TestTransaction::new(vec![0xff; 8])])
+        .await
+        .unwrap_err();
+    assert_eq_generic_err(err, Error::ShuttingDown);
+
+    // Public transactions are still accepted and exported
+    let public = TestTransaction::new(vec![0xfe; 8]);
+    event_stream_sender
+        .broadcast(Event {
+            view_number: ViewNumber::genesis(),
+            event: EventType::Transactions {
+                transactions: vec![public.clone()],
+            },
+        })
+        .await
+        .unwrap();
+    timeout(TIMEOUT, async {
+        while !matches!(
+            global_state.coordinator.tx_status(&public.commit()),
+            TransactionStatus::Pending
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Public transaction wasn't accepted while shutting down");
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!shutdown.is_finished());
+    assert_eq!(
+        global_state.shutdown_handle().phase(),
+        ShutdownPhase::Draining
+    );
+
+    drop(in_flight);
+    let pending = timeout(TIMEOUT, shutdown).await.unwrap().unwrap();
+    assert_eq!(
+        pending,
+        transactions.into_iter().chain([public]).collect::<Vec<_>>()
+    );
+
+    timeout(TIMEOUT, event_loop)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_broadcast::broadcast;
+use hotshot_builder_api::v0_1::builder::BuildError;
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_example_types::state_types::TestInstanceState;
+use marketplace_builder_shared::rate_limit::RateLimit;
+use marketplace_builder_shared::submit_limit::{SubmitLimiterMetrics, SubmitLimits};
+use marketplace_builder_shared::testing::constants::{
+    TEST_NUM_NODES_IN_VID_COMPUTATION, TEST_PROTOCOL_MAX_BLOCK_SIZE,
+};
+use tracing_test::traced_test;
+
+use crate::service::{BuilderConfig, GlobalState};
+use crate::testing::TestServiceWrapper;
+
+fn transactions(first: u8, num: u8) -> Vec<TestTransaction> {
+    (first..first + num)
+        .map(|i| TestTransaction::new(vec![i; 40]))
+        .collect()
+}
+
+fn assert_rejected(result: Result<impl std::fmt::Debug, BuildError>, expected: &str) {
+    let Err(BuildError::Error(message)) = result else {
+        panic!("Submission should be rejected");
+    };
+    assert!(message.contains(expected), "{message}");
+}
+
+/// Private submissions should be limited in bytes per client and in size of a single submission
+#[tokio::test]
+#[traced_test]
+async fn test_submit_limits() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            submit_limits: SubmitLimits {
+                bytes: Some(RateLimit {
+                    burst: 100,
+                    replenish_period: Duration::from_secs(3600),
+                }),
+                max_body_size: Some(100),
+                ..Default::default()
+            },
+            ..BuilderConfig::test()
+        },
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    assert_rejected(
+        test_service
+            .submit_transactions_private(transactions(0, 3))
+            .await,
+        "Submission too big",
+    );
+
+    test_service
+        .submit_transactions_private(transactions(0, 2))
+        .await
+        .unwrap();
+    assert_rejected(
+        test_service
+            .submit_transactions_private(transactions(2, 1))
+            .await,
+        "Rate limit exceeded",
+    );
+
+    // Other clients have their own allowance
+    global_state
+        .submit_txns_from("client", transactions(3, 2))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        global_state.submit_limiter_metrics(),
+        SubmitLimiterMetrics {
+            tracked_clients: 2,
+            accepted: 2,
+            rate_limited: 1,
+            oversized: 1,
+        }
+    );
+}
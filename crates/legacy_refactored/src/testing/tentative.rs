@@ -0,0 +1,171 @@
+use async_broadcast::{broadcast, Sender};
+use hotshot::types::{Event, EventType};
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_example_types::state_types::TestInstanceState;
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use marketplace_builder_shared::coordinator::BuilderStateLookup;
+use marketplace_builder_shared::testing::consensus::SimulatedChainState;
+use marketplace_builder_shared::testing::constants::{
+    TEST_NUM_NODES_IN_VID_COMPUTATION, TEST_PROTOCOL_MAX_BLOCK_SIZE,
+};
+use tracing_test::traced_test;
+
+use crate::empty_block::Never;
+use crate::service::{BuilderConfig, GlobalState};
+use crate::testing::TestServiceWrapper;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Report `view` as timed out
+async fn time_out(event_stream_sender: &Sender<Event<TestTypes>>, view: ViewNumber) {
+    event_stream_sender
+        .broadcast(Event {
+            view_number: view,
+            event: EventType::ViewTimeout { view_number: view },
+        })
+        .await
+        .unwrap();
+}
+
+/// Wait until no transactions are tentatively included for blocks built for `view`
+async fn wait_for_cleared(global_state: &GlobalState<TestTypes>, view: ViewNumber) {
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while !global_state
+            .coordinator
+            .tentatively_included(view)
+            .await
+            .is_empty()
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Tentative inclusions weren't cleared on view timeout");
+}
+
+/// Transactions of a claimed block shouldn't be packed into blocks
+/// for other views until the claimed view times out
+#[tokio::test]
+#[traced_test]
+async fn test_tentatively_included() {
+    const NUM_TXNS: usize = 5;
+
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            empty_block_policy: Arc::new(Never),
+            ..BuilderConfig::test()
+        },
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let mut chain_state = SimulatedChainState::new(event_stream_sender.clone());
+
+    let transactions: Vec<_> = (0..NUM_TXNS)
+        .map(|i| TestTransaction::new(vec![i as u8; 8]))
+        .collect();
+    test_service
+        .submit_transactions_private(transactions.clone())
+        .await
+        .unwrap();
+    let state_id = chain_state.simulate_consensus_round(None).await;
+
+    // Claim the block, as a leader would
+    assert_eq!(test_service.get_transactions(&state_id).await, transactions);
+
+    let BuilderStateLookup::Found(builder_state) = global_state
+        .coordinator
+        .lookup_builder_state(&state_id)
+        .await
+    else {
+        panic!("Builder state not found");
+    };
+    // Claims are keyed by the view the leader sends in its requests, the parent's
+    let claimed_view = state_id.parent_view;
+    let sibling_view = ViewNumber::new(*claimed_view + 1);
+
+    // The same view is still offered the claimed transactions
+    let blocks = global_state
+        .build_blocks(Arc::clone(&builder_state), claimed_view)
+        .await
+        .unwrap();
+    assert_eq!(blocks.len(), 1);
+
+    // Other views skip them
+    let blocks = global_state
+        .build_blocks(Arc::clone(&builder_state), sibling_view)
+        .await
+        .unwrap();
+    assert!(blocks.is_empty());
+
+    // Once the view the block would've been proposed in times out, they're offered again
+    time_out(&event_stream_sender, sibling_view).await;
+    wait_for_cleared(&global_state, sibling_view).await;
+
+    let blocks = global_state
+        .build_blocks(builder_state, sibling_view)
+        .await
+        .unwrap();
+    let [block] = blocks.as_slice() else {
+        panic!("Expected a single block, got {}", blocks.len());
+    };
+    assert_eq!(block.block_payload.transactions, transactions);
+}
+
+/// After two consecutive timeouts, the next leader builds on the same parent
+/// as the failed one did. Its claim should be cleared by the second timeout.
+#[tokio::test]
+#[traced_test]
+async fn test_tentatively_included_consecutive_timeouts() {
+    const NUM_TXNS: usize = 5;
+
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            empty_block_policy: Arc::new(Never),
+            ..BuilderConfig::test()
+        },
+        TestInstanceState::default(),
+        TEST_PROTOCOL_MAX_BLOCK_SIZE,
+        TEST_NUM_NODES_IN_VID_COMPUTATION,
+    );
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let test_service =
+        TestServiceWrapper::new(Arc::clone(&global_state), event_stream_sender.clone()).await;
+    Arc::clone(&global_state).start_event_loop(event_stream);
+
+    let mut chain_state = SimulatedChainState::new(event_stream_sender.clone());
+
+    let transactions: Vec<_> = (0..NUM_TXNS)
+        .map(|i| TestTransaction::new(vec![i as u8; 8]))
+        .collect();
+    test_service
+        .submit_transactions_private(transactions.clone())
+        .await
+        .unwrap();
+    let state_id = chain_state.simulate_consensus_round(None).await;
+    let other_view = ViewNumber::new(*state_id.parent_view + 3);
+
+    // Leaders of the next two views both build on the same parent and time out
+    for timed_out_view in [*state_id.parent_view + 1, *state_id.parent_view + 2] {
+        assert_eq!(test_service.get_transactions(&state_id).await, transactions);
+        assert_eq!(
+            global_state
+                .coordinator
+                .tentatively_included(other_view)
+                .await
+                .len(),
+            NUM_TXNS
+        );
+
+        time_out(&event_stream_sender, ViewNumber::new(timed_out_view)).await;
+        wait_for_cleared(&global_state, other_view).await;
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hotshot_types::traits::block_contents::precompute_vid_commitment;
+use marketplace_builder_shared::testing::constants::TEST_NUM_NODES_IN_VID_COMPUTATION;
+
+use crate::vid_pool::{VidLatencyCallback, VidPriority, VidWorkerPool};
+
+fn payload(byte: u8) -> Vec<u8> {
+    vec![byte; 1024]
+}
+
+#[tokio::test]
+async fn test_jobs_complete_within_limit() {
+    const NUM_JOBS: u8 = 8;
+
+    let pool = VidWorkerPool::new(2);
+    let jobs: Vec<_> = (0..NUM_JOBS)
+        .map(|i| pool.job(payload(i), TEST_NUM_NODES_IN_VID_COMPUTATION))
+        .collect();
+
+    // Nothing runs until jobs are started
+    let metrics = pool.metrics();
+    assert_eq!(metrics.idle, NUM_JOBS as usize);
+    assert_eq!(metrics.running + metrics.queued, 0);
+
+    for job in &jobs {
+        job.start(VidPriority::Claimed);
+        assert!(pool.metrics().running <= pool.max_concurrency());
+    }
+
+    for (i, job) in jobs.into_iter().enumerate() {
+        let (commitment, _) = job.resolve().await.unwrap();
+        let (expected, _) =
+            precompute_vid_commitment(&payload(i as u8), TEST_NUM_NODES_IN_VID_COMPUTATION);
+        assert_eq!(commitment, expected);
+    }
+
+    let metrics = pool.metrics();
+    assert_eq!(metrics.completed, NUM_JOBS as u64);
+    assert_eq!(metrics.cancelled, 0);
+    assert_eq!(metrics.idle + metrics.queued, 0);
+}
+
+#[tokio::test]
+async fn test_cancellation() {
+    // No workers, so started jobs stay queued
+    let pool = VidWorkerPool::new(0);
+
+    let idle = pool.job(payload(0), TEST_NUM_NODES_IN_VID_COMPUTATION);
+    let claimed = pool.job(payload(1), TEST_NUM_NODES_IN_VID_COMPUTATION);
+    claimed.start(VidPriority::Claimed);
+    assert_eq!(pool.metrics().idle, 1);
+    assert_eq!(pool.metrics().queued, 1);
+
+    // Dropping the last handle cancels the job, whether it was started or not
+    drop(idle);
+    assert_eq!(pool.metrics().cancelled, 1);
+    assert_eq!(pool.metrics().idle, 0);
+    let clone = claimed.clone();
+    drop(claimed);
+    assert_eq!(pool.metrics().queued, 1);
+    drop(clone);
+    let metrics = pool.metrics();
+    assert_eq!(metrics.cancelled, 2);
+    assert_eq!(metrics.queued + metrics.idle, 0);
+}
+
+#[tokio::test]
+async fn test_priority() {
+    // Large enough for VID to take a while
+    let large_payload = vec![0; 1 << 20];
+    let pool = VidWorkerPool::new(1);
+
+    // Occupy the only worker
+    let running = pool.job(large_payload.clone(), TEST_NUM_NODES_IN_VID_COMPUTATION);
+    running.start(VidPriority::Claimed);
+
+    let claimed = pool.job(large_payload, TEST_NUM_NODES_IN_VID_COMPUTATION);
+    claimed.start(VidPriority::Claimed);
+    let requested = pool.job(payload(1), TEST_NUM_NODES_IN_VID_COMPUTATION);
+
+    // Job with header input requested overtakes the one that was only claimed
+    requested.resolve().await.unwrap();
+    assert_eq!(pool.metrics().completed, 2);
+    assert_eq!(pool.metrics().running, 1);
+
+    claimed.resolve().await.unwrap();
+    assert_eq!(pool.metrics().completed, 3);
+}
+
+#[tokio::test]
+async fn test_latency_callback() {
+    let measurements = Arc::new(Mutex::new(Vec::new()));
+    let callback: VidLatencyCallback = {
+        let measurements = Arc::clone(&measurements);
+        Arc::new(move |block_size: u64, num_nodes: usize, _: Duration| {
+            measurements.lock().unwrap().push((block_size, num_nodes))
+        })
+    };
+    let pool = VidWorkerPool::with_latency_callback(2, Some(callback));
+
+    pool.job(payload(0), TEST_NUM_NODES_IN_VID_COMPUTATION)
+        .resolve()
+        .await
+        .unwrap();
+    assert_eq!(
+        *measurements.lock().unwrap(),
+        vec![(payload(0).len() as u64, TEST_NUM_NODES_IN_VID_COMPUTATION)]
+    );
+}
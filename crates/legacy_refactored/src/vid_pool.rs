@@ -0,0 +1,313 @@
+//! Bounded pool of workers precomputing VID for built blocks.
+//!
+//! Every block the builder offers gets a [`VidJob`], which stays idle until the block
+//! is claimed. Started jobs are queued by [`VidPriority`] and run on blocking threads,
+//! with at most [`VidWorkerPool::max_concurrency`] running at once. Jobs that haven't
+//! started running yet are cancelled once every handle to them is dropped, e.g. when
+//! their block is pruned from [`BlockStore`](crate::block_store::BlockStore).
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap},
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::{
+    channel::oneshot,
+    future::{FutureExt, Shared},
+};
+use hotshot_types::{
+    traits::block_contents::precompute_vid_commitment,
+    vid::{VidCommitment, VidPrecomputeData},
+};
+
+/// Result of VID precomputation for a block
+pub type VidData = (VidCommitment, VidPrecomputeData);
+
+/// Callback invoked with block size, number of nodes and duration
+/// of every completed VID precomputation
+pub type VidLatencyCallback = Arc<dyn Fn(u64, usize, Duration) + Send + Sync>;
+
+/// Priority of a started [`VidJob`], determined by the claim state of its block.
+/// Jobs with higher priority are run first, jobs with equal priority in order of creation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VidPriority {
+    /// Block was claimed, proposer will likely request its header input next
+    Claimed,
+    /// Proposer is waiting on header input for the block
+    HeaderInputRequested,
+}
+
+/// Snapshot of [`VidWorkerPool`] utilization
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VidPoolMetrics {
+    /// Maximum number of jobs running at once
+    pub max_concurrency: usize,
+    /// Number of jobs currently running
+    pub running: usize,
+    /// Number of started jobs waiting for a free worker
+    pub queued: usize,
+    /// Number of jobs for blocks that haven't been claimed yet
+    pub idle: usize,
+    /// Total number of jobs completed
+    pub completed: u64,
+    /// Total number of jobs cancelled before running
+    pub cancelled: u64,
+}
+
+impl VidPoolMetrics {
+    /// Share of workers currently busy, from 0 to 1
+    pub fn utilization(&self) -> f64 {
+        if self.max_concurrency == 0 {
+            return 0.0;
+        }
+        self.running as f64 / self.max_concurrency as f64
+    }
+}
+
+struct PendingJob {
+    encoded_txns: Vec<u8>,
+    num_nodes: usize,
+    sender: oneshot::Sender<VidData>,
+    /// `None` until the job is started
+    priority: Option<VidPriority>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    next_id: u64,
+    running: usize,
+    pending: HashMap<u64, PendingJob>,
+    queue: BTreeSet<(Reverse<VidPriority>, u64)>,
+}
+
+struct PoolInner {
+    max_concurrency: usize,
+    on_complete: Option<VidLatencyCallback>,
+    state: Mutex<PoolState>,
+    completed: AtomicU64,
+    cancelled: AtomicU64,
+}
+
+impl PoolInner {
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        // Poisoning isn't a concern, as the state is never left inconsistent
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Queue job `id` with `priority`, unless it's already queued with the same or higher priority
+    fn start(self: &Arc<Self>, id: u64, priority: VidPriority) {
+        let mut guard = self.lock();
+        let state = &mut *guard;
+        // Already running, completed or cancelled
+        let Some(job) = state.pending.get_mut(&id) else {
+            return;
+        };
+        if let Some(current) = job.priority {
+            if current >= priority {
+                return;
+            }
+            state.queue.remove(&(Reverse(current), id));
+        }
+        job.priority = Some(priority);
+        state.queue.insert((Reverse(priority), id));
+        self.dispatch(state);
+    }
+
+    /// Remove job `id` if it hasn't started running
+    fn cancel(&self, id: u64) {
+        let mut state = self.lock();
+        let Some(job) = state.pending.get(&id) else {
+            return;
+        };
+        if let Some(priority) = job.priority {
+            state.queue.remove(&(Reverse(priority), id));
+        }
+        // Dropping the sender notifies anyone waiting on the job
+        state.pending.remove(&id);
+        self.cancelled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Run queued jobs while there are free workers
+    fn dispatch(self: &Arc<Self>, state: &mut PoolState) {
+        while state.running < self.max_concurrency {
+            let Some((_, id)) = state.queue.pop_first() else {
+                break;
+            };
+            let Some(job) = state.pending.remove(&id) else {
+                continue;
+            };
+            state.running += 1;
+
+            let guard = RunningGuard(Arc::clone(self));
+            tokio::task::spawn_blocking(move || {
+                let start = Instant::now();
+                let vid_data = precompute_vid_commitment(&job.encoded_txns, job.num_nodes);
+                if let Some(on_complete) = &guard.0.on_complete {
+                    on_complete(
+                        job.encoded_txns.len() as u64,
+                        job.num_nodes,
+                        start.elapsed(),
+                    );
+                }
+                guard.0.completed.fetch_add(1, Ordering::Relaxed);
+                // Everyone waiting on the job may have gone away while it was running
+                let _ = job.sender.send(vid_data);
+            });
+        }
+    }
+}
+
+/// Frees up a worker once the job holding it finishes, even if it panics
+struct RunningGuard(Arc<PoolInner>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.running -= 1;
+        self.0.dispatch(&mut state);
+    }
+}
+
+/// Bounded pool of workers precomputing VID, see [module documentation](self)
+#[derive(Clone)]
+pub struct VidWorkerPool {
+    inner: Arc<PoolInner>,
+}
+
+impl VidWorkerPool {
+    /// Create a pool running at most `max_concurrency` jobs at once.
+    /// Note that with `max_concurrency` of zero no job will ever run.
+    pub fn new(max_concurrency: usize) -> Self {
+        Self::with_latency_callback(max_concurrency, None)
+    }
+
+    /// Create a pool running at most `max_concurrency` jobs at once,
+    /// reporting how long every job took to `on_complete`
+    pub fn with_latency_callback(
+        max_concurrency: usize,
+        on_complete: Option<VidLatencyCallback>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                max_concurrency,
+                on_complete,
+                state: Mutex::new(PoolState::default()),
+                completed: AtomicU64::new(0),
+                cancelled: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Maximum number of jobs running at once
+    pub fn max_concurrency(&self) -> usize {
+        self.inner.max_concurrency
+    }
+
+    /// Create an idle job precomputing VID for `encoded_txns` with `num_nodes`.
+    /// It won't be queued until [`VidJob::start`] or [`VidJob::resolve`] is called.
+    pub fn job(&self, encoded_txns: Vec<u8>, num_nodes: usize) -> VidJob {
+        let (sender, receiver) = oneshot::channel();
+        let id = {
+            let mut state = self.inner.lock();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.pending.insert(
+                id,
+                PendingJob {
+                    encoded_txns,
+                    num_nodes,
+                    sender,
+                    priority: None,
+                },
+            );
+            id
+        };
+        VidJob {
+            handle: Arc::new(JobHandle {
+                pool: Arc::clone(&self.inner),
+                id,
+            }),
+            result: receiver.shared(),
+        }
+    }
+
+    /// Current utilization of the pool
+    pub fn metrics(&self) -> VidPoolMetrics {
+        let state = self.inner.lock();
+        let queued = state.queue.len();
+        VidPoolMetrics {
+            max_concurrency: self.inner.max_concurrency,
+            running: state.running,
+            queued,
+            idle: state.pending.len() - queued,
+            completed: self.inner.completed.load(Ordering::Relaxed),
+            cancelled: self.inner.cancelled.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for VidWorkerPool {
+    /// Pool with a worker per CPU
+    fn default() -> Self {
+        Self::new(num_cpus::get())
+    }
+}
+
+impl fmt::Debug for VidWorkerPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VidWorkerPool")
+            .field("metrics", &self.metrics())
+            .finish()
+    }
+}
+
+/// Cancels the job once the last [`VidJob`] referring to it is dropped
+struct JobHandle {
+    pool: Arc<PoolInner>,
+    id: u64,
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        self.pool.cancel(self.id);
+    }
+}
+
+/// Handle to VID precomputation for a single block, created by [`VidWorkerPool::job`].
+/// Clones refer to the same job.
+#[derive(Clone)]
+pub struct VidJob {
+    handle: Arc<JobHandle>,
+    result: Shared<oneshot::Receiver<VidData>>,
+}
+
+impl VidJob {
+    /// Queue the job with `priority`. If it's already queued with lower priority,
+    /// it's moved up the queue.
+    pub fn start(&self, priority: VidPriority) {
+        self.handle.pool.start(self.handle.id, priority);
+    }
+
+    /// Start the job with [`VidPriority::HeaderInputRequested`] and wait for it to finish.
+    /// Returns `None` if the job was cancelled.
+    pub async fn resolve(self) -> Option<VidData> {
+        self.start(VidPriority::HeaderInputRequested);
+        self.result.clone().await.ok()
+    }
+}
+
+impl fmt::Debug for VidJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VidJob")
+            .field("id", &self.handle.id)
+            .finish_non_exhaustive()
+    }
+}
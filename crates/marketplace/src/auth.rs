@@ -0,0 +1,129 @@
+//! Authentication of proposers requesting bundles.
+//!
+//! Every bundle carries a signed fee commitment, so serving bundles to anyone
+//! who asks lets outsiders collect those. With [`BundleAuthConfig`] set, proposers
+//! are expected to request bundles through [`AUTHENTICATED_BUNDLE_MODULE`], signing
+//! the request as described in [`bundle_request_signing_bytes`].
+//!
+//! Signed requests from privileged proposers, i.e. ones on the allowlist or,
+//! without an allowlist, the leader of the requested view, are always served.
+//! Everyone else, including anonymous callers of the standard API, is rate-limited
+//! per caller or rejected: signed requests are limited by sender key
+//! and anonymous ones by IP address.
+
+use std::collections::HashSet;
+
+use hotshot_types::{
+    traits::{
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+    },
+    vid::VidCommitment,
+};
+use marketplace_builder_shared::{
+    coordinator::proposal_validation::LeaderOracle,
+    error::Error,
+    rate_limit::{KeyedRateLimiter, RateLimit},
+};
+
+/// Name of the API module serving authenticated bundle requests
+pub const AUTHENTICATED_BUNDLE_MODULE: &str = "authenticated_bundle";
+
+/// Configuration of bundle request authentication
+#[derive(Debug, Clone)]
+pub struct BundleAuthConfig<Types: NodeType> {
+    /// Keys allowed to request bundles. Signed requests from other keys are rejected.
+    /// If unset, the leader of the requested view according to
+    /// [`BuilderConfig::leader_oracle`](crate::service::BuilderConfig::leader_oracle)
+    /// is served, and other correctly signed requests are subject to [`Self::rate_limit`].
+    pub allowed_proposers: Option<HashSet<Types::SignatureKey>>,
+    /// Limit on requests from each unprivileged caller, tracked separately
+    /// for every sender key and every anonymous caller's remote address.
+    /// If unset, requests from unprivileged callers are rejected.
+    pub rate_limit: Option<RateLimit>,
+}
+
+/// Bytes a proposer signs to request a bundle for `view_number`
+/// built on top of `parent_hash` from `parent_view`
+pub fn bundle_request_signing_bytes(
+    parent_view: u64,
+    parent_hash: &VidCommitment,
+    view_number: u64,
+) -> Vec<u8> {
+    let parent_hash: &[u8] = parent_hash.as_ref();
+    let mut bytes = Vec::with_capacity(parent_hash.len() + 16);
+    bytes.extend_from_slice(&parent_view.to_le_bytes());
+    bytes.extend_from_slice(parent_hash);
+    bytes.extend_from_slice(&view_number.to_le_bytes());
+    bytes
+}
+
+/// Enforces [`BundleAuthConfig`]
+#[derive(Debug)]
+pub(crate) struct BundleAuth<Types: NodeType> {
+    allowed_proposers: Option<HashSet<Types::SignatureKey>>,
+    leader_oracle: Option<LeaderOracle<Types>>,
+    signed_limiter: Option<KeyedRateLimiter<Types::SignatureKey>>,
+    anonymous_limiter: Option<KeyedRateLimiter<String>>,
+}
+
+impl<Types: NodeType> BundleAuth<Types> {
+    pub(crate) fn new(
+        config: BundleAuthConfig<Types>,
+        leader_oracle: Option<LeaderOracle<Types>>,
+    ) -> Self {
+        Self {
+            allowed_proposers: config.allowed_proposers,
+            leader_oracle,
+            signed_limiter: config.rate_limit.map(KeyedRateLimiter::new),
+            anonymous_limiter: config.rate_limit.map(KeyedRateLimiter::new),
+        }
+    }
+
+    /// Check that the request is signed by `sender` and that `sender` is allowed to request bundles,
+    /// see [module documentation](self)
+    pub(crate) fn check_signed(
+        &self,
+        parent_view: u64,
+        parent_hash: &VidCommitment,
+        view_number: u64,
+        sender: &Types::SignatureKey,
+        signature: &<Types::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<(), Error<Types>> {
+        if !sender.validate(
+            signature,
+            &bundle_request_signing_bytes(parent_view, parent_hash, view_number),
+        ) {
+            return Err(Error::SignatureValidation);
+        }
+        match &self.allowed_proposers {
+            Some(allowed_proposers) if allowed_proposers.contains(sender) => Ok(()),
+            Some(_) => Err(Error::Unauthorized),
+            None if self.is_leader(sender, view_number) => Ok(()),
+            None => Self::limit(self.signed_limiter.as_ref(), sender),
+        }
+    }
+
+    /// Account for an unauthenticated request by `caller`
+    pub(crate) fn check_anonymous(&self, caller: &str) -> Result<(), Error<Types>> {
+        Self::limit(self.anonymous_limiter.as_ref(), &caller.to_owned())
+    }
+
+    fn is_leader(&self, sender: &Types::SignatureKey, view_number: u64) -> bool {
+        self.leader_oracle.as_ref().is_some_and(|oracle| {
+            oracle.leader(Types::View::new(view_number)).as_ref() == Some(sender)
+        })
+    }
+
+    fn limit<K: std::hash::Hash + Eq + Clone>(
+        limiter: Option<&KeyedRateLimiter<K>>,
+        caller: &K,
+    ) -> Result<(), Error<Types>> {
+        let Some(limiter) = limiter else {
+            return Err(Error::Unauthorized);
+        };
+        limiter
+            .check(caller)
+            .map_err(|retry_after| Error::RateLimited { retry_after })
+    }
+}
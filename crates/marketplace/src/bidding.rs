@@ -0,0 +1,215 @@
+//! Participation in the marketplace auction.
+//!
+//! To be asked for bundles, the builder has to win the auction run by the solver.
+//! [`Bidder`] builds and signs bids for upcoming views and submits them to the solver,
+//! driven by view events from HotShot.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use hotshot_types::traits::{node_implementation::NodeType, signature_key::BuilderSignatureKey};
+use marketplace_builder_shared::{error::Error, utils::BuilderKeys};
+use serde::{Deserialize, Serialize};
+use surf_disco::Client;
+use tide_disco::error::ServerError;
+use tokio::time::timeout;
+use url::Url;
+use vbs::version::StaticVersion;
+
+use crate::namespace::NamespaceId;
+
+/// Version of the solver API
+pub type SolverApiVersion = StaticVersion<0, 1>;
+
+/// Route of the solver API accepting bids, relative to [`BidConfig::solver_url`]
+pub const SUBMIT_BID_ROUTE: &str = "marketplace-solver/submit_bid";
+
+/// Configuration of the bidding subsystem
+#[derive(Debug, Clone)]
+pub struct BidConfig {
+    /// Base URL of the solver API
+    pub solver_url: Url,
+    /// URL at which the builder serves its API, advertised in bids
+    pub builder_url: Url,
+    /// Amount to bid for each namespace we're building bundles for
+    pub bid_amounts: BTreeMap<NamespaceId, u64>,
+    /// How many views ahead of the last finished view to bid for.
+    /// Should be large enough for the auction for the target view to still be open.
+    pub lookahead: u64,
+    /// Maximum time allotted to a single bid submission
+    pub submission_timeout: Duration,
+}
+
+/// Contents of a bid
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BidTxBody<Types: NodeType> {
+    /// Builder account the bid is placed from
+    pub account: Types::BuilderSignatureKey,
+    /// View the bid is for
+    pub view: u64,
+    /// Namespace the bid is for
+    pub namespace: NamespaceId,
+    /// Bid amount
+    pub bid_amount: u64,
+    /// URL at which the builder serves bundles
+    pub url: Url,
+}
+
+impl<Types: NodeType> BidTxBody<Types> {
+    /// Serialized form of the body, which is what the signature is over
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        // Serializing plain data into a buffer can't fail
+        bincode::serialize(self).expect("Failed to serialize bid body")
+    }
+
+    /// Sign the bid with builder's private key
+    pub fn sign(
+        self,
+        private_key: &<Types::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
+    ) -> Result<BidTx<Types>, Error<Types>> {
+        let signature = <Types::BuilderSignatureKey as BuilderSignatureKey>::sign_builder_message(
+            private_key,
+            &self.signing_bytes(),
+        )
+        .map_err(Error::Signing)?;
+        Ok(BidTx {
+            body: self,
+            signature,
+        })
+    }
+}
+
+/// A signed bid
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BidTx<Types: NodeType> {
+    /// Contents of the bid
+    pub body: BidTxBody<Types>,
+    /// Signature over [`BidTxBody::signing_bytes`] by [`BidTxBody::account`]
+    pub signature: <Types::BuilderSignatureKey as BuilderSignatureKey>::BuilderSignature,
+}
+
+impl<Types: NodeType> BidTx<Types> {
+    /// Check that the bid is signed by its account
+    pub fn verify(&self) -> bool {
+        self.body
+            .account
+            .validate_builder_signature(&self.signature, &self.body.signing_bytes())
+    }
+}
+
+/// Builds, signs and submits bids to the solver
+#[derive(derive_more::Debug)]
+pub struct Bidder<Types: NodeType> {
+    config: BidConfig,
+    #[debug(skip)]
+    builder_keys: BuilderKeys<Types>,
+    #[debug(skip)]
+    client: Client<ServerError, SolverApiVersion>,
+    /// Highest view we've successfully submitted bids for, used to avoid bidding twice
+    last_bid_view: AtomicU64,
+    /// Views we're currently submitting bids for
+    in_flight: Mutex<HashSet<u64>>,
+}
+
+impl<Types: NodeType> Bidder<Types> {
+    /// Create a new bidder signing bids with `builder_keys`
+    pub fn new(config: BidConfig, builder_keys: BuilderKeys<Types>) -> Self {
+        Self {
+            client: Client::new(config.solver_url.clone()),
+            config,
+            builder_keys,
+            last_bid_view: AtomicU64::new(0),
+            in_flight: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Build and sign bids for `view`, one per configured namespace
+    pub fn bids_for_view(&self, view: u64) -> Result<Vec<BidTx<Types>>, Error<Types>> {
+        self.config
+            .bid_amounts
+            .iter()
+            .map(|(&namespace, &bid_amount)| {
+                BidTxBody {
+                    account: self.builder_keys.0.clone(),
+                    view,
+                    namespace,
+                    bid_amount,
+                    url: self.config.builder_url.clone(),
+                }
+                .sign(&self.builder_keys.1)
+            })
+            .collect()
+    }
+
+    /// This function should be called whenever a view finishes.
+    /// Bids for the view [`BidConfig::lookahead`] views ahead, unless it already has.
+    /// If any bid fails to be submitted, bids for that view are retried the next time
+    /// this is called for the same view.
+    #[tracing::instrument(skip(self))]
+    pub async fn handle_view_finished(&self, view_number: Types::View) {
+        let target_view = *view_number + self.config.lookahead;
+        if self.last_bid_view.load(Ordering::Acquire) >= target_view {
+            // Already bid for this view
+            return;
+        }
+        if !self.lock_in_flight().insert(target_view) {
+            // Bids for this view are being submitted by another call
+            return;
+        }
+
+        if self.submit_bids_for_view(target_view).await {
+            self.last_bid_view.fetch_max(target_view, Ordering::AcqRel);
+        }
+        self.lock_in_flight().remove(&target_view);
+    }
+
+    /// Sign and submit bids for `target_view`, returning whether all of them were submitted
+    async fn submit_bids_for_view(&self, target_view: u64) -> bool {
+        let bids = match self.bids_for_view(target_view) {
+            Ok(bids) => bids,
+            Err(err) => {
+                tracing::error!(?err, target_view, "Failed to sign bids");
+                return false;
+            }
+        };
+
+        bids.into_iter()
+            .map(|bid| self.submit_bid(bid))
+            .collect::<FuturesUnordered<_>>()
+            .fold(true, |all_submitted, result| async move {
+                if let Err(err) = &result {
+                    tracing::warn!(%err, target_view, "Failed to submit bid");
+                }
+                all_submitted && result.is_ok()
+            })
+            .await
+    }
+
+    /// Submit a single bid to the solver
+    pub async fn submit_bid(&self, bid: BidTx<Types>) -> anyhow::Result<()> {
+        let request = self.client.post::<()>(SUBMIT_BID_ROUTE).body_binary(&bid)?;
+        timeout(self.config.submission_timeout, request.send()).await??;
+        tracing::debug!(
+            view = bid.body.view,
+            namespace = bid.body.namespace,
+            "Submitted bid"
+        );
+        Ok(())
+    }
+
+    fn lock_in_flight(&self) -> MutexGuard<'_, HashSet<u64>> {
+        // The set is never left in an inconsistent state, so poisoning can be ignored
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
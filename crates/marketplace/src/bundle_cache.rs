@@ -0,0 +1,135 @@
+//! Caching of bundles served by the builder.
+//!
+//! Leaders may retry bundle requests, and each of them should get the same bundle
+//! and fee in response. Signed bundles are cached per requested builder state and
+//! view, and served from the cache for duplicate requests.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use hotshot_types::{bundle::Bundle, traits::node_implementation::NodeType};
+use marketplace_builder_shared::{
+    block::BuilderStateId, coordinator::tiered_view_map::TieredViewMap,
+};
+
+/// Default value for [`BundleCachePolicy::ttl`]
+pub const DEFAULT_BUNDLE_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Determines for how long and under which conditions cached bundles are served
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BundleCachePolicy {
+    /// For how long duplicate requests will be served a cached bundle
+    pub ttl: Duration,
+    /// If set, a cached bundle is discarded and a new one assembled once at least
+    /// this many new transactions arrived to the builder state it was assembled from
+    pub refresh_threshold: Option<usize>,
+}
+
+impl Default for BundleCachePolicy {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_BUNDLE_CACHE_TTL,
+            refresh_threshold: None,
+        }
+    }
+}
+
+/// A bundle we've already served
+#[derive(Debug, Clone)]
+pub struct CachedBundle<Types: NodeType> {
+    /// The signed bundle
+    pub bundle: Bundle<Types>,
+    /// When the bundle was assembled
+    pub created_at: Instant,
+    /// Length of the builder state's transaction queue when the bundle was assembled
+    pub queue_len: usize,
+}
+
+/// Signed bundles keyed by the builder state they were assembled from and
+/// the view they are for
+#[derive(Debug)]
+pub struct BundleCache<Types: NodeType> {
+    policy: BundleCachePolicy,
+    bundles: TieredViewMap<BuilderStateId<Types>, BTreeMap<u64, CachedBundle<Types>>>,
+}
+
+impl<Types: NodeType> BundleCache<Types> {
+    /// Create an empty cache governed by `policy`
+    pub fn new(policy: BundleCachePolicy) -> Self {
+        Self {
+            policy,
+            bundles: TieredViewMap::new(),
+        }
+    }
+
+    /// Returns a cached bundle for `view_number` assembled from builder state `state_id`,
+    /// unless it's expired or has been outdated by at least [`BundleCachePolicy::refresh_threshold`]
+    /// new transactions, judging by current length of the builder state's queue `queue_len`
+    pub fn get(
+        &self,
+        state_id: &BuilderStateId<Types>,
+        view_number: u64,
+        queue_len: usize,
+    ) -> Option<&Bundle<Types>> {
+        let cached = self.bundles.get(state_id)?.get(&view_number)?;
+        if cached.created_at.elapsed() > self.policy.ttl {
+            return None;
+        }
+        if self
+            .policy
+            .refresh_threshold
+            .is_some_and(|threshold| queue_len.saturating_sub(cached.queue_len) >= threshold)
+        {
+            return None;
+        }
+        Some(&cached.bundle)
+    }
+
+    /// Cache a newly assembled bundle. If a concurrent request has already cached
+    /// a bundle that is still valid, that bundle is kept and returned instead,
+    /// so that both requests are served the same bundle.
+    pub fn insert(
+        &mut self,
+        state_id: BuilderStateId<Types>,
+        view_number: u64,
+        bundle: Bundle<Types>,
+        queue_len: usize,
+    ) -> Bundle<Types> {
+        if let Some(cached) = self.get(&state_id, view_number, queue_len) {
+            return cached.clone();
+        }
+
+        let cached = CachedBundle {
+            bundle: bundle.clone(),
+            created_at: Instant::now(),
+            queue_len,
+        };
+        match self.bundles.get_mut(&state_id) {
+            Some(bundles) => {
+                bundles.insert(view_number, cached);
+            }
+            None => {
+                self.bundles
+                    .insert(state_id, BTreeMap::from([(view_number, cached)]));
+            }
+        }
+        bundle
+    }
+
+    /// Number of cached bundles
+    pub fn len(&self) -> usize {
+        self.bundles.values().map(BTreeMap::len).sum()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.bundles.is_empty()
+    }
+
+    /// Drop bundles assembled from builder states with parent view below `cutoff`
+    pub fn prune(&mut self, cutoff: Types::View) {
+        self.bundles.prune(cutoff);
+    }
+}
@@ -0,0 +1,127 @@
+//! Strategies for pricing bundles served by the builder.
+//!
+//! The fee offered for a bundle is determined by a [`FeeStrategy`], which
+//! can take into account the bundle itself, the state of the transaction queue
+//! it was assembled from and the view it is intended for.
+
+use std::{fmt::Debug, sync::Arc};
+
+use hotshot_types::traits::{block_contents::Transaction, node_implementation::NodeType};
+use marketplace_builder_shared::state::QueueStatistics;
+
+/// Information available to a [`FeeStrategy`] when pricing a bundle
+#[derive(Debug)]
+pub struct FeeContext<'a, Types: NodeType> {
+    /// Transactions included in the bundle
+    pub transactions: &'a [Types::Transaction],
+    /// Total size of the bundle's transactions, in bytes
+    pub bundle_size: u64,
+    /// Statistics of the queue of the builder state the bundle was assembled from
+    pub queue: &'a QueueStatistics,
+    /// View the bundle is intended for
+    pub view_number: u64,
+}
+
+impl<'a, Types: NodeType> FeeContext<'a, Types> {
+    /// Create a new context, computing bundle size from `transactions`
+    pub fn new(
+        transactions: &'a [Types::Transaction],
+        queue: &'a QueueStatistics,
+        view_number: u64,
+    ) -> Self {
+        Self {
+            transactions,
+            bundle_size: transactions
+                .iter()
+                .map(|txn| txn.minimum_block_size())
+                .sum(),
+            queue,
+            view_number,
+        }
+    }
+}
+
+/// A strategy determining the sequencing fee offered for a bundle
+pub trait FeeStrategy<Types: NodeType>: Debug + Send + Sync + 'static {
+    /// Fee to offer for the bundle described by `context`
+    fn offered_fee(&self, context: &FeeContext<'_, Types>) -> u64;
+}
+
+/// Offers a fixed fee per byte of the bundle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedFee {
+    /// Fee per bundle byte
+    pub base_fee: u64,
+}
+
+impl<Types: NodeType> FeeStrategy<Types> for FixedFee {
+    fn offered_fee(&self, context: &FeeContext<'_, Types>) -> u64 {
+        self.base_fee.saturating_mul(context.bundle_size)
+    }
+}
+
+/// Scales the fee per byte with congestion: the multiplier grows linearly
+/// from `1` when the queue is empty to [`Self::max_multiplier`] once the queue
+/// holds [`Self::saturation_bytes`] or more.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CongestionFee {
+    /// Fee per bundle byte when there's no congestion
+    pub base_fee: u64,
+    /// Size of the queue in bytes at which the fee stops growing
+    pub saturation_bytes: u64,
+    /// Maximum multiplier applied to the base fee
+    pub max_multiplier: f64,
+}
+
+impl<Types: NodeType> FeeStrategy<Types> for CongestionFee {
+    fn offered_fee(&self, context: &FeeContext<'_, Types>) -> u64 {
+        let base = self.base_fee.saturating_mul(context.bundle_size);
+        if self.saturation_bytes == 0 {
+            return base;
+        }
+
+        let congestion = (context.queue.total_bytes as f64 / self.saturation_bytes as f64).min(1.0);
+        let multiplier = 1.0 + (self.max_multiplier.max(1.0) - 1.0) * congestion;
+
+        // Float to integer casts saturate
+        (base as f64 * multiplier) as u64
+    }
+}
+
+/// Function extracting fee declared by the user from a transaction
+pub type UserFeeExtractor<Types> =
+    Arc<dyn Fn(&<Types as NodeType>::Transaction) -> u64 + Send + Sync>;
+
+/// Passes through a share of fees declared by users in the bundle's transactions,
+/// but never offers less than [`Self::floor`]
+#[derive(derive_more::Debug)]
+pub struct UserFeeShare<Types: NodeType> {
+    /// Extracts fee declared by the user from a transaction
+    #[debug(skip)]
+    pub user_fee: UserFeeExtractor<Types>,
+    /// Share of user fees to pass through, in basis points
+    pub share_bps: u16,
+    /// Minimum fee to offer
+    pub floor: FixedFee,
+}
+
+impl<Types: NodeType> UserFeeShare<Types> {
+    /// Basis points in a whole
+    pub const BPS_DENOMINATOR: u64 = 10_000;
+}
+
+impl<Types: NodeType> FeeStrategy<Types> for UserFeeShare<Types> {
+    fn offered_fee(&self, context: &FeeContext<'_, Types>) -> u64 {
+        let user_fees = context
+            .transactions
+            .iter()
+            .map(self.user_fee.as_ref())
+            .fold(0u64, u64::saturating_add);
+        let share: u64 = (u128::from(user_fees) * u128::from(self.share_bps)
+            / u128::from(Self::BPS_DENOMINATOR))
+        .try_into()
+        .unwrap_or(u64::MAX);
+
+        share.max(self.floor.offered_fee(context))
+    }
+}
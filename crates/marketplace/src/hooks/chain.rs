@@ -0,0 +1,224 @@
+//! Composition of multiple [`BuilderHooks`] implementations.
+//!
+//! Hooks can be stacked either with [`HookChain`], which holds any number
+//! of boxed hooks, or as a tuple, e.g. `(MyFilter, MyMetrics)`. In both cases
+//! hooks run in order, each receiving the output of the previous one:
+//! - [`BuilderHooks::process_transactions`] stops once no transactions are left
+//! - [`BuilderHooks::transaction_verdicts`] passes on accepted transactions only,
+//!   so the first hook to reject or defer a transaction decides its fate
+//! - [`BuilderHooks::on_bundle_assembled`] stops at the first veto
+//! - notification hooks are called on every hook
+
+use async_trait::async_trait;
+use committable::Commitment;
+use hotshot::types::Event;
+use hotshot_types::traits::node_implementation::NodeType;
+
+use super::{BuilderHooks, RejectionReason, TransactionVerdict};
+
+/// Hooks running multiple hooks in order, see module documentation for details
+pub struct HookChain<Types: NodeType> {
+    hooks: Vec<Box<dyn BuilderHooks<Types>>>,
+}
+
+impl<Types: NodeType> HookChain<Types> {
+    /// Create an empty chain
+    pub fn new() -> Self {
+        Self { hooks: Vec::new() }
+    }
+
+    /// Append `hook` to the end of the chain
+    pub fn with(mut self, hook: impl BuilderHooks<Types>) -> Self {
+        self.push(hook);
+        self
+    }
+
+    /// Append `hook` to the end of the chain
+    pub fn push(&mut self, hook: impl BuilderHooks<Types>) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Number of hooks in the chain
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Whether the chain is empty
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+}
+
+impl<Types: NodeType> Default for HookChain<Types> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn process_transactions<Types: NodeType>(
+    hooks: &[&dyn BuilderHooks<Types>],
+    mut transactions: Vec<Types::Transaction>,
+) -> Vec<Types::Transaction> {
+    for hook in hooks {
+        if transactions.is_empty() {
+            break;
+        }
+        transactions = hook.process_transactions(transactions).await;
+    }
+    transactions
+}
+
+async fn transaction_verdicts<Types: NodeType>(
+    hooks: &[&dyn BuilderHooks<Types>],
+    transactions: Vec<Types::Transaction>,
+) -> Vec<(Types::Transaction, TransactionVerdict)> {
+    let mut decided = Vec::new();
+    let mut accepted = transactions;
+    for hook in hooks {
+        if accepted.is_empty() {
+            break;
+        }
+        let mut still_accepted = Vec::new();
+        for (txn, verdict) in hook.transaction_verdicts(accepted).await {
+            match verdict {
+                TransactionVerdict::Accept => still_accepted.push(txn),
+                verdict => decided.push((txn, verdict)),
+            }
+        }
+        accepted = still_accepted;
+    }
+    decided.extend(
+        accepted
+            .into_iter()
+            .map(|txn| (txn, TransactionVerdict::Accept)),
+    );
+    decided
+}
+
+async fn handle_hotshot_event<Types: NodeType>(
+    hooks: &[&dyn BuilderHooks<Types>],
+    event: &Event<Types>,
+) {
+    for hook in hooks {
+        hook.handle_hotshot_event(event).await;
+    }
+}
+
+async fn on_bundle_assembled<Types: NodeType>(
+    hooks: &[&dyn BuilderHooks<Types>],
+    view_number: u64,
+    mut transactions: Vec<Types::Transaction>,
+) -> Option<Vec<Types::Transaction>> {
+    for hook in hooks {
+        transactions = hook.on_bundle_assembled(view_number, transactions).await?;
+    }
+    Some(transactions)
+}
+
+async fn on_transactions_sequenced<Types: NodeType>(
+    hooks: &[&dyn BuilderHooks<Types>],
+    view_number: Types::View,
+    commitments: &[Commitment<Types::Transaction>],
+) {
+    for hook in hooks {
+        hook.on_transactions_sequenced(view_number, commitments)
+            .await;
+    }
+}
+
+async fn on_transaction_rejected<Types: NodeType>(
+    hooks: &[&dyn BuilderHooks<Types>],
+    commitment: Commitment<Types::Transaction>,
+    reason: &RejectionReason,
+) {
+    for hook in hooks {
+        hook.on_transaction_rejected(commitment, reason).await;
+    }
+}
+
+/// Implements [`BuilderHooks`] by delegating to the chained implementations above,
+/// given an expression producing hooks to run from `self` bound to `$this`
+macro_rules! impl_chained_hooks {
+    ([$($generics:tt)*] $ty:ty where [$($bounds:tt)*], $this:ident => $hooks:expr) => {
+        #[async_trait]
+        impl<$($generics)*> BuilderHooks<Types> for $ty
+        where
+            $($bounds)*
+        {
+            async fn process_transactions(
+                &self,
+                transactions: Vec<Types::Transaction>,
+            ) -> Vec<Types::Transaction> {
+                let $this = self;
+                let hooks: Vec<&dyn BuilderHooks<Types>> = $hooks;
+                process_transactions(&hooks, transactions).await
+            }
+
+            async fn transaction_verdicts(
+                &self,
+                transactions: Vec<Types::Transaction>,
+            ) -> Vec<(Types::Transaction, TransactionVerdict)> {
+                let $this = self;
+                let hooks: Vec<&dyn BuilderHooks<Types>> = $hooks;
+                transaction_verdicts(&hooks, transactions).await
+            }
+
+            async fn handle_hotshot_event(&self, event: &Event<Types>) {
+                let $this = self;
+                let hooks: Vec<&dyn BuilderHooks<Types>> = $hooks;
+                handle_hotshot_event(&hooks, event).await
+            }
+
+            async fn on_bundle_assembled(
+                &self,
+                view_number: u64,
+                transactions: Vec<Types::Transaction>,
+            ) -> Option<Vec<Types::Transaction>> {
+                let $this = self;
+                let hooks: Vec<&dyn BuilderHooks<Types>> = $hooks;
+                on_bundle_assembled(&hooks, view_number, transactions).await
+            }
+
+            async fn on_transactions_sequenced(
+                &self,
+                view_number: Types::View,
+                commitments: &[Commitment<Types::Transaction>],
+            ) {
+                let $this = self;
+                let hooks: Vec<&dyn BuilderHooks<Types>> = $hooks;
+                on_transactions_sequenced(&hooks, view_number, commitments).await
+            }
+
+            async fn on_transaction_rejected(
+                &self,
+                commitment: Commitment<Types::Transaction>,
+                reason: &RejectionReason,
+            ) {
+                let $this = self;
+                let hooks: Vec<&dyn BuilderHooks<Types>> = $hooks;
+                on_transaction_rejected(&hooks, commitment, reason).await
+            }
+        }
+    };
+}
+
+impl_chained_hooks!(
+    [Types] HookChain<Types> where [Types: NodeType],
+    this => this.hooks.iter().map(|hook| hook.as_ref()).collect()
+);
+
+/// Implements [`BuilderHooks`] for a tuple of hooks, running them in order
+macro_rules! impl_tuple_hooks {
+    ($($hook:ident: $idx:tt),+) => {
+        impl_chained_hooks!(
+            [Types, $($hook),+] ($($hook,)+) where [Types: NodeType, $($hook: BuilderHooks<Types>,)+],
+            this => vec![$(&this.$idx),+]
+        );
+    };
+}
+
+impl_tuple_hooks!(A: 0, B: 1);
+impl_tuple_hooks!(A: 0, B: 1, C: 2);
+impl_tuple_hooks!(A: 0, B: 1, C: 2, D: 3);
+impl_tuple_hooks!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_tuple_hooks!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
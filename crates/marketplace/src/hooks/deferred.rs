@@ -0,0 +1,131 @@
+//! Transactions held back by [`BuilderHooks::transaction_verdicts`](super::BuilderHooks::transaction_verdicts).
+//!
+//! Deferred transactions are kept in memory and evaluated again once the current view
+//! finishes. [`DeferralPolicy`] bounds both the number of transactions held back
+//! at once and the number of views a single transaction can be held back for, so that
+//! neither hooks deferring indefinitely nor clients flooding the builder with
+//! transactions that get deferred can grow memory usage without limit.
+
+use committable::Commitment;
+use hotshot_types::traits::node_implementation::NodeType;
+use marketplace_builder_shared::block::ReceivedTransaction;
+
+use super::RejectionReason;
+
+/// Default value for [`DeferralPolicy::capacity`]
+pub const DEFAULT_DEFERRED_CAPACITY: usize = 10_000;
+
+/// Default value for [`DeferralPolicy::max_deferrals`]
+pub const DEFAULT_MAX_DEFERRALS: u32 = 10;
+
+/// Limits on transactions deferred by hooks. Transactions that can't be deferred
+/// under these limits are rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeferralPolicy {
+    /// Maximum number of transactions held back at once
+    pub capacity: usize,
+    /// Maximum number of consecutive views a single transaction can be held back for
+    pub max_deferrals: u32,
+}
+
+impl Default for DeferralPolicy {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_DEFERRED_CAPACITY,
+            max_deferrals: DEFAULT_MAX_DEFERRALS,
+        }
+    }
+}
+
+/// Transactions held back until the current view finishes, see [module documentation](self)
+#[derive(Debug)]
+pub(crate) struct DeferredTransactions<Types: NodeType> {
+    policy: DeferralPolicy,
+    /// Transactions along with the number of times each has been deferred
+    transactions: Vec<(ReceivedTransaction<Types>, u32)>,
+}
+
+impl<Types: NodeType> DeferredTransactions<Types> {
+    pub(crate) fn new(policy: DeferralPolicy) -> Self {
+        Self {
+            policy,
+            transactions: Vec::new(),
+        }
+    }
+
+    /// Number of transactions that can still be deferred
+    pub(crate) fn remaining_capacity(&self) -> usize {
+        self.policy.capacity.saturating_sub(self.transactions.len())
+    }
+
+    /// Hold back `transactions`, each along with the number of times it has been deferred,
+    /// counting this time. Returns commitments of transactions held back and ones that
+    /// couldn't be under [`DeferralPolicy`], with the reason.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn defer_all(
+        &mut self,
+        transactions: Vec<(ReceivedTransaction<Types>, u32)>,
+    ) -> (
+        Vec<Commitment<Types::Transaction>>,
+        Vec<(Commitment<Types::Transaction>, RejectionReason)>,
+    ) {
+        let mut deferred = Vec::new();
+        let mut rejected = Vec::new();
+        for (txn, deferrals) in transactions {
+            let commit = txn.commit;
+            if deferrals > self.policy.max_deferrals {
+                rejected.push((commit, self.too_long_reason()));
+            } else if self.transactions.len() >= self.policy.capacity {
+                rejected.push((commit, RejectionReason::DeferralCapacity));
+            } else {
+                self.transactions.push((txn, deferrals));
+                deferred.push(commit);
+            }
+        }
+        (deferred, rejected)
+    }
+
+    /// Like [`Self::defer_all`], but either holds back all of `transactions` or,
+    /// if any of them couldn't be under [`DeferralPolicy`], none of them.
+    /// Capacity is checked and reserved at once, so concurrent batches can't overflow it.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn defer_all_or_none(
+        &mut self,
+        transactions: Vec<(ReceivedTransaction<Types>, u32)>,
+    ) -> (
+        Vec<Commitment<Types::Transaction>>,
+        Vec<(Commitment<Types::Transaction>, RejectionReason)>,
+    ) {
+        let fits = transactions.len() <= self.remaining_capacity()
+            && transactions
+                .iter()
+                .all(|(_, deferrals)| *deferrals <= self.policy.max_deferrals);
+        if fits {
+            return self.defer_all(transactions);
+        }
+
+        let rejected = transactions
+            .into_iter()
+            .map(|(txn, deferrals)| {
+                let reason = if deferrals > self.policy.max_deferrals {
+                    self.too_long_reason()
+                } else {
+                    RejectionReason::DeferralCapacity
+                };
+                (txn.commit, reason)
+            })
+            .collect();
+        (Vec::new(), rejected)
+    }
+
+    fn too_long_reason(&self) -> RejectionReason {
+        RejectionReason::DeferredTooLong {
+            max_deferrals: self.policy.max_deferrals,
+        }
+    }
+
+    /// Remove all deferred transactions, along with the number of times each has been deferred
+    pub(crate) fn take(&mut self) -> Vec<(ReceivedTransaction<Types>, u32)> {
+        std::mem::take(&mut self.transactions)
+    }
+}
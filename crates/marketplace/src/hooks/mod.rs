@@ -0,0 +1,223 @@
+use std::{collections::HashSet, marker::PhantomData};
+
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use hotshot::types::Event;
+use hotshot_types::traits::node_implementation::NodeType;
+
+use crate::namespace::NamespaceId;
+
+pub mod chain;
+pub use chain::HookChain;
+
+pub mod deferred;
+pub use deferred::DeferralPolicy;
+
+pub mod stock;
+pub use stock::{EventLogger, PrefixFilter, PrefixFilterMode, SizeFilter};
+
+/// Reason for the builder rejecting a transaction
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum RejectionReason {
+    /// Transaction is too big to fit into a bundle
+    #[error("Transaction too big ({len}/{max_len})")]
+    TooBig { len: u64, max_len: u64 },
+    /// Transaction belongs to a namespace this builder doesn't serve
+    #[error("Namespace {0} isn't served by this builder")]
+    UnservedNamespace(NamespaceId),
+    /// Transaction was rejected by [`BuilderHooks::transaction_verdicts`]
+    #[error("Rejected by builder hooks: {0}")]
+    Hook(String),
+    /// Transaction was deferred by [`BuilderHooks::transaction_verdicts`] for more views
+    /// than allowed by [`DeferralPolicy::max_deferrals`]
+    #[error("Deferred by builder hooks for more than {max_deferrals} views")]
+    DeferredTooLong { max_deferrals: u32 },
+    /// Transaction was deferred by [`BuilderHooks::transaction_verdicts`], but
+    /// [`DeferralPolicy::capacity`] has been reached
+    #[error("Too many transactions deferred by builder hooks")]
+    DeferralCapacity,
+    /// Any other reason, described by the message
+    #[error("{0}")]
+    Other(String),
+}
+
+/// Verdict on a single transaction returned by [`BuilderHooks::transaction_verdicts`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionVerdict {
+    /// Pass the transaction on to the builder
+    Accept,
+    /// Reject the transaction, with the reason reported in its status
+    Reject(String),
+    /// Hold the transaction back and evaluate it again once the current view finishes
+    Defer,
+}
+
+/// Reason reported for transactions filtered out by [`BuilderHooks::process_transactions`]
+pub const FILTERED_OUT_REASON: &str = "Filtered out by builder hooks";
+
+/// A trait for hooks into the builder service. Used to further customize
+/// builder behaviour in ways not possible in builder core.
+/// If you don't need such customisation, use [`NoHooks`].
+///
+/// A simple example filtering incoming transactions based on some imaginary
+/// application-specific magic byte:
+/// ```rust
+/// # type MyTypes = hotshot_example_types::node_types::TestTypes;
+/// # type MyTransaction = hotshot_example_types::block_types::TestTransaction;
+/// use marketplace_builder_core::hooks::BuilderHooks;
+/// struct MyBuilderHooks { magic: u8 };
+///
+/// #[async_trait::async_trait]
+/// impl BuilderHooks<MyTypes> for MyBuilderHooks {
+///     async fn process_transactions(&self, transactions: Vec<MyTransaction>) -> Vec<MyTransaction> {
+///         transactions.into_iter().filter(|tx| tx.bytes()[0] == self.magic).collect()
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait BuilderHooks<Types: NodeType>: Sync + Send + 'static {
+    /// Implement this to process transactions before
+    /// they'll be passed on to the builder.
+    #[inline(always)]
+    async fn process_transactions(
+        &self,
+        transactions: Vec<Types::Transaction>,
+    ) -> Vec<Types::Transaction> {
+        transactions
+    }
+
+    /// Implement this to decide on each incoming transaction individually.
+    /// Returns transactions along with verdicts on them.
+    ///
+    /// By default, runs [`Self::process_transactions`] and accepts transactions it returns,
+    /// rejecting the ones it filtered out with [`FILTERED_OUT_REASON`].
+    async fn transaction_verdicts(
+        &self,
+        transactions: Vec<Types::Transaction>,
+    ) -> Vec<(Types::Transaction, TransactionVerdict)> {
+        let processed = self.process_transactions(transactions.clone()).await;
+        let kept = processed
+            .iter()
+            .map(Committable::commit)
+            .collect::<HashSet<_>>();
+        let filtered_out = transactions
+            .into_iter()
+            .filter(|txn| !kept.contains(&txn.commit()))
+            .map(|txn| {
+                (
+                    txn,
+                    TransactionVerdict::Reject(FILTERED_OUT_REASON.to_owned()),
+                )
+            });
+        processed
+            .into_iter()
+            .map(|txn| (txn, TransactionVerdict::Accept))
+            .chain(filtered_out)
+            .collect()
+    }
+
+    /// Handle any hotshot event _before_ the builder event loop handles it.
+    /// Event handling is done sequentially, i.e. you can rely on the fact
+    /// that the builder will process this event _after_ the hooks have finished
+    /// processing it. Accordingly, if this property is not important to you,
+    /// and especially if you're doing anything involved with this hook
+    /// it is advisable to spawn a task doing the actual work and return,
+    /// so that builder's event loop isn't blocked for too long.
+    #[inline(always)]
+    async fn handle_hotshot_event(&self, _event: &Event<Types>) {}
+
+    /// Called with transactions selected for a bundle for `view_number`
+    /// before the bundle is signed. Implement this to reorder or filter them.
+    /// Returning `None` vetoes the bundle, in which case no bundle is served.
+    #[inline(always)]
+    async fn on_bundle_assembled(
+        &self,
+        _view_number: u64,
+        transactions: Vec<Types::Transaction>,
+    ) -> Option<Vec<Types::Transaction>> {
+        Some(transactions)
+    }
+
+    /// Called after a leaf for `view_number` is decided with commitments of
+    /// transactions included in it.
+    #[inline(always)]
+    async fn on_transactions_sequenced(
+        &self,
+        _view_number: Types::View,
+        _commitments: &[Commitment<Types::Transaction>],
+    ) {
+    }
+
+    /// Called whenever the builder rejects a transaction
+    #[inline(always)]
+    async fn on_transaction_rejected(
+        &self,
+        _commitment: Commitment<Types::Transaction>,
+        _reason: &RejectionReason,
+    ) {
+    }
+}
+
+#[async_trait]
+impl<T: ?Sized, Types> BuilderHooks<Types> for Box<T>
+where
+    Types: NodeType,
+    T: BuilderHooks<Types>,
+{
+    #[inline(always)]
+    async fn process_transactions(
+        &self,
+        transactions: Vec<Types::Transaction>,
+    ) -> Vec<Types::Transaction> {
+        (**self).process_transactions(transactions).await
+    }
+
+    #[inline(always)]
+    async fn transaction_verdicts(
+        &self,
+        transactions: Vec<Types::Transaction>,
+    ) -> Vec<(Types::Transaction, TransactionVerdict)> {
+        (**self).transaction_verdicts(transactions).await
+    }
+
+    #[inline(always)]
+    async fn handle_hotshot_event(&self, event: &Event<Types>) {
+        (**self).handle_hotshot_event(event).await
+    }
+
+    #[inline(always)]
+    async fn on_bundle_assembled(
+        &self,
+        view_number: u64,
+        transactions: Vec<Types::Transaction>,
+    ) -> Option<Vec<Types::Transaction>> {
+        (**self)
+            .on_bundle_assembled(view_number, transactions)
+            .await
+    }
+
+    #[inline(always)]
+    async fn on_transactions_sequenced(
+        &self,
+        view_number: Types::View,
+        commitments: &[Commitment<Types::Transaction>],
+    ) {
+        (**self)
+            .on_transactions_sequenced(view_number, commitments)
+            .await
+    }
+
+    #[inline(always)]
+    async fn on_transaction_rejected(
+        &self,
+        commitment: Commitment<Types::Transaction>,
+        reason: &RejectionReason,
+    ) {
+        (**self).on_transaction_rejected(commitment, reason).await
+    }
+}
+
+/// Hooks that do nothing
+pub struct NoHooks<Types: NodeType>(pub PhantomData<Types>);
+
+impl<Types: NodeType> BuilderHooks<Types> for NoHooks<Types> {}
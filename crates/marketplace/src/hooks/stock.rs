@@ -0,0 +1,201 @@
+//! Ready-made [`BuilderHooks`] implementations, meant to be combined
+//! with each other and custom hooks via [`HookChain`](super::HookChain) or tuples.
+
+use std::{io, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use hotshot::types::Event;
+use hotshot_types::traits::{block_contents::Transaction, node_implementation::NodeType};
+use marketplace_builder_shared::utils::{EventRecorder, RecordingFormat};
+
+use super::{BuilderHooks, TransactionVerdict};
+
+/// Rejects transactions larger than `max_len` bytes, as reported
+/// by [`Transaction::minimum_block_size`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeFilter {
+    /// Maximum accepted transaction size
+    pub max_len: u64,
+}
+
+impl SizeFilter {
+    /// Create a filter rejecting transactions larger than `max_len` bytes
+    pub fn new(max_len: u64) -> Self {
+        Self { max_len }
+    }
+
+    fn verdict<Tx: Transaction>(&self, transaction: &Tx) -> TransactionVerdict {
+        let len = transaction.minimum_block_size();
+        if len > self.max_len {
+            TransactionVerdict::Reject(format!(
+                "Transaction too big for size filter ({len}/{})",
+                self.max_len
+            ))
+        } else {
+            TransactionVerdict::Accept
+        }
+    }
+}
+
+#[async_trait]
+impl<Types: NodeType> BuilderHooks<Types> for SizeFilter {
+    async fn process_transactions(
+        &self,
+        transactions: Vec<Types::Transaction>,
+    ) -> Vec<Types::Transaction> {
+        transactions
+            .into_iter()
+            .filter(|txn| self.verdict(txn) == TransactionVerdict::Accept)
+            .collect()
+    }
+
+    async fn transaction_verdicts(
+        &self,
+        transactions: Vec<Types::Transaction>,
+    ) -> Vec<(Types::Transaction, TransactionVerdict)> {
+        transactions
+            .into_iter()
+            .map(|txn| {
+                let verdict = self.verdict(&txn);
+                (txn, verdict)
+            })
+            .collect()
+    }
+}
+
+/// Whether [`PrefixFilter`] accepts or rejects transactions matching its prefixes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrefixFilterMode {
+    /// Accept only transactions starting with one of the prefixes
+    Allow,
+    /// Reject transactions starting with any of the prefixes
+    Deny,
+}
+
+/// Function returning the bytes of a transaction [`PrefixFilter`] matches prefixes against
+pub type TransactionBytes<Types> =
+    Arc<dyn for<'a> Fn(&'a <Types as NodeType>::Transaction) -> &'a [u8] + Send + Sync>;
+
+/// Accepts or rejects transactions based on their leading bytes, see [`PrefixFilterMode`]
+#[derive(derive_more::Debug)]
+pub struct PrefixFilter<Types: NodeType> {
+    mode: PrefixFilterMode,
+    prefixes: Vec<Vec<u8>>,
+    #[debug(skip)]
+    bytes: TransactionBytes<Types>,
+}
+
+impl<Types: NodeType> Clone for PrefixFilter<Types> {
+    fn clone(&self) -> Self {
+        Self {
+            mode: self.mode,
+            prefixes: self.prefixes.clone(),
+            bytes: Arc::clone(&self.bytes),
+        }
+    }
+}
+
+impl<Types: NodeType> PrefixFilter<Types> {
+    /// Create a filter matching `prefixes` against transaction bytes returned by `bytes`
+    pub fn new(
+        mode: PrefixFilterMode,
+        prefixes: impl IntoIterator<Item = impl Into<Vec<u8>>>,
+        bytes: impl for<'a> Fn(&'a Types::Transaction) -> &'a [u8] + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            mode,
+            prefixes: prefixes.into_iter().map(Into::into).collect(),
+            bytes: Arc::new(bytes),
+        }
+    }
+
+    /// Create a filter accepting only transactions starting with one of `prefixes`
+    pub fn allow(
+        prefixes: impl IntoIterator<Item = impl Into<Vec<u8>>>,
+        bytes: impl for<'a> Fn(&'a Types::Transaction) -> &'a [u8] + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(PrefixFilterMode::Allow, prefixes, bytes)
+    }
+
+    /// Create a filter rejecting transactions starting with any of `prefixes`
+    pub fn deny(
+        prefixes: impl IntoIterator<Item = impl Into<Vec<u8>>>,
+        bytes: impl for<'a> Fn(&'a Types::Transaction) -> &'a [u8] + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(PrefixFilterMode::Deny, prefixes, bytes)
+    }
+
+    fn verdict(&self, transaction: &Types::Transaction) -> TransactionVerdict {
+        let bytes = (self.bytes)(transaction);
+        let matches = self.prefixes.iter().any(|prefix| bytes.starts_with(prefix));
+        match (self.mode, matches) {
+            (PrefixFilterMode::Allow, true) | (PrefixFilterMode::Deny, false) => {
+                TransactionVerdict::Accept
+            }
+            (PrefixFilterMode::Allow, false) => {
+                TransactionVerdict::Reject("Transaction prefix isn't allowed".to_owned())
+            }
+            (PrefixFilterMode::Deny, true) => {
+                TransactionVerdict::Reject("Transaction prefix is denied".to_owned())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<Types: NodeType> BuilderHooks<Types> for PrefixFilter<Types> {
+    async fn process_transactions(
+        &self,
+        transactions: Vec<Types::Transaction>,
+    ) -> Vec<Types::Transaction> {
+        transactions
+            .into_iter()
+            .filter(|txn| self.verdict(txn) == TransactionVerdict::Accept)
+            .collect()
+    }
+
+    async fn transaction_verdicts(
+        &self,
+        transactions: Vec<Types::Transaction>,
+    ) -> Vec<(Types::Transaction, TransactionVerdict)> {
+        transactions
+            .into_iter()
+            .map(|txn| {
+                let verdict = self.verdict(&txn);
+                (txn, verdict)
+            })
+            .collect()
+    }
+}
+
+/// Writes every event passed to [`BuilderHooks::handle_hotshot_event`] to a file.
+/// The recording can be read back with
+/// [`read_recording`](marketplace_builder_shared::utils::read_recording).
+///
+/// Writing happens in the background, so neither handling events nor dropping
+/// the logger blocks; see [`EventRecorder`] for details.
+#[derive(Debug)]
+pub struct EventLogger<Types: NodeType> {
+    recorder: EventRecorder<Types>,
+}
+
+impl<Types: NodeType> EventLogger<Types> {
+    /// Start logging events to a file at `path`. The file is truncated if it exists.
+    pub fn new(path: impl AsRef<Path>, format: RecordingFormat) -> io::Result<Self> {
+        Ok(Self {
+            recorder: EventRecorder::new(path, format)?,
+        })
+    }
+
+    /// Stop logging and wait for logged events to be written
+    pub async fn finish(self) {
+        self.recorder.finish().await;
+    }
+}
+
+#[async_trait]
+impl<Types: NodeType> BuilderHooks<Types> for EventLogger<Types> {
+    async fn handle_hotshot_event(&self, event: &Event<Types>) {
+        self.recorder.record(event);
+    }
+}
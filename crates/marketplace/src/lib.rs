@@ -4,9 +4,16 @@
 //!
 //! It also provides one API service to external users:
 //! 1. Serves a user's request to submit a private transaction
+//!
+//! Optionally, it bids for upcoming views in the solver auction.
 #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
 
+pub mod auth;
+pub mod bidding;
+pub mod bundle_cache;
+pub mod fee;
 pub mod hooks;
+pub mod namespace;
 pub mod service;
 
 // tracking the testing
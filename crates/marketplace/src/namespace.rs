@@ -0,0 +1,48 @@
+//! Restricting the builder to the namespaces it serves.
+//!
+//! In the marketplace, builders win auctions for specific namespaces.
+//! [`NamespaceConfig`] describes which namespaces this builder serves and how to
+//! tell which namespace a transaction belongs to.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use hotshot_types::traits::node_implementation::NodeType;
+
+/// Identifier of a namespace (rollup) bundles are built for
+pub type NamespaceId = u64;
+
+/// Function extracting the namespace a transaction belongs to
+pub type NamespaceExtractor<Types> =
+    Arc<dyn Fn(&<Types as NodeType>::Transaction) -> NamespaceId + Send + Sync>;
+
+/// Namespaces served by the builder
+#[derive(derive_more::Debug)]
+pub struct NamespaceConfig<Types: NodeType> {
+    /// Namespaces this builder builds bundles for
+    pub served: BTreeSet<NamespaceId>,
+    /// Extracts the namespace of a transaction
+    #[debug(skip)]
+    pub namespace_of: NamespaceExtractor<Types>,
+}
+
+impl<Types: NodeType> Clone for NamespaceConfig<Types> {
+    fn clone(&self) -> Self {
+        Self {
+            served: self.served.clone(),
+            namespace_of: Arc::clone(&self.namespace_of),
+        }
+    }
+}
+
+impl<Types: NodeType> NamespaceConfig<Types> {
+    /// Returns the namespace of `transaction` if it isn't served by this builder
+    pub fn unserved_namespace(&self, transaction: &Types::Transaction) -> Option<NamespaceId> {
+        let namespace = (self.namespace_of)(transaction);
+        (!self.served.contains(&namespace)).then_some(namespace)
+    }
+
+    /// Whether `transaction` belongs to a namespace served by this builder
+    pub fn serves(&self, transaction: &Types::Transaction) -> bool {
+        self.unserved_namespace(transaction).is_none()
+    }
+}
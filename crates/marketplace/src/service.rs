@@ -1,30 +1,47 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use marketplace_builder_shared::{
     block::{BuilderStateId, ReceivedTransaction, TransactionSource},
-    coordinator::{BuilderStateCoordinator, BuilderStateLookup},
-    state::BuilderState,
-    utils::BuilderKeys,
+    block_size_limits::BlockSizeLimits,
+    coordinator::{
+        offers::OfferPolicy, proposal_validation::LeaderOracle, BuilderStateCoordinator,
+        BuilderStateLookup,
+    },
+    error::Error,
+    fee_ledger::{self, FeeLedger, FeeLedgerDataSource, FeeSubject, FEE_LEDGER_MODULE},
+    inclusion_estimate::{
+        self, InclusionEstimate, InclusionEstimateDataSource, InclusionEstimator,
+        INCLUSION_ESTIMATE_MODULE,
+    },
+    shutdown::{Shutdown, ShutdownPhase},
+    state::{BuilderState, QueueStatistics},
+    submit_limit::{
+        self, PrivateMempoolDataSource, SubmitLimiter, SubmitLimiterMetrics, SubmitLimits,
+        ANONYMOUS_CLIENT, PRIVATE_MEMPOOL_MODULE,
+    },
+    utils::{BuilderKeys, GapCallback},
 };
 
 pub use async_broadcast::{broadcast, RecvError, TryRecvError};
+use async_lock::{Mutex, RwLock};
 use async_trait::async_trait;
 use committable::{Commitment, Committable};
-use futures::{future::BoxFuture, stream::FuturesUnordered, Stream};
+use futures::stream::{FuturesOrdered, StreamExt};
 use futures::{
-    stream::{FuturesOrdered, StreamExt},
-    TryStreamExt,
+    future::{BoxFuture, Future},
+    stream::FuturesUnordered,
+    FutureExt, Stream,
 };
 use hotshot::types::Event;
 use hotshot_builder_api::{
     v0_2::builder::TransactionStatus,
     v0_99::{
-        builder::{define_api, submit_api, BuildError, Error as BuilderApiError},
+        builder::{define_api, BuildError, Error as BuilderApiError},
         data_source::{AcceptsTxnSubmits, BuilderDataSource},
     },
 };
 use hotshot_types::bundle::Bundle;
-use hotshot_types::traits::block_contents::{BuilderFee, Transaction};
+use hotshot_types::traits::block_contents::{BlockHeader, BlockPayload, BuilderFee};
 use hotshot_types::{
     event::EventType,
     traits::{
@@ -36,14 +53,59 @@ use hotshot_types::{
 use std::sync::Arc;
 use std::{fmt::Display, time::Instant};
 use tagged_base64::TaggedBase64;
-use tide_disco::{app::AppError, method::ReadState, App};
+use tide_disco::{app::AppError, method::ReadState, Api, App};
 use tokio::{spawn, task::JoinHandle, time::sleep};
 use tracing::Level;
 use vbs::version::StaticVersion;
 
 pub use marketplace_builder_shared::utils::EventServiceStream;
 
-use crate::hooks::BuilderHooks;
+use crate::{
+    auth::{BundleAuth, BundleAuthConfig, AUTHENTICATED_BUNDLE_MODULE},
+    bidding::{BidConfig, Bidder},
+    bundle_cache::{BundleCache, BundleCachePolicy},
+    fee::{FeeContext, FeeStrategy, FixedFee},
+    hooks::{
+        deferred::DeferredTransactions, BuilderHooks, DeferralPolicy, RejectionReason,
+        TransactionVerdict,
+    },
+    namespace::NamespaceConfig,
+};
+
+/// Definition of the API served in [`AUTHENTICATED_BUNDLE_MODULE`]
+const AUTHENTICATED_BUNDLE_API: &str = r#"
+[route.bundle]
+PATH = ["bundle/:parent_view/:parent_hash/:view_number/:sender/:signature"]
+":parent_view" = "Integer"
+":parent_hash" = "TaggedBase64"
+":view_number" = "Integer"
+":sender" = "TaggedBase64"
+":signature" = "TaggedBase64"
+METHOD = "GET"
+DOC = "Get a bundle for `view_number` built on top of `parent_hash` from `parent_view`, signed by `sender`"
+"#;
+
+/// Definition of the API served in place of HotShot's marketplace builder API
+/// when bundle requests are authenticated. Mirrors HotShot's routes.
+const CALLER_AWARE_BUILDER_API: &str = r#"
+[route.bundle]
+PATH = ["bundle/:parent_view/:parent_hash/:view_number"]
+":parent_view" = "Integer"
+":parent_hash" = "TaggedBase64"
+":view_number" = "Integer"
+METHOD = "GET"
+DOC = "Get a bundle for `view_number` built on top of `parent_hash` from `parent_view`"
+
+[route.builder_address]
+PATH = ["builderaddress"]
+METHOD = "GET"
+DOC = "Get the builder's public key"
+"#;
+
+/// We will not increment max bundle size if we aren't able to serve a response
+/// with a margin below [`GlobalState::api_timeout`]
+/// more than [`GlobalState::api_timeout`] / `RESPONSE_TARGET_MARGIN_DIVISOR`
+const RESPONSE_TARGET_MARGIN_DIVISOR: u32 = 10;
 
 /// Configuration to initialize the builder
 #[derive(Debug, Clone)]
@@ -58,6 +120,12 @@ pub struct BuilderConfig<Types: NodeType> {
     /// `available_blocks` API call if the builder doesn't have any transactions at the moment
     /// of the call. Should be less than [`Self::api_timeout`]
     pub tx_capture_timeout: Duration,
+    /// Maximum bundle size allowed by the protocol. Bundles will never be larger than this,
+    /// but may be limited further if the builder struggles to serve them in time.
+    pub protocol_max_bundle_size: u64,
+    /// Interval at which the builder will optimistically increment its maximum
+    /// allowed bundle size in case it becomes lower than the protocol maximum.
+    pub max_bundle_size_increment_period: Duration,
     /// (Approximate) duration over which included transaction hashes will be stored
     /// by the builder for deduplication of incoming transactions.
     pub txn_garbage_collect_duration: Duration,
@@ -65,8 +133,36 @@ pub struct BuilderConfig<Types: NodeType> {
     pub txn_channel_capacity: usize,
     /// Capacity of cache storing information for transaction status API
     pub tx_status_cache_capacity: usize,
+    /// If set, proposals from senders other than the leader for the proposal's view
+    /// will be rejected
+    pub leader_oracle: Option<LeaderOracle<Types>>,
     /// Base fee; the sequencing fee for a bundle is calculated as bundle size × base fee
+    /// unless [`Self::fee_strategy`] is set
     pub base_fee: u64,
+    /// Strategy determining the fee offered for bundles.
+    /// Defaults to [`FixedFee`] with [`Self::base_fee`].
+    pub fee_strategy: Option<Arc<dyn FeeStrategy<Types>>>,
+    /// If set, the builder will bid for upcoming views in the solver auction
+    pub bid_config: Option<BidConfig>,
+    /// Namespaces this builder serves. If set, bundles will only include transactions
+    /// from these namespaces and private submissions for other namespaces will be rejected.
+    /// If unset, transactions from all namespaces are accepted.
+    pub namespaces: Option<NamespaceConfig<Types>>,
+    /// Determines for how long duplicate bundle requests are served cached bundles
+    pub bundle_cache: BundleCachePolicy,
+    /// Whether transactions already offered in bundles for other views
+    /// may be offered again before those views are decided
+    pub offer_policy: OfferPolicy,
+    /// If set, proposers are expected to sign bundle requests and anonymous requests
+    /// are rate-limited or rejected, see [`crate::auth`]
+    pub bundle_auth: Option<BundleAuthConfig<Types>>,
+    /// Limits on private mempool submissions, see [`SubmitLimits`]
+    pub submit_limits: SubmitLimits,
+    /// Limits on transactions deferred by [`BuilderHooks::transaction_verdicts`]
+    pub deferral_policy: DeferralPolicy,
+    /// Period for which queue statistics and latencies inclusion estimates are based on
+    /// are reused, see [`InclusionEstimator`]
+    pub estimate_refresh_period: Duration,
 }
 
 /// The main type implementing the marketplace builder.
@@ -84,10 +180,29 @@ where
     /// Maximum time we're allowed to expend waiting for more transactions to
     /// arrive when serving a bundle.
     tx_capture_timeout: Duration,
-    /// Base fee per bundle byte
-    base_fee: u64,
+    /// Limits on bundle size. See [`BlockSizeLimits`] documentation for more details.
+    pub(crate) block_size_limits: Arc<BlockSizeLimits>,
+    /// Strategy determining the fee offered for bundles
+    fee_strategy: Arc<dyn FeeStrategy<Types>>,
+    /// Submits bids to the solver, if bidding is enabled
+    bidder: Option<Arc<Bidder<Types>>>,
+    /// Namespaces this builder serves, see [`BuilderConfig::namespaces`]
+    namespaces: Option<NamespaceConfig<Types>>,
+    /// Bundles we've already served, see [`BuilderConfig::bundle_cache`]
+    bundle_cache: Arc<RwLock<BundleCache<Types>>>,
+    /// Authentication of bundle requests, see [`BuilderConfig::bundle_auth`]
+    bundle_auth: Option<BundleAuth<Types>>,
+    /// Transactions deferred by [`BuilderHooks::transaction_verdicts`],
+    /// to be evaluated again once the current view finishes
+    deferred: Arc<Mutex<DeferredTransactions<Types>>>,
     /// See [`BuilderHooks`] for more information
     hooks: Arc<Hooks>,
+    /// Graceful shutdown of the builder, see [`Self::shutdown`]
+    shutdown: Shutdown,
+    /// Enforces [`BuilderConfig::submit_limits`]
+    submit_limiter: SubmitLimiter,
+    /// Inputs of inclusion estimates, see [`BuilderConfig::estimate_refresh_period`]
+    estimator: InclusionEstimator,
 }
 
 #[cfg(test)]
@@ -101,9 +216,22 @@ impl<Types: NodeType> BuilderConfig<Types> {
                 ),
             api_timeout: TEST_API_TIMEOUT,
             tx_capture_timeout: TEST_MAXIMIZE_TX_CAPTURE_TIMEOUT,
+            protocol_max_bundle_size: TEST_PROTOCOL_MAX_BLOCK_SIZE,
+            max_bundle_size_increment_period: TEST_MAX_BLOCK_SIZE_INCREMENT_PERIOD,
             txn_garbage_collect_duration: TEST_INCLUDED_TX_GC_PERIOD,
             txn_channel_capacity: TEST_CHANNEL_BUFFER_SIZE,
             base_fee: TEST_BASE_FEE,
+            fee_strategy: None,
+            bid_config: None,
+            namespaces: None,
+            bundle_cache: BundleCachePolicy::default(),
+            offer_policy: OfferPolicy::default(),
+            bundle_auth: None,
+            submit_limits: SubmitLimits::default(),
+            deferral_policy: DeferralPolicy::default(),
+            // Tests expect estimates to reflect the queue immediately
+            estimate_refresh_period: Duration::ZERO,
+            leader_oracle: None,
             tx_status_cache_capacity: TEST_TX_STATUS_CACHE_CAPACITY,
         }
     }
@@ -119,48 +247,223 @@ where
     for<'a> <Types::SignatureKey as TryFrom<&'a TaggedBase64>>::Error: Display,
 {
     pub fn new(config: BuilderConfig<Types>, hooks: Hooks) -> Arc<Self> {
-        let coordinator = BuilderStateCoordinator::new(
+        let mut coordinator = BuilderStateCoordinator::new(
             config.txn_channel_capacity,
             config.txn_garbage_collect_duration,
             config.tx_status_cache_capacity,
-        );
+        )
+        .with_offer_policy(config.offer_policy);
+        if let Some(leader_oracle) = config.leader_oracle.clone() {
+            coordinator = coordinator.with_leader_oracle(leader_oracle);
+        }
+        let bidder = config
+            .bid_config
+            .map(|bid_config| Arc::new(Bidder::new(bid_config, config.builder_keys.clone())));
         Arc::new(Self {
             hooks: Arc::new(hooks),
+            deferred: Arc::new(Mutex::new(DeferredTransactions::new(
+                config.deferral_policy,
+            ))),
+            shutdown: Shutdown::new(),
+            submit_limiter: SubmitLimiter::new(config.submit_limits),
+            estimator: InclusionEstimator::new(
+                config.estimate_refresh_period,
+                config.tx_capture_timeout,
+            ),
             coordinator: Arc::new(coordinator),
+            bidder,
+            namespaces: config.namespaces,
+            bundle_cache: Arc::new(RwLock::new(BundleCache::new(config.bundle_cache))),
+            bundle_auth: config
+                .bundle_auth
+                .map(|auth| BundleAuth::new(auth, config.leader_oracle)),
             builder_keys: config.builder_keys,
             api_timeout: config.api_timeout,
             tx_capture_timeout: config.tx_capture_timeout,
-            base_fee: config.base_fee,
+            block_size_limits: Arc::new(BlockSizeLimits::new(
+                config.protocol_max_bundle_size,
+                config.max_bundle_size_increment_period,
+            )),
+            fee_strategy: config.fee_strategy.unwrap_or_else(|| {
+                Arc::new(FixedFee {
+                    base_fee: config.base_fee,
+                })
+            }),
         })
     }
 
-    /// Consumes `self` and returns a `tide_disco` [`App`] with builder and private mempool APIs registered
+    /// Consumes `self` and returns a `tide_disco` [`App`] with builder, private mempool,
+    /// fee ledger and inclusion estimate APIs registered.
+    /// Private mempool is served by [`submit_limit::define_api`] instead of HotShot's
+    /// `txn_submit` API, so that submission limits apply to every client separately.
+    /// If [`BuilderConfig::bundle_auth`] is set, authenticated bundle API is registered as well,
+    /// and the builder API is served by [`Self::caller_aware_builder_api`].
+    /// Serve the app with [`RetryAfterListener`](marketplace_builder_shared::utils::RetryAfterListener)
+    /// for rate-limited requests to get a `Retry-After` header.
     pub fn into_app(
         self: Arc<Self>,
     ) -> Result<App<ProxyGlobalState<Types, Hooks>, BuilderApiError>, AppError> {
+        let authenticate = self.bundle_auth.is_some();
         let proxy = ProxyGlobalState(self);
-        let builder_api = define_api::<ProxyGlobalState<Types, Hooks>, Types>(&Default::default())?;
+        let mut app: App<ProxyGlobalState<Types, Hooks>, BuilderApiError> = App::with_state(proxy);
 
-        // TODO: Replace StaticVersion with proper constant when added in HotShot
-        let private_mempool_api =
-            submit_api::<ProxyGlobalState<Types, Hooks>, Types, StaticVersion<0, 1>>(
-                &Default::default(),
+        if authenticate {
+            app.register_module(
+                hotshot_types::constants::MARKETPLACE_BUILDER_MODULE,
+                Self::caller_aware_builder_api()?,
             )?;
+        } else {
+            app.register_module(
+                hotshot_types::constants::MARKETPLACE_BUILDER_MODULE,
+                define_api::<ProxyGlobalState<Types, Hooks>, Types>(&Default::default())?,
+            )?;
+        }
 
-        let mut app: App<ProxyGlobalState<Types, Hooks>, BuilderApiError> = App::with_state(proxy);
+        app.register_module(
+            PRIVATE_MEMPOOL_MODULE,
+            submit_limit::define_api::<ProxyGlobalState<Types, Hooks>, Types, BuilderApiError>()?,
+        )?;
+
+        app.register_module(
+            FEE_LEDGER_MODULE,
+            fee_ledger::define_api::<ProxyGlobalState<Types, Hooks>, Types, BuilderApiError>()?,
+        )?;
 
         app.register_module(
-            hotshot_types::constants::MARKETPLACE_BUILDER_MODULE,
-            builder_api,
+            INCLUSION_ESTIMATE_MODULE,
+            inclusion_estimate::define_api::<ProxyGlobalState<Types, Hooks>, BuilderApiError>()?,
         )?;
 
-        app.register_module("txn_submit", private_mempool_api)?;
+        if authenticate {
+            app.register_module(
+                AUTHENTICATED_BUNDLE_MODULE,
+                Self::authenticated_bundle_api()?,
+            )?;
+        }
 
         Ok(app)
     }
 
+    /// API serving bundles to proposers signing their requests, see [`ProxyGlobalState::authenticated_bundle`]
+    fn authenticated_bundle_api(
+    ) -> Result<Api<ProxyGlobalState<Types, Hooks>, BuilderApiError, StaticVersion<0, 1>>, AppError>
+    {
+        let mut api = Api::new(
+            toml::from_str::<toml::Value>(AUTHENTICATED_BUNDLE_API)
+                .expect("Authenticated bundle API definition should be valid TOML"),
+        )?;
+        api.get("bundle", |req, state| {
+            async move {
+                let parent_view = req.integer_param("parent_view")?;
+                let parent_hash: VidCommitment = req.blob_param("parent_hash")?;
+                let view_number = req.integer_param("view_number")?;
+                let sender: Types::SignatureKey = req.blob_param("sender")?;
+                let signature: <Types::SignatureKey as SignatureKey>::PureAssembledSignatureType =
+                    req.blob_param("signature")?;
+                state
+                    .authenticated_bundle(
+                        parent_view,
+                        &parent_hash,
+                        view_number,
+                        &sender,
+                        &signature,
+                    )
+                    .await
+            }
+            .boxed()
+        })?;
+        Ok(api)
+    }
+
+    /// Builder API with the same routes as HotShot's, but with handlers aware of the caller,
+    /// so that anonymous callers are rate-limited individually by IP address and
+    /// rejected requests get a status telling why, see [`ProxyGlobalState::anonymous_bundle`].
+    /// HotShot's handlers can't be replaced once registered, so the routes are declared
+    /// in [`CALLER_AWARE_BUILDER_API`] and served under HotShot's API version.
+    fn caller_aware_builder_api() -> Result<
+        Api<ProxyGlobalState<Types, Hooks>, BuilderApiError, hotshot_builder_api::v0_99::Version>,
+        AppError,
+    > {
+        let mut api = Api::new(
+            toml::from_str::<toml::Value>(CALLER_AWARE_BUILDER_API)
+                .expect("Builder API definition should be valid TOML"),
+        )?;
+        api.get("bundle", |req, state| {
+            async move {
+                let parent_view = req.integer_param("parent_view")?;
+                let parent_hash: VidCommitment = req.blob_param("parent_hash")?;
+                let view_number = req.integer_param("view_number")?;
+                let caller = submit_limit::remote_client(req.remote());
+                state
+                    .anonymous_bundle(&caller, parent_view, &parent_hash, view_number)
+                    .await
+            }
+            .boxed()
+        })?
+        .get("builder_address", |_req, state| {
+            async move { Ok(state.0.builder_keys.0.clone()) }.boxed()
+        })?;
+        Ok(api)
+    }
+
+    /// Handle to the shutdown of this builder, see [`Self::shutdown`]
+    pub fn shutdown_handle(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    /// Shut the builder down gracefully. New private submissions are rejected with
+    /// [`Error::ShuttingDown`], while public transactions from HotShot's mempool are
+    /// still accepted, in-flight bundle requests are allowed to finish,
+    /// and then the event loop is stopped. Returns transactions that were pending
+    /// at that point, including ones deferred by hooks, so that they can be
+    /// persisted or submitted elsewhere.
+    pub async fn shutdown(&self) -> Vec<Types::Transaction> {
+        tracing::info!("Shutting down, waiting for in-flight requests");
+        self.shutdown.begin();
+        self.shutdown.drained().await;
+
+        let mut pending: Vec<_> = self
+            .coordinator
+            .pending_transactions()
+            .await
+            .into_iter()
+            .map(|txn| txn.transaction.clone())
+            .collect();
+        pending.extend(
+            self.deferred
+                .lock()
+                .await
+                .take()
+                .into_iter()
+                .map(|(txn, _)| txn.transaction),
+        );
+        tracing::info!(num_pending = pending.len(), "Stopping event loop");
+        self.shutdown.stop();
+
+        pending
+    }
+
+    /// Spawns a task that shuts the builder down once `signal` resolves, e.g.
+    /// `CancellationToken::cancelled_owned`. See [`Self::shutdown`] for details.
+    /// Returns a handle resolving to transactions that were pending.
+    pub fn shutdown_on(
+        self: Arc<Self>,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> JoinHandle<Vec<Types::Transaction>> {
+        spawn(async move {
+            signal.await;
+            self.shutdown().await
+        })
+    }
+
+    /// Current state of the private mempool submission limiter
+    pub fn submit_limiter_metrics(&self) -> SubmitLimiterMetrics {
+        self.submit_limiter.metrics()
+    }
+
     /// Spawns an event loop handling HotShot events from the provided stream.
-    /// Returns a handle for the spawned task.
+    /// Returns a handle for the spawned task, which resolves once the builder
+    /// is shut down or the stream ends.
     pub fn start_event_loop(
         &self,
         event_stream: impl Stream<Item = Event<Types>> + Unpin + Send + 'static,
@@ -168,22 +471,48 @@ where
         spawn(Self::event_loop(
             Arc::clone(&self.coordinator),
             Arc::clone(&self.hooks),
+            Arc::clone(&self.block_size_limits),
+            Arc::clone(&self.bundle_cache),
+            Arc::clone(&self.deferred),
+            self.bidder.clone(),
+            self.shutdown.clone(),
             event_stream,
         ))
     }
 
+    /// Returns a callback re-anchoring builder states once the events stream
+    /// reports views lost while reconnecting, see
+    /// [`BuilderStateCoordinator::event_gap_handler`]
+    pub fn event_gap_handler(&self) -> GapCallback {
+        self.coordinator.event_gap_handler()
+    }
+
     /// Internal implementation of the event loop, drives the underlying coordinator
-    /// and runs hooks
+    /// and runs hooks. If `bidder` is set, bids for upcoming views as views finish.
+    /// Public transactions are ignored once `shutdown` begins, and the loop exits once it's stopped.
+    #[allow(clippy::too_many_arguments)]
     async fn event_loop(
         coordinator: Arc<BuilderStateCoordinator<Types>>,
         hooks: Arc<Hooks>,
-        mut event_stream: impl Stream<Item = Event<Types>> + Unpin + Send + 'static,
+        block_size_limits: Arc<BlockSizeLimits>,
+        bundle_cache: Arc<RwLock<BundleCache<Types>>>,
+        deferred: Arc<Mutex<DeferredTransactions<Types>>>,
+        bidder: Option<Arc<Bidder<Types>>>,
+        shutdown: Shutdown,
+        event_stream: impl Stream<Item = Event<Types>> + Unpin + Send + 'static,
     ) -> anyhow::Result<()> {
+        let mut event_stream =
+            event_stream.take_until(Box::pin(shutdown.reached(ShutdownPhase::Stopped)));
         loop {
             let Some(event) = event_stream.next().await else {
+                if shutdown.phase() == ShutdownPhase::Stopped {
+                    tracing::info!("Event loop stopped");
+                    return Ok(());
+                }
                 anyhow::bail!("Event stream ended");
             };
 
+            coordinator.handle_pending_event_gap().await;
             hooks.handle_hotshot_event(&event).await;
 
             match event.event {
@@ -193,16 +522,39 @@ where
                 EventType::Transactions { transactions } => {
                     let hooks = Arc::clone(&hooks);
                     let coordinator = Arc::clone(&coordinator);
+                    let block_size_limits = Arc::clone(&block_size_limits);
+                    let deferred = Arc::clone(&deferred);
                     spawn(async move {
-                        let transactions = hooks.process_transactions(transactions).await;
+                        let transactions = transactions
+                            .into_iter()
+                            .map(|txn| ReceivedTransaction::new(txn, TransactionSource::Public))
+                            .collect();
+                        let verdicts = apply_verdicts(
+                            &coordinator,
+                            hooks.as_ref(),
+                            TransactionSource::Public,
+                            transactions,
+                        )
+                        .await;
+                        defer_transactions(
+                            &coordinator,
+                            hooks.as_ref(),
+                            &deferred,
+                            verdicts.deferred.into_iter().map(|txn| (txn, 1)).collect(),
+                            false,
+                        )
+                        .await;
 
-                        let _ = transactions
+                        let _ = verdicts
+                            .accepted
                             .into_iter()
                             .map(|txn| {
-                                coordinator.handle_transaction(ReceivedTransaction::new(
+                                handle_transaction(
+                                    &coordinator,
+                                    &block_size_limits,
+                                    hooks.as_ref(),
                                     txn,
-                                    TransactionSource::Public,
-                                ))
+                                )
                             })
                             .collect::<FuturesUnordered<_>>()
                             .collect::<Vec<_>>()
@@ -210,34 +562,148 @@ where
                     });
                 }
                 EventType::Decide { leaf_chain, .. } => {
+                    let prune_cutoff = leaf_chain[0].leaf.view_number();
+
+                    let hooks = Arc::clone(&hooks);
+                    let decided = Arc::clone(&leaf_chain);
+                    spawn(async move {
+                        for leaf_info in decided.iter() {
+                            let Some(payload) = leaf_info.leaf.block_payload() else {
+                                continue;
+                            };
+                            let commitments = payload
+                                .transaction_commitments(leaf_info.leaf.block_header().metadata());
+                            hooks
+                                .on_transactions_sequenced(
+                                    leaf_info.leaf.view_number(),
+                                    &commitments,
+                                )
+                                .await;
+                        }
+                    });
+
                     let coordinator = Arc::clone(&coordinator);
                     spawn(async move { coordinator.handle_decide(leaf_chain).await });
+
+                    let bundle_cache = Arc::clone(&bundle_cache);
+                    spawn(async move { bundle_cache.write().await.prune(prune_cutoff) });
+                }
+                EventType::DaProposal { proposal, sender } => {
+                    let coordinator = Arc::clone(&coordinator);
+                    spawn(async move {
+                        coordinator
+                            .handle_signed_da_proposal(proposal, sender)
+                            .await
+                    });
                 }
-                EventType::DaProposal { proposal, .. } => {
+                EventType::QuorumProposal { proposal, sender } => {
                     let coordinator = Arc::clone(&coordinator);
-                    spawn(async move { coordinator.handle_da_proposal(proposal.data).await });
+                    spawn(async move {
+                        coordinator
+                            .handle_signed_quorum_proposal(proposal, sender)
+                            .await
+                    });
                 }
-                EventType::QuorumProposal { proposal, .. } => {
+                EventType::ViewFinished { view_number } => {
+                    let hooks = Arc::clone(&hooks);
                     let coordinator = Arc::clone(&coordinator);
-                    spawn(async move { coordinator.handle_quorum_proposal(proposal.data).await });
+                    let block_size_limits = Arc::clone(&block_size_limits);
+                    let deferred = Arc::clone(&deferred);
+                    let shutdown = shutdown.clone();
+                    spawn(async move {
+                        // Give deferred transactions another chance,
+                        // unless they're about to be exported on shutdown
+                        if shutdown.is_shutting_down() {
+                            return;
+                        }
+                        let transactions = deferred.lock().await.take();
+                        let deferrals = transactions
+                            .iter()
+                            .map(|(txn, deferrals)| (txn.commit, *deferrals))
+                            .collect::<HashMap<_, _>>();
+                        // Evaluate transactions from each source separately, so that
+                        // transactions created by hooks are attributed correctly
+                        let (private, public): (Vec<_>, Vec<_>) = transactions
+                            .into_iter()
+                            .map(|(txn, _)| txn)
+                            .partition(|txn| txn.source == TransactionSource::Private);
+                        for (source, transactions) in [
+                            (TransactionSource::Private, private),
+                            (TransactionSource::Public, public),
+                        ] {
+                            if transactions.is_empty() {
+                                continue;
+                            }
+                            let verdicts =
+                                apply_verdicts(&coordinator, hooks.as_ref(), source, transactions)
+                                    .await;
+                            defer_transactions(
+                                &coordinator,
+                                hooks.as_ref(),
+                                &deferred,
+                                verdicts
+                                    .deferred
+                                    .into_iter()
+                                    .map(|txn| {
+                                        let previous =
+                                            deferrals.get(&txn.commit).copied().unwrap_or(0);
+                                        (txn, previous + 1)
+                                    })
+                                    .collect(),
+                                false,
+                            )
+                            .await;
+                            for txn in verdicts.accepted {
+                                let _ = handle_transaction(
+                                    &coordinator,
+                                    &block_size_limits,
+                                    hooks.as_ref(),
+                                    txn,
+                                )
+                                .await;
+                            }
+                        }
+                    });
+
+                    if let Some(bidder) = bidder.as_ref().map(Arc::clone) {
+                        spawn(async move { bidder.handle_view_finished(view_number).await });
+                    }
                 }
                 _ => {}
             }
         }
     }
 
-    /// Collect transactions to include in the bundle. Will wait until we have
-    /// at least one transaction or up to the configured `tx_capture_timeout` duration elapses.
+    /// Collect transactions to include in the bundle. Will wait until we have at least one
+    /// transaction from a served namespace or up to the configured `tx_capture_timeout`
+    /// duration elapses.
+    /// Only transactions from namespaces served by the builder and not excluded by
+    /// [`BuilderConfig::offer_policy`] are included, up to the current maximum bundle size.
+    /// Returns collected transactions and whether any were left out because of the size limit.
     #[tracing::instrument(skip_all, fields(builder_parent_block_references = %state.parent_block_references))]
     async fn collect_transactions(
         &self,
         state: &Arc<BuilderState<Types>>,
-    ) -> Option<Vec<Types::Transaction>> {
+        view_number: Types::View,
+    ) -> (Vec<Types::Transaction>, bool) {
+        let serves = |transaction: &Types::Transaction| {
+            self.namespaces
+                .as_ref()
+                .is_none_or(|namespaces| namespaces.serves(transaction))
+        };
+
         // collect all the transactions from the near future
         let timeout_after = Instant::now() + self.tx_capture_timeout;
         let sleep_interval = self.tx_capture_timeout / 10;
         while Instant::now() <= timeout_after {
-            let queue_populated = state.collect_txns(timeout_after).await;
+            // Transactions from namespaces we don't serve don't count, as they won't be included
+            let queue_populated = state.collect_txns(timeout_after).await
+                && state
+                    .txn_queue
+                    .read()
+                    .await
+                    .iter()
+                    .any(|txn| serves(&txn.transaction));
 
             if queue_populated || Instant::now() + sleep_interval > timeout_after {
                 // we don't have time for another iteration
@@ -247,28 +713,40 @@ where
             sleep(sleep_interval).await
         }
 
-        let transactions = state
-            .txn_queue
-            .read()
-            .await
-            .iter()
-            .map(|txn| txn.transaction.clone())
-            .collect();
+        let excluded = self.coordinator.excluded_offers(view_number).await;
+        let max_bundle_size = self.block_size_limits.max_block_size();
+        let mut transactions = Vec::new();
+        let mut total_size = 0;
+        let mut truncated = false;
+        for txn in state.txn_queue.read().await.iter() {
+            if !serves(&txn.transaction) || excluded.contains(&txn.commit) {
+                continue;
+            }
+            total_size += txn.min_block_size;
+            // We will include one transaction over our target bundle size
+            // if it's the first eligible transaction in queue, so that it doesn't
+            // get stuck in queue forever
+            if total_size > max_bundle_size && !transactions.is_empty() {
+                truncated = true;
+                break;
+            }
+            transactions.push(txn.transaction.clone());
+        }
 
-        Some(transactions)
+        (transactions, truncated)
     }
 
-    /// Assembles a [`Bundle`] for a certain view from a list of transactions by adding fee and signature
+    /// Assembles a [`Bundle`] for a certain view from a list of transactions by adding fee and signature.
+    /// The fee is determined by [`BuilderConfig::fee_strategy`].
     async fn assemble_bundle(
         &self,
         transactions: Vec<Types::Transaction>,
+        queue: &QueueStatistics,
         view_number: u64,
     ) -> Result<Bundle<Types>, BuildError> {
-        let bundle_size: u64 = transactions
-            .iter()
-            .map(|txn| txn.minimum_block_size())
-            .sum();
-        let offered_fee = self.base_fee * bundle_size;
+        let offered_fee =
+            self.fee_strategy
+                .offered_fee(&FeeContext::new(&transactions, queue, view_number));
 
         let fee_signature =
             <Types::BuilderSignatureKey as BuilderSignatureKey>::sign_sequencing_fee_marketplace(
@@ -303,6 +781,159 @@ where
     }
 }
 
+/// Transactions sorted by [`BuilderHooks::transaction_verdicts`]
+struct Verdicts<Types: NodeType> {
+    accepted: Vec<ReceivedTransaction<Types>>,
+    deferred: Vec<ReceivedTransaction<Types>>,
+    /// Rejected transactions with reasons given by hooks
+    rejected: HashMap<Commitment<Types::Transaction>, String>,
+}
+
+/// Run `transactions` through [`BuilderHooks::transaction_verdicts`].
+/// Rejected transactions are marked as such and reported to `hooks`. Transactions
+/// returned by hooks that weren't passed to them are attributed to `source`.
+async fn apply_verdicts<Types: NodeType, Hooks: BuilderHooks<Types>>(
+    coordinator: &BuilderStateCoordinator<Types>,
+    hooks: &Hooks,
+    source: TransactionSource,
+    transactions: Vec<ReceivedTransaction<Types>>,
+) -> Verdicts<Types> {
+    let verdicts = hooks
+        .transaction_verdicts(
+            transactions
+                .iter()
+                .map(|txn| txn.transaction.clone())
+                .collect(),
+        )
+        .await;
+    // Hooks may return transactions in different order, so we need to be able
+    // to look up the originals to preserve their receipt information
+    let mut received = transactions
+        .into_iter()
+        .map(|txn| (txn.commit, txn))
+        .collect::<HashMap<_, _>>();
+
+    let mut result = Verdicts {
+        accepted: Vec::new(),
+        deferred: Vec::new(),
+        rejected: HashMap::new(),
+    };
+    for (txn, verdict) in verdicts {
+        let commit = txn.commit();
+        let txn = received
+            .remove(&commit)
+            .unwrap_or_else(|| ReceivedTransaction::new(txn, source.clone()));
+        match verdict {
+            TransactionVerdict::Accept => result.accepted.push(txn),
+            TransactionVerdict::Reject(reason) => {
+                tracing::debug!(%commit, %reason, "Transaction rejected by hooks");
+                coordinator.update_txn_status(
+                    &commit,
+                    TransactionStatus::Rejected {
+                        reason: reason.clone(),
+                    },
+                );
+                hooks
+                    .on_transaction_rejected(commit, &RejectionReason::Hook(reason.clone()))
+                    .await;
+                result.rejected.insert(commit, reason);
+            }
+            TransactionVerdict::Defer => result.deferred.push(txn),
+        }
+    }
+    result
+}
+
+/// Hold `transactions` back until the current view finishes, each along with the number
+/// of times it has been deferred. Transactions that can't be deferred under
+/// [`BuilderConfig::deferral_policy`] are rejected and reported to `hooks`.
+/// If `all_or_nothing` is set and any of them can't be deferred, all of them are rejected.
+/// Returns the rejected transactions.
+async fn defer_transactions<Types: NodeType, Hooks: BuilderHooks<Types>>(
+    coordinator: &BuilderStateCoordinator<Types>,
+    hooks: &Hooks,
+    deferred: &Mutex<DeferredTransactions<Types>>,
+    transactions: Vec<(ReceivedTransaction<Types>, u32)>,
+    all_or_nothing: bool,
+) -> Vec<(Commitment<Types::Transaction>, RejectionReason)> {
+    if transactions.is_empty() {
+        return Vec::new();
+    }
+    let (held, rejected) = {
+        let mut deferred = deferred.lock().await;
+        if all_or_nothing {
+            deferred.defer_all_or_none(transactions)
+        } else {
+            deferred.defer_all(transactions)
+        }
+    };
+    for commit in &held {
+        coordinator.update_txn_status(commit, TransactionStatus::Pending);
+    }
+    for (commit, reason) in &rejected {
+        tracing::debug!(%commit, %reason, "Transaction couldn't be deferred");
+        reject_transaction(coordinator, hooks, *commit, reason.clone()).await;
+    }
+    rejected
+}
+
+/// Mark the transaction as rejected for `reason` and report it to `hooks`
+async fn reject_transaction<Types: NodeType, Hooks: BuilderHooks<Types>>(
+    coordinator: &BuilderStateCoordinator<Types>,
+    hooks: &Hooks,
+    commit: Commitment<Types::Transaction>,
+    reason: RejectionReason,
+) {
+    coordinator.update_txn_status(
+        &commit,
+        TransactionStatus::Rejected {
+            reason: reason.to_string(),
+        },
+    );
+    hooks.on_transaction_rejected(commit, &reason).await;
+}
+
+/// Passes the transaction on to the coordinator, unless it's too big
+/// to fit into a bundle under current limits, in which case it is rejected.
+/// Rejections are reported to `hooks`.
+async fn handle_transaction<Types: NodeType, Hooks: BuilderHooks<Types>>(
+    coordinator: &BuilderStateCoordinator<Types>,
+    block_size_limits: &BlockSizeLimits,
+    hooks: &Hooks,
+    tx: ReceivedTransaction<Types>,
+) -> Result<(), Error<Types>> {
+    let commit = tx.commit;
+    let len = tx.min_block_size;
+    let max_tx_len = block_size_limits.max_block_size();
+    if len > max_tx_len {
+        tracing::warn!(%tx.commit, %len, %max_tx_len, "Transaction too big");
+        let error = Error::TxTooBig { len, max_tx_len };
+        coordinator.update_txn_status(
+            &commit,
+            TransactionStatus::Rejected {
+                reason: error.to_string(),
+            },
+        );
+        hooks
+            .on_transaction_rejected(
+                commit,
+                &RejectionReason::TooBig {
+                    len,
+                    max_len: max_tx_len,
+                },
+            )
+            .await;
+        return Err(error);
+    }
+    if let Err(error) = coordinator.handle_transaction(tx).await {
+        hooks
+            .on_transaction_rejected(commit, &RejectionReason::Other(error.to_string()))
+            .await;
+        return Err(error);
+    }
+    Ok(())
+}
+
 #[derive(derive_more::Deref, derive_more::DerefMut)]
 #[deref(forward)]
 #[deref_mut(forward)]
@@ -311,11 +942,224 @@ where
     Types: NodeType,
     Hooks: BuilderHooks<Types>;
 
-/*
-Handling Builder API responses
-*/
-#[async_trait]
-impl<Types, Hooks> BuilderDataSource<Types> for ProxyGlobalState<Types, Hooks>
+impl<Types, Hooks> ProxyGlobalState<Types, Hooks>
+where
+    Types: NodeType,
+    Hooks: BuilderHooks<Types>,
+{
+    /// Submit transactions to the private mempool, returning a result for each of them,
+    /// in the same order. Transactions rejected by hooks, too big or belonging to
+    /// namespaces this builder doesn't serve result in an error, and the rest are
+    /// passed on to the builder. Transactions deferred by hooks are reported as
+    /// successfully submitted, unless [`BuilderConfig::deferral_policy`] doesn't allow
+    /// deferring them. Once shutdown begins, all transactions are rejected.
+    pub async fn submit_txns_with_results(
+        &self,
+        txns: Vec<Types::Transaction>,
+    ) -> Vec<Result<Commitment<Types::Transaction>, BuildError>> {
+        self.submit_txns_from(ANONYMOUS_CLIENT, txns).await
+    }
+
+    /// Same as [`Self::submit_txns_with_results`], enforcing [`BuilderConfig::submit_limits`]
+    /// for `client`. If the submission exceeds the limits, all transactions are rejected.
+    /// Submissions through the private mempool API are attributed to the client's
+    /// IP address, see [`submit_limit::remote_client`].
+    pub async fn submit_txns_from(
+        &self,
+        client: &str,
+        txns: Vec<Types::Transaction>,
+    ) -> Vec<Result<Commitment<Types::Transaction>, BuildError>> {
+        let num_txns = txns.len();
+        match self.submit(client, txns, false).await {
+            Ok(results) => results,
+            Err(err) => vec![Err(err.into()); num_txns],
+        }
+    }
+
+    /// Validate `txns` submitted by `client` and pass them on to the builder.
+    /// Fails as a whole if the submission is rejected before individual transactions
+    /// are considered. If `all_or_nothing` is set, it also fails as a whole if any of
+    /// the transactions is rejected, in which case none of them is passed on or deferred.
+    async fn submit(
+        &self,
+        client: &str,
+        txns: Vec<Types::Transaction>,
+        all_or_nothing: bool,
+    ) -> Result<Vec<Result<Commitment<Types::Transaction>, BuildError>>, Error<Types>> {
+        if self.shutdown.is_shutting_down() {
+            return Err(Error::ShuttingDown);
+        }
+        let commitments = txns.iter().map(Committable::commit).collect::<Vec<_>>();
+        let received: Vec<_> = txns
+            .into_iter()
+            .map(|txn| ReceivedTransaction::new(txn, TransactionSource::Private))
+            .collect();
+
+        let num_bytes = received.iter().map(|txn| txn.min_block_size).sum();
+        self.submit_limiter
+            .check::<Types>(client, received.len(), num_bytes)
+            .inspect_err(
+                |err| tracing::warn!(%client, %err, "Rejected private mempool submission"),
+            )?;
+
+        // Reject transactions we know won't make it into a bundle before running hooks
+        let mut errors = HashMap::new();
+        let mut candidates = Vec::with_capacity(received.len());
+        for txn in received {
+            match self.precheck(&txn) {
+                Ok(()) => candidates.push(txn),
+                Err((reason, error)) => {
+                    reject_transaction(&self.coordinator, self.hooks.as_ref(), txn.commit, reason)
+                        .await;
+                    errors.insert(txn.commit, error);
+                }
+            }
+        }
+        if all_or_nothing && !errors.is_empty() {
+            return Err(batch_error(&commitments, errors));
+        }
+
+        let verdicts = apply_verdicts(
+            &self.coordinator,
+            self.hooks.as_ref(),
+            TransactionSource::Private,
+            candidates,
+        )
+        .await;
+        errors.extend(
+            verdicts
+                .rejected
+                .into_iter()
+                .map(|(commit, reason)| (commit, BuildError::Error(reason))),
+        );
+        if all_or_nothing && !errors.is_empty() {
+            return Err(batch_error(&commitments, errors));
+        }
+
+        let rejected = defer_transactions(
+            &self.coordinator,
+            self.hooks.as_ref(),
+            &self.deferred,
+            verdicts.deferred.into_iter().map(|txn| (txn, 1)).collect(),
+            all_or_nothing,
+        )
+        .await;
+        errors.extend(
+            rejected
+                .into_iter()
+                .map(|(commit, reason)| (commit, BuildError::Error(reason.to_string()))),
+        );
+        if all_or_nothing && !errors.is_empty() {
+            return Err(batch_error(&commitments, errors));
+        }
+
+        let handled = verdicts
+            .accepted
+            .into_iter()
+            .map(|txn| async move {
+                let commit = txn.commit;
+                handle_transaction(
+                    &self.coordinator,
+                    &self.block_size_limits,
+                    self.hooks.as_ref(),
+                    txn,
+                )
+                .await
+                .map_err(|err| (commit, err.into()))
+            })
+            .collect::<FuturesOrdered<_>>()
+            .collect::<Vec<_>>()
+            .await;
+        errors.extend(handled.into_iter().filter_map(Result::err));
+
+        Ok(commitments
+            .into_iter()
+            .map(|commit| match errors.remove(&commit) {
+                Some(err) => Err(err),
+                None => Ok(commit),
+            })
+            .collect())
+    }
+
+    /// Checks of a private submission that don't depend on hooks: the transaction
+    /// has to belong to a served namespace and fit into a bundle under current limits
+    fn precheck(
+        &self,
+        txn: &ReceivedTransaction<Types>,
+    ) -> Result<(), (RejectionReason, BuildError)> {
+        let commit = txn.commit;
+        if let Some(namespace) = self
+            .namespaces
+            .as_ref()
+            .and_then(|namespaces| namespaces.unserved_namespace(&txn.transaction))
+        {
+            return Err((
+                RejectionReason::UnservedNamespace(namespace),
+                BuildError::Error(format!(
+                    "Transaction {commit} belongs to namespace {namespace}, \
+                     which isn't served by this builder"
+                )),
+            ));
+        }
+        let len = txn.min_block_size;
+        let max_tx_len = self.block_size_limits.max_block_size();
+        if len > max_tx_len {
+            return Err((
+                RejectionReason::TooBig {
+                    len,
+                    max_len: max_tx_len,
+                },
+                Error::<Types>::TxTooBig { len, max_tx_len }.into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Estimate fee and time until inclusion for a transaction of `transaction_size` bytes
+    /// submitted now, based on the queue of the highest view builder state, current
+    /// maximum bundle size and recent inclusion latencies, refreshed at most once per
+    /// [`BuilderConfig::estimate_refresh_period`]. The fee is what
+    /// [`BuilderConfig::fee_strategy`] would offer for the transaction's space alone,
+    /// without any fees declared by the transaction itself.
+    pub async fn estimate_inclusion(&self, transaction_size: u64) -> InclusionEstimate {
+        let inputs = self.estimator.inputs(&self.coordinator).await;
+        let estimated_fee = self.fee_strategy.offered_fee(&FeeContext {
+            transactions: &[],
+            bundle_size: transaction_size,
+            queue: &inputs.queue,
+            view_number: inputs.proposal_view,
+        });
+        InclusionEstimate::new(
+            transaction_size,
+            inputs.queue,
+            self.block_size_limits.max_block_size(),
+            estimated_fee,
+            inputs.latency,
+        )
+    }
+}
+
+/// Reason a transaction was rejected for, as reported to API clients
+fn rejection_message(error: BuildError) -> String {
+    match error {
+        BuildError::Error(message) => message,
+        error => error.to_string(),
+    }
+}
+
+/// Error rejecting a whole batch, based on the error for the first rejected transaction
+fn batch_error<Types: NodeType>(
+    commitments: &[Commitment<Types::Transaction>],
+    mut errors: HashMap<Commitment<Types::Transaction>, BuildError>,
+) -> Error<Types> {
+    let message = match commitments.iter().find_map(|commit| errors.remove(commit)) {
+        Some(error) => rejection_message(error),
+        None => "Transaction rejected".to_owned(),
+    };
+    Error::SubmissionRejected(format!("{message}; the whole batch has been rejected"))
+}
+
+impl<Types, Hooks> ProxyGlobalState<Types, Hooks>
 where
     Types: NodeType,
     Hooks: BuilderHooks<Types>,
@@ -324,17 +1168,77 @@ where
     >>::Error: Display,
     for<'a> <Types::SignatureKey as TryFrom<&'a TaggedBase64>>::Error: Display,
 {
+    /// Serve a bundle to a proposer, checking that the request is signed by `sender`
+    /// as described in [`bundle_request_signing_bytes`](crate::auth::bundle_request_signing_bytes)
+    /// and that `sender` is privileged or within [`BundleAuthConfig::rate_limit`],
+    /// see [`crate::auth`]. Rejected requests fail with a status telling why,
+    /// e.g. `429 Too Many Requests` with the time after which to retry.
+    #[tracing::instrument(
+        skip(self, signature),
+        err(level = Level::INFO)
+        ret(level = Level::TRACE)
+    )]
+    pub async fn authenticated_bundle(
+        &self,
+        parent_view: u64,
+        parent_hash: &VidCommitment,
+        view_number: u64,
+        sender: &Types::SignatureKey,
+        signature: &<Types::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<Bundle<Types>, BuilderApiError> {
+        if let Some(auth) = &self.bundle_auth {
+            auth.check_signed(parent_view, parent_hash, view_number, sender, signature)
+                .inspect_err(|err| tracing::warn!(%err, "Rejected bundle request"))
+                .map_err(Error::into_api_error::<BuilderApiError>)?;
+        }
+        self.serve_bundle_for_api(parent_view, parent_hash, view_number)
+            .await
+    }
+
+    /// Serve a bundle to an unauthenticated `caller`, identified e.g. by remote address.
+    /// With [`BuilderConfig::bundle_auth`] set, every caller is subject to its own
+    /// [`BundleAuthConfig::rate_limit`], and rejected requests fail with a status telling why.
     #[tracing::instrument(
         skip(self),
         err(level = Level::INFO)
         ret(level = Level::TRACE)
     )]
-    async fn bundle(
+    pub async fn anonymous_bundle(
+        &self,
+        caller: &str,
+        parent_view: u64,
+        parent_hash: &VidCommitment,
+        view_number: u64,
+    ) -> Result<Bundle<Types>, BuilderApiError> {
+        if let Some(auth) = &self.bundle_auth {
+            auth.check_anonymous(caller)
+                .map_err(Error::into_api_error::<BuilderApiError>)?;
+        }
+        self.serve_bundle_for_api(parent_view, parent_hash, view_number)
+            .await
+    }
+
+    async fn serve_bundle_for_api(
+        &self,
+        parent_view: u64,
+        parent_hash: &VidCommitment,
+        view_number: u64,
+    ) -> Result<Bundle<Types>, BuilderApiError> {
+        self.serve_bundle(parent_view, parent_hash, view_number)
+            .await
+            .map_err(|source| BuilderApiError::BlockAvailable {
+                source,
+                resource: format!("bundle for view {view_number}"),
+            })
+    }
+
+    async fn serve_bundle(
         &self,
         parent_view: u64,
         parent_hash: &VidCommitment,
         view_number: u64,
     ) -> Result<Bundle<Types>, BuildError> {
+        let _in_flight = self.shutdown.track();
         let start = Instant::now();
 
         let parent_view = Types::View::new(parent_view);
@@ -373,18 +1277,109 @@ where
                 parent_view
             );
 
-            let Some(transactions) = self.collect_transactions(&builder_state).await else {
-                tracing::debug!("No response to send");
+            // Count transactions that arrived since the cached bundle was assembled without
+            // collecting them, so that the cache can tell whether the bundle needs refreshing
+            let pending_len = builder_state.pending_len().await;
+            if let Some(bundle) =
+                self.bundle_cache
+                    .read()
+                    .await
+                    .get(&state_id, view_number, pending_len)
+            {
+                tracing::info!("Serving cached bundle");
+                return Ok(bundle.clone());
+            }
+
+            let (transactions, truncated) = self
+                .collect_transactions(&builder_state, Types::View::new(view_number))
+                .await;
+
+            let Some(transactions) = self
+                .hooks
+                .on_bundle_assembled(view_number, transactions)
+                .await
+            else {
+                tracing::info!("Bundle vetoed by hooks");
                 return Err(BuildError::NotFound);
             };
 
-            let bundle = self.assemble_bundle(transactions, view_number).await?;
+            let queue = builder_state.queue_statistics().await;
+            let bundle = self
+                .assemble_bundle(transactions, &queue, view_number)
+                .await?;
+            let bundle =
+                self.bundle_cache
+                    .write()
+                    .await
+                    .insert(state_id, view_number, bundle, queue.len);
+            self.coordinator
+                .record_offered(
+                    Types::View::new(view_number),
+                    bundle.transactions.iter().map(Committable::commit),
+                )
+                .await;
+            self.coordinator
+                .fee_ledger()
+                .record_offered(
+                    view_number,
+                    FeeSubject::Bundle(
+                        bundle
+                            .transactions
+                            .iter()
+                            .map(Committable::commit)
+                            .collect(),
+                    ),
+                    bundle.sequencing_fee.fee_amount,
+                    bundle.sequencing_fee.fee_account.clone(),
+                )
+                .await;
+
+            if start.elapsed() > self.api_timeout {
+                // we can't keep up with this bundle size, reduce max bundle size
+                self.block_size_limits.decrement_block_size();
+            } else if self.api_timeout.saturating_sub(start.elapsed())
+                > self.api_timeout / RESPONSE_TARGET_MARGIN_DIVISOR
+            {
+                self.block_size_limits.try_increment_block_size(truncated);
+            }
 
             tracing::info!("Serving bundle");
 
             return Ok(bundle);
         }
     }
+}
+
+/*
+Handling Builder API responses
+*/
+#[async_trait]
+impl<Types, Hooks> BuilderDataSource<Types> for ProxyGlobalState<Types, Hooks>
+where
+    Types: NodeType,
+    Hooks: BuilderHooks<Types>,
+    for<'a> <<Types::SignatureKey as SignatureKey>::PureAssembledSignatureType as TryFrom<
+        &'a TaggedBase64,
+    >>::Error: Display,
+    for<'a> <Types::SignatureKey as TryFrom<&'a TaggedBase64>>::Error: Display,
+{
+    #[tracing::instrument(
+        skip(self),
+        err(level = Level::INFO)
+        ret(level = Level::TRACE)
+    )]
+    async fn bundle(
+        &self,
+        parent_view: u64,
+        parent_hash: &VidCommitment,
+        view_number: u64,
+    ) -> Result<Bundle<Types>, BuildError> {
+        if let Some(auth) = &self.bundle_auth {
+            auth.check_anonymous(ANONYMOUS_CLIENT)?;
+        }
+        self.serve_bundle(parent_view, parent_hash, view_number)
+            .await
+    }
 
     async fn builder_address(
         &self,
@@ -403,18 +1398,12 @@ where
         &self,
         txns: Vec<<Types as NodeType>::Transaction>,
     ) -> Result<Vec<Commitment<<Types as NodeType>::Transaction>>, BuildError> {
-        let txns = self.hooks.process_transactions(txns).await;
-
-        txns.into_iter()
-            .map(|txn| ReceivedTransaction::new(txn, TransactionSource::Private))
-            .map(|txn| async {
-                let commit = txn.commit;
-                self.coordinator.handle_transaction(txn).await?;
-                Ok(commit)
-            })
-            .collect::<FuturesOrdered<_>>()
-            .try_collect()
-            .await
+        // The API can't report results for individual transactions,
+        // so batches are accepted or rejected as a whole
+        self.submit(ANONYMOUS_CLIENT, txns, true)
+            .await?
+            .into_iter()
+            .collect()
     }
 
     async fn txn_status(
@@ -425,6 +1414,55 @@ where
     }
 }
 
+impl<Types, Hooks> FeeLedgerDataSource<Types> for ProxyGlobalState<Types, Hooks>
+where
+    Types: NodeType,
+    Hooks: BuilderHooks<Types>,
+{
+    fn fee_ledger(&self) -> &FeeLedger<Types> {
+        self.coordinator.fee_ledger()
+    }
+}
+
+#[async_trait]
+impl<Types, Hooks> PrivateMempoolDataSource<Types> for ProxyGlobalState<Types, Hooks>
+where
+    Types: NodeType,
+    Hooks: BuilderHooks<Types>,
+{
+    async fn submit_private(
+        &self,
+        client: &str,
+        txns: Vec<Types::Transaction>,
+    ) -> Result<Vec<Commitment<Types::Transaction>>, Error<Types>> {
+        // Batches are accepted or rejected as a whole, the same as through HotShot's API
+        self.submit(client, txns, true)
+            .await?
+            .into_iter()
+            .map(|result| result.map_err(|err| Error::SubmissionRejected(rejection_message(err))))
+            .collect()
+    }
+
+    fn transaction_status(&self, txn_hash: &Commitment<Types::Transaction>) -> TransactionStatus {
+        self.coordinator.tx_status(txn_hash)
+    }
+
+    fn submit_limiter_metrics(&self) -> SubmitLimiterMetrics {
+        self.0.submit_limiter_metrics()
+    }
+}
+
+#[async_trait]
+impl<Types, Hooks> InclusionEstimateDataSource for ProxyGlobalState<Types, Hooks>
+where
+    Types: NodeType,
+    Hooks: BuilderHooks<Types>,
+{
+    async fn estimate_inclusion(&self, transaction_size: u64) -> InclusionEstimate {
+        ProxyGlobalState::estimate_inclusion(self, transaction_size).await
+    }
+}
+
 #[async_trait]
 impl<Types, Hooks> ReadState for ProxyGlobalState<Types, Hooks>
 where
@@ -0,0 +1,282 @@
+use std::{collections::HashSet, marker::PhantomData, sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use hotshot::types::{BLSPubKey, SignatureKey};
+use hotshot_builder_api::v0_99::{
+    builder::Error as BuilderApiError, data_source::BuilderDataSource, Version as BuilderApiVersion,
+};
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    bundle::Bundle, constants::MARKETPLACE_BUILDER_MODULE,
+    traits::node_implementation::ConsensusTime,
+};
+use marketplace_builder_shared::{
+    block::BuilderStateId, coordinator::proposal_validation::LeaderOracle, rate_limit::RateLimit,
+    testing::consensus::SimulatedChainState,
+};
+use surf_disco::Client;
+use tide_disco::{Error as _, StatusCode};
+use tokio::spawn;
+use tracing_test::traced_test;
+use url::Url;
+use vbs::version::StaticVersion;
+
+use crate::{
+    auth::{bundle_request_signing_bytes, BundleAuthConfig},
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+type TestProxy = ProxyGlobalState<TestTypes, NoHooks<TestTypes>>;
+
+/// Start a builder with given authentication config and simulate a consensus round,
+/// returning the builder and the state to request bundles from
+async fn setup(auth: BundleAuthConfig<TestTypes>) -> (TestProxy, BuilderStateId<TestTypes>) {
+    setup_with_oracle(auth, None).await
+}
+
+/// Same as [`setup`], but with a leader oracle
+async fn setup_with_oracle(
+    auth: BundleAuthConfig<TestTypes>,
+    leader_oracle: Option<LeaderOracle<TestTypes>>,
+) -> (TestProxy, BuilderStateId<TestTypes>) {
+    setup_with_config(BuilderConfig {
+        bundle_auth: Some(auth),
+        leader_oracle,
+        ..BuilderConfig::test()
+    })
+    .await
+}
+
+/// Same as [`setup`], but with arbitrary builder config
+async fn setup_with_config(
+    config: BuilderConfig<TestTypes>,
+) -> (TestProxy, BuilderStateId<TestTypes>) {
+    let global_state = GlobalState::new(config, NoHooks(PhantomData));
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    (proxy_global_state, builder_state_id)
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_signed_requests() {
+    let (allowed, allowed_private_key) = BLSPubKey::generated_from_seed_indexed([0; 32], 0);
+    let (outsider, outsider_private_key) = BLSPubKey::generated_from_seed_indexed([0; 32], 1);
+
+    let (proxy_global_state, state_id) = setup(BundleAuthConfig {
+        allowed_proposers: Some(HashSet::from([allowed])),
+        rate_limit: None,
+    })
+    .await;
+    let parent_view = *state_id.parent_view;
+    let parent_hash = &state_id.parent_commitment;
+    let signing_bytes = bundle_request_signing_bytes(parent_view, parent_hash, 1);
+
+    let signature = BLSPubKey::sign(&allowed_private_key, &signing_bytes).unwrap();
+    proxy_global_state
+        .authenticated_bundle(parent_view, parent_hash, 1, &allowed, &signature)
+        .await
+        .unwrap();
+
+    // Signature is for a different view
+    proxy_global_state
+        .authenticated_bundle(parent_view, parent_hash, 2, &allowed, &signature)
+        .await
+        .unwrap_err();
+
+    // Correctly signed, but not on the allowlist
+    let signature = BLSPubKey::sign(&outsider_private_key, &signing_bytes).unwrap();
+    let err = proxy_global_state
+        .authenticated_bundle(parent_view, parent_hash, 1, &outsider, &signature)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), StatusCode::UNAUTHORIZED);
+
+    // Anonymous requests are rejected outright
+    proxy_global_state
+        .bundle(parent_view, parent_hash, 1)
+        .await
+        .unwrap_err();
+    let err = proxy_global_state
+        .anonymous_bundle("127.0.0.1", parent_view, parent_hash, 1)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_requests_rate_limited_per_caller() {
+    let (proxy_global_state, state_id) = setup(BundleAuthConfig {
+        allowed_proposers: None,
+        rate_limit: Some(RateLimit {
+            burst: 2,
+            replenish_period: Duration::from_secs(3600),
+        }),
+    })
+    .await;
+    let parent_view = *state_id.parent_view;
+    let parent_hash = &state_id.parent_commitment;
+
+    for view in 1..=2 {
+        proxy_global_state
+            .anonymous_bundle("10.0.0.1", parent_view, parent_hash, view)
+            .await
+            .unwrap();
+    }
+    let err = proxy_global_state
+        .anonymous_bundle("10.0.0.1", parent_view, parent_hash, 3)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(err.to_string().contains("retry after"), "{err}");
+
+    // Another caller isn't affected
+    proxy_global_state
+        .anonymous_bundle("10.0.0.2", parent_view, parent_hash, 3)
+        .await
+        .unwrap();
+
+    // Signing a request with a self-generated key doesn't bypass the limit
+    let (key, private_key) = BLSPubKey::generated_from_seed_indexed([0; 32], 2);
+    for view in 1..=3 {
+        let signature = BLSPubKey::sign(
+            &private_key,
+            &bundle_request_signing_bytes(parent_view, parent_hash, view),
+        )
+        .unwrap();
+        let result = proxy_global_state
+            .authenticated_bundle(parent_view, parent_hash, view, &key, &signature)
+            .await;
+        if view <= 2 {
+            result.unwrap();
+        } else {
+            assert_eq!(result.unwrap_err().status(), StatusCode::TOO_MANY_REQUESTS);
+        }
+    }
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_leader_not_rate_limited() {
+    // Same leaders as `SimulatedChainState` uses, so that its proposals are accepted
+    let leader_of = |view: u64| BLSPubKey::generated_from_seed_indexed([view as u8; 32], view);
+    let (proxy_global_state, state_id) = setup_with_oracle(
+        BundleAuthConfig {
+            allowed_proposers: None,
+            rate_limit: None,
+        },
+        Some(LeaderOracle::new(move |view| Some(leader_of(view.u64()).0))),
+    )
+    .await;
+    let parent_view = *state_id.parent_view;
+    let parent_hash = &state_id.parent_commitment;
+    let view_number = parent_view + 1;
+    let signing_bytes = bundle_request_signing_bytes(parent_view, parent_hash, view_number);
+
+    let (leader, leader_private_key) = leader_of(view_number);
+    let signature = BLSPubKey::sign(&leader_private_key, &signing_bytes).unwrap();
+    for _ in 0..5 {
+        proxy_global_state
+            .authenticated_bundle(parent_view, parent_hash, view_number, &leader, &signature)
+            .await
+            .unwrap();
+    }
+
+    // Leader of another view isn't privileged for this one
+    let (other, other_private_key) = leader_of(view_number + 1);
+    let signature = BLSPubKey::sign(&other_private_key, &signing_bytes).unwrap();
+    let err = proxy_global_state
+        .authenticated_bundle(parent_view, parent_hash, view_number, &other, &signature)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), StatusCode::UNAUTHORIZED);
+}
+
+/// Serve the builder's HTTP API and return a client for the builder API module
+async fn serve_builder_api(
+    proxy_global_state: &TestProxy,
+) -> Client<BuilderApiError, BuilderApiVersion> {
+    let port = portpicker::pick_unused_port().unwrap();
+    let app = Arc::clone(&proxy_global_state.0).into_app().unwrap();
+    spawn(app.serve(
+        format!("http://localhost:{port}").parse::<Url>().unwrap(),
+        StaticVersion::<0, 1> {},
+    ));
+    let client = Client::<BuilderApiError, BuilderApiVersion>::new(
+        format!("http://localhost:{port}/{MARKETPLACE_BUILDER_MODULE}")
+            .parse()
+            .unwrap(),
+    );
+    assert!(client.connect(Some(Duration::from_secs(1))).await);
+    client
+}
+
+/// Builder API served with bundle authentication enabled should have the same
+/// routes as HotShot's builder API, which is served when it's disabled
+#[tokio::test]
+#[traced_test]
+async fn test_caller_aware_api_matches_upstream() {
+    let (upstream, upstream_state_id) = setup_with_config(BuilderConfig::test()).await;
+    let (caller_aware, caller_aware_state_id) = setup(BundleAuthConfig {
+        allowed_proposers: None,
+        rate_limit: None,
+    })
+    .await;
+    let upstream = serve_builder_api(&upstream).await;
+    let caller_aware = serve_builder_api(&caller_aware).await;
+
+    let upstream_address = upstream
+        .get::<BLSPubKey>("builderaddress")
+        .send()
+        .await
+        .unwrap();
+    let caller_aware_address = caller_aware
+        .get::<BLSPubKey>("builderaddress")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(upstream_address, caller_aware_address);
+
+    let bundle_route = |state_id: &BuilderStateId<TestTypes>| {
+        format!(
+            "bundle/{}/{}/{}",
+            *state_id.parent_view,
+            state_id.parent_commitment,
+            *state_id.parent_view + 1
+        )
+    };
+    let upstream_bundle = upstream
+        .get::<Bundle<TestTypes>>(&bundle_route(&upstream_state_id))
+        .send()
+        .await
+        .unwrap();
+    let caller_aware_bundle = caller_aware
+        .get::<Bundle<TestTypes>>(&bundle_route(&caller_aware_state_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        upstream_bundle.transactions,
+        caller_aware_bundle.transactions
+    );
+
+    // Routes not in HotShot's spec aren't served by either
+    for route in ["bundle/1", "builderaddress/1"] {
+        let upstream_status = upstream.get::<()>(route).send().await.unwrap_err().status();
+        let caller_aware_status = caller_aware
+            .get::<()>(route)
+            .send()
+            .await
+            .unwrap_err()
+            .status();
+        assert_eq!(upstream_status, StatusCode::NOT_FOUND);
+        assert_eq!(caller_aware_status, upstream_status);
+    }
+}
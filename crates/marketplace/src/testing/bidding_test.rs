@@ -0,0 +1,144 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use async_broadcast::broadcast;
+use hotshot::types::{Event, EventType};
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use tokio::time::{sleep, timeout};
+use tracing_test::traced_test;
+use url::Url;
+
+use crate::{
+    bidding::{BidConfig, Bidder},
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState},
+    testing::mock_solver::MockSolver,
+};
+
+const LOOKAHEAD: u64 = 2;
+
+fn bid_config(solver_url: Url) -> BidConfig {
+    BidConfig {
+        solver_url,
+        builder_url: "http://builder.example:8080".parse().unwrap(),
+        bid_amounts: BTreeMap::from([(1, 100), (2, 250)]),
+        lookahead: LOOKAHEAD,
+        submission_timeout: Duration::from_secs(1),
+    }
+}
+
+fn solver_url() -> Url {
+    format!(
+        "http://localhost:{}",
+        portpicker::pick_unused_port().unwrap()
+    )
+    .parse()
+    .unwrap()
+}
+
+#[test]
+#[traced_test]
+fn test_bids_are_signed() {
+    let config = BuilderConfig::<TestTypes>::test();
+    let bidder = Bidder::<TestTypes>::new(
+        bid_config("http://localhost:1".parse().unwrap()),
+        config.builder_keys.clone(),
+    );
+
+    let bids = bidder.bids_for_view(7).unwrap();
+    assert_eq!(bids.len(), 2);
+    for bid in &bids {
+        assert!(bid.verify());
+        assert_eq!(bid.body.view, 7);
+        assert_eq!(bid.body.account, config.builder_keys.0);
+    }
+
+    let mut tampered = bids[0].clone();
+    tampered.body.bid_amount += 1;
+    assert!(!tampered.verify(), "Signature must cover the bid amount");
+}
+
+/// Check that the builder bids for upcoming views with configured amounts
+/// as views finish, and only once per view
+#[tokio::test(flavor = "multi_thread")]
+#[traced_test]
+async fn test_bid_submission() {
+    let solver_url = solver_url();
+    let (solver, _handle) = MockSolver::<TestTypes>::spawn(solver_url.clone()).await;
+
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            bid_config: Some(bid_config(solver_url)),
+            ..BuilderConfig::test()
+        },
+        NoHooks(std::marker::PhantomData),
+    );
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+
+    for view in [1, 1, 2] {
+        let view_number = ViewNumber::new(view);
+        event_stream_sender
+            .broadcast(Event {
+                view_number,
+                event: EventType::ViewFinished { view_number },
+            })
+            .await
+            .unwrap();
+    }
+
+    let bids = timeout(Duration::from_secs(5), async {
+        loop {
+            let bids = solver.bids().await;
+            if bids.len() >= 4 {
+                break bids;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("Didn't receive bids in time");
+
+    // Let any duplicate submissions arrive
+    sleep(Duration::from_millis(200)).await;
+    assert_eq!(solver.bids().await.len(), 4, "Duplicate bids submitted");
+
+    let mut submitted = bids
+        .iter()
+        .inspect(|bid| assert!(bid.verify()))
+        .map(|bid| (bid.body.view, bid.body.namespace, bid.body.bid_amount))
+        .collect::<Vec<_>>();
+    submitted.sort();
+    assert_eq!(
+        submitted,
+        vec![
+            (1 + LOOKAHEAD, 1, 100),
+            (1 + LOOKAHEAD, 2, 250),
+            (2 + LOOKAHEAD, 1, 100),
+            (2 + LOOKAHEAD, 2, 250),
+        ]
+    );
+}
+
+/// Check that bids which failed to be submitted are retried for the same view
+#[tokio::test(flavor = "multi_thread")]
+#[traced_test]
+async fn test_failed_bid_retried() {
+    let solver_url = solver_url();
+    let bidder = Bidder::<TestTypes>::new(
+        bid_config(solver_url.clone()),
+        BuilderConfig::<TestTypes>::test().builder_keys,
+    );
+    let view_number = ViewNumber::new(1);
+
+    // Solver isn't running yet, so submission fails
+    bidder.handle_view_finished(view_number).await;
+
+    let (solver, _handle) = MockSolver::<TestTypes>::spawn(solver_url).await;
+    bidder.handle_view_finished(view_number).await;
+    assert_eq!(solver.bids().await.len(), 2);
+
+    // Once submitted, bids aren't repeated
+    bidder.handle_view_finished(view_number).await;
+    assert_eq!(solver.bids().await.len(), 2);
+}
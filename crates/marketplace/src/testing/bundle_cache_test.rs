@@ -0,0 +1,176 @@
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use hotshot_builder_api::v0_99::data_source::{AcceptsTxnSubmits, BuilderDataSource};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use marketplace_builder_shared::{block::BuilderStateId, testing::consensus::SimulatedChainState};
+use tokio::time::sleep;
+use tracing_test::traced_test;
+
+use crate::{
+    bundle_cache::{BundleCache, BundleCachePolicy},
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+type TestProxy = ProxyGlobalState<TestTypes, NoHooks<TestTypes>>;
+
+/// Start a builder with given cache policy and simulate a consensus round,
+/// returning the builder and the state to request bundles from
+async fn setup(policy: BundleCachePolicy) -> (TestProxy, BuilderStateId<TestTypes>) {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            bundle_cache: policy,
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    proxy_global_state
+        .submit_txns(vec![TestTransaction::new(vec![0])])
+        .await
+        .unwrap();
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    (proxy_global_state, builder_state_id)
+}
+
+/// Submit transactions with given leading bytes
+async fn submit(proxy_global_state: &TestProxy, leading_bytes: impl IntoIterator<Item = u8>) {
+    proxy_global_state
+        .submit_txns(
+            leading_bytes
+                .into_iter()
+                .map(|byte| TestTransaction::new(vec![byte; 2]))
+                .collect(),
+        )
+        .await
+        .unwrap();
+}
+
+/// Check that duplicate requests are served the same bundle,
+/// even if new transactions have arrived in the meantime
+#[tokio::test]
+#[traced_test]
+async fn test_duplicate_requests_served_from_cache() {
+    let (proxy_global_state, state_id) = setup(BundleCachePolicy::default()).await;
+    let parent_view = *state_id.parent_view;
+
+    let bundle = proxy_global_state
+        .bundle(parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+
+    submit(&proxy_global_state, 1..=3).await;
+
+    let retried = proxy_global_state
+        .bundle(parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+    assert_eq!(bundle, retried);
+
+    // A different view is a different request
+    let other_view = proxy_global_state
+        .bundle(parent_view, &state_id.parent_commitment, 2)
+        .await
+        .unwrap();
+    assert_eq!(other_view.transactions.len(), 4);
+}
+
+/// Check that cached bundle is refreshed once enough new transactions arrive
+#[tokio::test]
+#[traced_test]
+async fn test_cache_refresh_threshold() {
+    let (proxy_global_state, state_id) = setup(BundleCachePolicy {
+        refresh_threshold: Some(2),
+        ..Default::default()
+    })
+    .await;
+    let parent_view = *state_id.parent_view;
+
+    let bundle = proxy_global_state
+        .bundle(parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+    assert_eq!(bundle.transactions.len(), 1);
+
+    submit(&proxy_global_state, [1]).await;
+    let retried = proxy_global_state
+        .bundle(parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+    assert_eq!(bundle, retried, "Below threshold, cached bundle is served");
+
+    submit(&proxy_global_state, [2, 3]).await;
+    let refreshed = proxy_global_state
+        .bundle(parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+    assert_eq!(refreshed.transactions.len(), 4);
+}
+
+/// Check that cached bundles expire
+#[tokio::test]
+#[traced_test]
+async fn test_cache_ttl() {
+    const TTL: Duration = Duration::from_millis(200);
+
+    let (proxy_global_state, state_id) = setup(BundleCachePolicy {
+        ttl: TTL,
+        ..Default::default()
+    })
+    .await;
+    let parent_view = *state_id.parent_view;
+
+    let bundle = proxy_global_state
+        .bundle(parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+
+    submit(&proxy_global_state, [1]).await;
+    sleep(TTL * 2).await;
+
+    let refreshed = proxy_global_state
+        .bundle(parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+    assert_ne!(bundle, refreshed);
+    assert_eq!(refreshed.transactions.len(), 2);
+}
+
+/// Check that the cache keeps the first bundle inserted for a request
+/// and drops bundles on pruning
+#[tokio::test]
+#[traced_test]
+async fn test_cache_insert_and_prune() {
+    let (proxy_global_state, state_id) = setup(BundleCachePolicy::default()).await;
+    let bundle = proxy_global_state
+        .bundle(*state_id.parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+    let other_bundle = proxy_global_state
+        .bundle(*state_id.parent_view, &state_id.parent_commitment, 2)
+        .await
+        .unwrap();
+
+    let mut cache = BundleCache::<TestTypes>::new(BundleCachePolicy::default());
+    assert_eq!(cache.insert(state_id.clone(), 1, bundle.clone(), 1), bundle);
+    assert_eq!(
+        cache.insert(state_id.clone(), 1, other_bundle.clone(), 1),
+        bundle,
+        "Concurrently assembled bundle shouldn't replace the cached one"
+    );
+    assert_eq!(cache.len(), 1);
+
+    cache.prune(state_id.parent_view);
+    assert_eq!(cache.len(), 1, "Cutoff is exclusive");
+
+    cache.prune(ViewNumber::new(*state_id.parent_view + 1));
+    assert!(cache.is_empty());
+}
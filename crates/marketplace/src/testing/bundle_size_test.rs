@@ -0,0 +1,134 @@
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use committable::Committable;
+use hotshot_builder_api::{
+    v0_2::builder::TransactionStatus,
+    v0_99::data_source::{AcceptsTxnSubmits, BuilderDataSource},
+};
+use hotshot_example_types::block_types::TestTransaction;
+use marketplace_builder_shared::{
+    block_size_limits::BlockSizeLimits, testing::consensus::SimulatedChainState,
+};
+use tracing_test::traced_test;
+
+use crate::{
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+const TXN_SIZE: usize = 30;
+
+/// Check that bundles don't exceed maximum bundle size
+#[tokio::test]
+#[traced_test]
+async fn test_bundle_size_limit() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            protocol_max_bundle_size: 100,
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    let transactions = (0..5)
+        .map(|i| TestTransaction::new(vec![i; TXN_SIZE]))
+        .collect::<Vec<_>>();
+    proxy_global_state
+        .submit_txns(transactions.clone())
+        .await
+        .unwrap();
+
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+    let bundle = proxy_global_state
+        .bundle(
+            *builder_state_id.parent_view,
+            &builder_state_id.parent_commitment,
+            1,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(bundle.transactions, transactions[..3]);
+}
+
+/// Check that transactions that wouldn't fit into a bundle are rejected
+#[tokio::test]
+#[traced_test]
+async fn test_too_big_transaction_rejected() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            protocol_max_bundle_size: TXN_SIZE as u64,
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    proxy_global_state
+        .submit_txns(vec![TestTransaction::new(vec![0; TXN_SIZE])])
+        .await
+        .unwrap();
+
+    let too_big = TestTransaction::new(vec![1; TXN_SIZE + 1]);
+    proxy_global_state
+        .submit_txns(vec![too_big.clone()])
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        proxy_global_state
+            .txn_status(too_big.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Rejected { .. }
+    ));
+}
+
+/// Check that bundle size limits grow back towards protocol maximum
+/// when the builder serves bundles in time
+#[tokio::test]
+#[traced_test]
+async fn test_bundle_size_increment() {
+    const PROTOCOL_MAX_BUNDLE_SIZE: u64 = BlockSizeLimits::MAX_BLOCK_SIZE_FLOOR * 3;
+
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            protocol_max_bundle_size: PROTOCOL_MAX_BUNDLE_SIZE,
+            // We don't want to delay increments for this test
+            max_bundle_size_increment_period: Duration::ZERO,
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    global_state
+        .block_size_limits
+        .set_max_block_size(BlockSizeLimits::MAX_BLOCK_SIZE_FLOOR);
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    let mut previous_max = global_state.block_size_limits.max_block_size();
+    for view in 1..=3 {
+        let builder_state_id = chain_state.simulate_consensus_round(None).await;
+        proxy_global_state
+            .bundle(
+                *builder_state_id.parent_view,
+                &builder_state_id.parent_commitment,
+                view,
+            )
+            .await
+            .unwrap();
+
+        let max = global_state.block_size_limits.max_block_size();
+        assert!(max > previous_max, "Limits should've been incremented");
+        assert!(max <= PROTOCOL_MAX_BUNDLE_SIZE);
+        previous_max = max;
+    }
+}
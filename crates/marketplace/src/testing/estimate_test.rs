@@ -0,0 +1,103 @@
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use hotshot_builder_api::v0_99::data_source::AcceptsTxnSubmits;
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_types::traits::block_contents::Transaction;
+use marketplace_builder_shared::testing::consensus::SimulatedChainState;
+use tracing_test::traced_test;
+
+use crate::{
+    fee::CongestionFee,
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+const TX_SIZE: usize = 100;
+const NUM_TXNS: usize = 10;
+
+fn transactions() -> Vec<TestTransaction> {
+    (0..NUM_TXNS)
+        .map(|i| TestTransaction::new(vec![i as u8; TX_SIZE]))
+        .collect()
+}
+
+/// Inclusion estimate should account for transactions queued ahead,
+/// with the fee offered by the configured fee strategy
+#[tokio::test]
+#[traced_test]
+async fn test_inclusion_estimate() {
+    let queue_bytes: u64 = transactions()
+        .iter()
+        .map(|tx| tx.minimum_block_size())
+        .sum();
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            fee_strategy: Some(Arc::new(CongestionFee {
+                base_fee: 2,
+                saturation_bytes: queue_bytes,
+                max_multiplier: 3.0,
+            })),
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+    chain_state.simulate_consensus_round(None).await;
+
+    let estimate = proxy_global_state.estimate_inclusion(TX_SIZE as u64).await;
+    assert_eq!(estimate.queue_len, 0);
+    assert_eq!(estimate.expected_views, Some(1));
+    assert_eq!(estimate.estimated_fee, 2 * TX_SIZE as u64);
+    assert_eq!(estimate.latency, None);
+
+    proxy_global_state
+        .submit_txns(transactions())
+        .await
+        .unwrap();
+
+    let estimate = proxy_global_state.estimate_inclusion(TX_SIZE as u64).await;
+    assert_eq!(estimate.queue_len, NUM_TXNS);
+    assert_eq!(estimate.queue_bytes, queue_bytes);
+    assert_eq!(
+        estimate.expected_views,
+        Some((queue_bytes + TX_SIZE as u64).div_ceil(estimate.max_block_size))
+    );
+    // Queue is saturated, so the maximum multiplier applies
+    assert_eq!(estimate.estimated_fee, 3 * 2 * TX_SIZE as u64);
+}
+
+/// Inclusion estimate inputs should be reused within the refresh period
+#[tokio::test]
+#[traced_test]
+async fn test_inclusion_estimate_cached() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            estimate_refresh_period: Duration::from_secs(60),
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+    chain_state.simulate_consensus_round(None).await;
+
+    let estimate = proxy_global_state.estimate_inclusion(TX_SIZE as u64).await;
+    assert_eq!(estimate.queue_len, 0);
+
+    proxy_global_state
+        .submit_txns(transactions())
+        .await
+        .unwrap();
+
+    // Queue isn't scanned again until the refresh period passes
+    let estimate = proxy_global_state.estimate_inclusion(TX_SIZE as u64).await;
+    assert_eq!(estimate.queue_len, 0);
+}
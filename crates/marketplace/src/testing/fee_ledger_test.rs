@@ -0,0 +1,63 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use async_broadcast::broadcast;
+use committable::Committable;
+use hotshot_builder_api::v0_99::data_source::{AcceptsTxnSubmits, BuilderDataSource};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use marketplace_builder_shared::{
+    fee_ledger::{FeeLedgerDataSource, FeeStatus, FeeSubject},
+    testing::consensus::SimulatedChainState,
+};
+use tracing_test::traced_test;
+
+use crate::{
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+/// Bundles served are recorded in the fee ledger
+#[tokio::test]
+#[traced_test]
+async fn test_bundle_fees_recorded() {
+    let global_state = GlobalState::new(BuilderConfig::test(), NoHooks(PhantomData));
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    let transaction = TestTransaction::new(vec![0]);
+    proxy_global_state
+        .submit_txns(vec![transaction.clone()])
+        .await
+        .unwrap();
+    let state_id = chain_state.simulate_consensus_round(None).await;
+
+    let bundle = proxy_global_state
+        .bundle(*state_id.parent_view, &state_id.parent_commitment, 1)
+        .await
+        .unwrap();
+
+    let records = proxy_global_state.fee_ledger().report(1..2).await;
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(record.view, 1);
+    assert_eq!(
+        record.subject,
+        FeeSubject::Bundle(vec![transaction.commit()])
+    );
+    assert_eq!(record.amount, bundle.sequencing_fee.fee_amount);
+    assert_eq!(record.account, bundle.sequencing_fee.fee_account);
+    assert_eq!(record.status, FeeStatus::Offered);
+
+    // Nothing outside of the requested range
+    assert!(proxy_global_state.fee_ledger().report(2..).await.is_empty());
+
+    let mut csv = Vec::new();
+    proxy_global_state
+        .fee_ledger()
+        .export_csv(.., &mut csv)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 2);
+}
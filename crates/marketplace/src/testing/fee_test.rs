@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use async_broadcast::broadcast;
+use hotshot_builder_api::v0_99::data_source::{AcceptsTxnSubmits, BuilderDataSource};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use marketplace_builder_shared::{state::QueueStatistics, testing::consensus::SimulatedChainState};
+use tracing_test::traced_test;
+
+use crate::{
+    fee::{CongestionFee, FeeContext, FeeStrategy, FixedFee, UserFeeShare},
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+/// Mock user fee declaration: first byte of the transaction
+fn first_byte_fee(txn: &TestTransaction) -> u64 {
+    txn.bytes().first().copied().unwrap_or_default().into()
+}
+
+fn transactions() -> Vec<TestTransaction> {
+    vec![
+        TestTransaction::new(vec![100; 10]),
+        TestTransaction::new(vec![50; 20]),
+    ]
+}
+
+#[test]
+#[traced_test]
+fn test_fixed_fee() {
+    let transactions = transactions();
+    let queue = QueueStatistics::default();
+    let context = FeeContext::<TestTypes>::new(&transactions, &queue, 1);
+
+    assert_eq!(
+        FixedFee { base_fee: 3 }.offered_fee(&context),
+        3 * context.bundle_size
+    );
+}
+
+#[test]
+#[traced_test]
+fn test_congestion_fee() {
+    let strategy = CongestionFee {
+        base_fee: 2,
+        saturation_bytes: 1000,
+        max_multiplier: 3.0,
+    };
+    let transactions = transactions();
+    let base =
+        2 * FeeContext::<TestTypes>::new(&transactions, &QueueStatistics::default(), 1).bundle_size;
+
+    let fee_at = |total_bytes| {
+        let queue = QueueStatistics {
+            total_bytes,
+            ..Default::default()
+        };
+        strategy.offered_fee(&FeeContext::<TestTypes>::new(&transactions, &queue, 1))
+    };
+
+    assert_eq!(fee_at(0), base, "No congestion, no markup");
+    assert_eq!(fee_at(500), base * 2, "Half-saturated queue");
+    assert_eq!(fee_at(1000), base * 3, "Saturated queue");
+    assert_eq!(fee_at(10_000), base * 3, "Multiplier is capped");
+}
+
+#[test]
+#[traced_test]
+fn test_user_fee_share() {
+    let transactions = transactions();
+    let queue = QueueStatistics::default();
+    let context = FeeContext::<TestTypes>::new(&transactions, &queue, 1);
+
+    let strategy = UserFeeShare::<TestTypes> {
+        user_fee: Arc::new(first_byte_fee),
+        share_bps: 5_000,
+        floor: FixedFee { base_fee: 0 },
+    };
+    assert_eq!(strategy.offered_fee(&context), 75);
+
+    let strategy = UserFeeShare {
+        floor: FixedFee { base_fee: 10 },
+        ..strategy
+    };
+    assert_eq!(
+        strategy.offered_fee(&context),
+        10 * context.bundle_size,
+        "Floor should apply when the share is lower"
+    );
+}
+
+/// Check that the builder offers the fee determined by configured strategy
+#[tokio::test]
+#[traced_test]
+async fn test_bundle_fee_strategy() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            fee_strategy: Some(Arc::new(UserFeeShare::<TestTypes> {
+                user_fee: Arc::new(first_byte_fee),
+                share_bps: 10_000,
+                floor: FixedFee { base_fee: 0 },
+            })),
+            ..BuilderConfig::test()
+        },
+        NoHooks(std::marker::PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    proxy_global_state
+        .submit_txns(transactions())
+        .await
+        .unwrap();
+
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    let bundle = proxy_global_state
+        .bundle(
+            *builder_state_id.parent_view,
+            &builder_state_id.parent_commitment,
+            1,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(bundle.transactions, transactions());
+    assert_eq!(bundle.sequencing_fee.fee_amount, 150);
+}
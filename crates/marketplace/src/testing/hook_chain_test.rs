@@ -0,0 +1,215 @@
+use std::sync::Arc;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+use hotshot::types::{Event, EventType};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{block_contents::Transaction, node_implementation::ConsensusTime},
+};
+use marketplace_builder_shared::utils::{read_recording, RecordingFormat};
+use tracing_test::traced_test;
+
+use crate::hooks::{
+    BuilderHooks, EventLogger, HookChain, PrefixFilter, SizeFilter, TransactionVerdict,
+};
+
+/// Hooks recording calls made to them under `name` and rejecting transactions starting with `reject`
+struct TracingHooks {
+    name: &'static str,
+    reject: u8,
+    calls: Arc<Mutex<Vec<&'static str>>>,
+}
+
+#[async_trait]
+impl BuilderHooks<TestTypes> for TracingHooks {
+    async fn process_transactions(
+        &self,
+        transactions: Vec<TestTransaction>,
+    ) -> Vec<TestTransaction> {
+        self.calls.lock().await.push(self.name);
+        transactions
+            .into_iter()
+            .filter(|txn| txn.bytes()[0] != self.reject)
+            .collect()
+    }
+
+    async fn on_bundle_assembled(
+        &self,
+        view_number: u64,
+        transactions: Vec<TestTransaction>,
+    ) -> Option<Vec<TestTransaction>> {
+        self.calls.lock().await.push(self.name);
+        (view_number != u64::from(self.reject)).then_some(transactions)
+    }
+
+    async fn handle_hotshot_event(&self, _event: &Event<TestTypes>) {
+        self.calls.lock().await.push(self.name);
+    }
+}
+
+fn tracing_hooks(
+    calls: &Arc<Mutex<Vec<&'static str>>>,
+) -> (TracingHooks, TracingHooks, TracingHooks) {
+    (
+        TracingHooks {
+            name: "first",
+            reject: 1,
+            calls: Arc::clone(calls),
+        },
+        TracingHooks {
+            name: "second",
+            reject: 2,
+            calls: Arc::clone(calls),
+        },
+        TracingHooks {
+            name: "third",
+            reject: 3,
+            calls: Arc::clone(calls),
+        },
+    )
+}
+
+fn view_finished(view: u64) -> Event<TestTypes> {
+    Event {
+        view_number: ViewNumber::new(view),
+        event: EventType::ViewFinished {
+            view_number: ViewNumber::new(view),
+        },
+    }
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_hook_chain_order_and_short_circuit() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let (first, second, third) = tracing_hooks(&calls);
+    let chain = HookChain::<TestTypes>::new()
+        .with(first)
+        .with(second)
+        .with(third);
+    assert_eq!(chain.len(), 3);
+
+    // Every hook filters its own transactions out
+    let transactions = (0..4).map(|i| TestTransaction::new(vec![i])).collect();
+    assert_eq!(
+        chain.process_transactions(transactions).await,
+        vec![TestTransaction::new(vec![0])]
+    );
+    assert_eq!(*calls.lock().await, vec!["first", "second", "third"]);
+
+    // Nothing left after the first hook, the rest aren't called
+    calls.lock().await.clear();
+    assert!(chain
+        .process_transactions(vec![TestTransaction::new(vec![1])])
+        .await
+        .is_empty());
+    assert_eq!(*calls.lock().await, vec!["first"]);
+
+    // Second hook vetoes the bundle, the third one isn't called
+    calls.lock().await.clear();
+    assert!(chain
+        .on_bundle_assembled(2, vec![TestTransaction::new(vec![0])])
+        .await
+        .is_none());
+    assert_eq!(*calls.lock().await, vec!["first", "second"]);
+
+    // Events are passed to every hook
+    calls.lock().await.clear();
+    chain.handle_hotshot_event(&view_finished(1)).await;
+    assert_eq!(*calls.lock().await, vec!["first", "second", "third"]);
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_tuple_hooks() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let hooks = tracing_hooks(&calls);
+
+    let transactions = (0..4).map(|i| TestTransaction::new(vec![i])).collect();
+    let verdicts = hooks.transaction_verdicts(transactions).await;
+    assert_eq!(*calls.lock().await, vec!["first", "second", "third"]);
+
+    // Transactions rejected by earlier hooks aren't passed on to later ones
+    let rejected = verdicts
+        .iter()
+        .filter(|(_, verdict)| matches!(verdict, TransactionVerdict::Reject(_)))
+        .map(|(txn, _)| txn.bytes()[0])
+        .collect::<Vec<_>>();
+    assert_eq!(rejected, vec![1, 2, 3]);
+    assert!(verdicts.contains(&(TestTransaction::new(vec![0]), TransactionVerdict::Accept)));
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_stock_filters() {
+    let small = TestTransaction::new(vec![0xaa, 0]);
+    let big = TestTransaction::new(vec![0xaa; 64]);
+    let other = TestTransaction::new(vec![0xbb, 0]);
+    let max_len = small.minimum_block_size().max(other.minimum_block_size());
+
+    let size_filter = SizeFilter::new(max_len);
+    let allow = PrefixFilter::<TestTypes>::allow([vec![0xaa]], |txn: &TestTransaction| {
+        txn.bytes().as_slice()
+    });
+    let deny = PrefixFilter::<TestTypes>::deny([vec![0xaa]], |txn: &TestTransaction| {
+        txn.bytes().as_slice()
+    });
+
+    assert_eq!(
+        BuilderHooks::<TestTypes>::process_transactions(
+            &size_filter,
+            vec![small.clone(), big.clone(), other.clone()]
+        )
+        .await,
+        vec![small.clone(), other.clone()]
+    );
+    assert_eq!(
+        allow
+            .process_transactions(vec![small.clone(), big.clone(), other.clone()])
+            .await,
+        vec![small.clone(), big.clone()]
+    );
+    assert_eq!(
+        deny.process_transactions(vec![small.clone(), big.clone(), other.clone()])
+            .await,
+        vec![other.clone()]
+    );
+
+    // Combined, only small transactions with an allowed prefix pass
+    let chain = HookChain::<TestTypes>::new().with(size_filter).with(allow);
+    assert_eq!(
+        chain
+            .process_transactions(vec![small.clone(), big, other])
+            .await,
+        vec![small]
+    );
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_event_logger() {
+    let path = std::env::temp_dir().join(format!("hook-event-log-{}", std::process::id()));
+    let logger = EventLogger::<TestTypes>::new(&path, RecordingFormat::JsonLines).unwrap();
+
+    for view in 0..3 {
+        logger.handle_hotshot_event(&view_finished(view)).await;
+    }
+
+    logger.finish().await;
+
+    let events = read_recording::<TestTypes>(&path, RecordingFormat::JsonLines)
+        .unwrap()
+        .collect::<anyhow::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(
+        events
+            .iter()
+            .map(|recorded| *recorded.event.view_number)
+            .collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
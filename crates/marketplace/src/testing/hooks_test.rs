@@ -0,0 +1,464 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_broadcast::broadcast;
+use async_lock::Mutex;
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use hotshot::types::{Event, EventType};
+use hotshot_builder_api::{
+    v0_2::builder::TransactionStatus,
+    v0_99::data_source::{AcceptsTxnSubmits, BuilderDataSource},
+};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{TestTypes, TestVersions},
+};
+use hotshot_types::{
+    data::ViewNumber,
+    simple_certificate::QuorumCertificate,
+    traits::{block_contents::Transaction, node_implementation::ConsensusTime},
+};
+use marketplace_builder_shared::testing::{consensus::SimulatedChainState, mock};
+use tokio::time::sleep;
+use tracing_test::traced_test;
+
+use crate::{
+    hooks::{
+        BuilderHooks, DeferralPolicy, RejectionReason, TransactionVerdict, FILTERED_OUT_REASON,
+    },
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+/// Hooks recording lifecycle calls, reversing bundles and vetoing bundles for view 13
+#[derive(Default)]
+struct RecordingHooks {
+    sequenced: Mutex<Vec<(ViewNumber, Vec<Commitment<TestTransaction>>)>>,
+    rejected: Mutex<Vec<(Commitment<TestTransaction>, RejectionReason)>>,
+}
+
+const VETOED_VIEW: u64 = 13;
+
+#[async_trait]
+impl BuilderHooks<TestTypes> for Arc<RecordingHooks> {
+    async fn on_bundle_assembled(
+        &self,
+        view_number: u64,
+        mut transactions: Vec<TestTransaction>,
+    ) -> Option<Vec<TestTransaction>> {
+        if view_number == VETOED_VIEW {
+            return None;
+        }
+        transactions.reverse();
+        Some(transactions)
+    }
+
+    async fn on_transactions_sequenced(
+        &self,
+        view_number: ViewNumber,
+        commitments: &[Commitment<TestTransaction>],
+    ) {
+        self.sequenced
+            .lock()
+            .await
+            .push((view_number, commitments.to_vec()));
+    }
+
+    async fn on_transaction_rejected(
+        &self,
+        commitment: Commitment<TestTransaction>,
+        reason: &RejectionReason,
+    ) {
+        self.rejected
+            .lock()
+            .await
+            .push((commitment, reason.clone()));
+    }
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_lifecycle_hooks() {
+    let hooks = Arc::new(RecordingHooks::default());
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            protocol_max_bundle_size: 10,
+            ..BuilderConfig::test()
+        },
+        Arc::clone(&hooks),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender.clone());
+
+    // Rejection hook
+    let too_big = TestTransaction::new(vec![0; 11]);
+    proxy_global_state
+        .submit_txns(vec![too_big.clone()])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        *hooks.rejected.lock().await,
+        vec![(
+            too_big.commit(),
+            RejectionReason::TooBig {
+                len: too_big.minimum_block_size(),
+                max_len: 10
+            }
+        )]
+    );
+
+    // Bundle hook
+    let transactions = vec![TestTransaction::new(vec![1]), TestTransaction::new(vec![2])];
+    proxy_global_state
+        .submit_txns(transactions.clone())
+        .await
+        .unwrap();
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    let bundle = proxy_global_state
+        .bundle(
+            *builder_state_id.parent_view,
+            &builder_state_id.parent_commitment,
+            1,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        bundle.transactions,
+        transactions.iter().rev().cloned().collect::<Vec<_>>()
+    );
+
+    proxy_global_state
+        .bundle(
+            *builder_state_id.parent_view,
+            &builder_state_id.parent_commitment,
+            VETOED_VIEW,
+        )
+        .await
+        .unwrap_err();
+
+    // Sequencing hook
+    let leaf_chain = mock::decide_leaf_chain_with_transactions(2, transactions.clone()).await;
+    let qc = QuorumCertificate::genesis::<TestVersions>(&Default::default(), &Default::default())
+        .await
+        .to_qc2();
+    event_stream_sender
+        .broadcast(Event {
+            view_number: ViewNumber::new(2),
+            event: EventType::Decide {
+                leaf_chain,
+                qc: Arc::new(qc),
+                block_size: None,
+            },
+        })
+        .await
+        .unwrap();
+
+    // Give builder time to handle decide event
+    sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(
+        *hooks.sequenced.lock().await,
+        vec![(
+            ViewNumber::new(2),
+            transactions.iter().map(Committable::commit).collect()
+        )]
+    );
+}
+
+/// Hooks accepting transactions starting with 0, rejecting ones starting with 1
+/// and deferring ones starting with 2 until released
+#[derive(Default)]
+struct VerdictHooks {
+    released: AtomicBool,
+}
+
+#[async_trait]
+impl BuilderHooks<TestTypes> for Arc<VerdictHooks> {
+    async fn transaction_verdicts(
+        &self,
+        transactions: Vec<TestTransaction>,
+    ) -> Vec<(TestTransaction, TransactionVerdict)> {
+        transactions
+            .into_iter()
+            .map(|txn| {
+                let verdict = match txn.bytes()[0] {
+                    0 => TransactionVerdict::Accept,
+                    2 if self.released.load(Ordering::SeqCst) => TransactionVerdict::Accept,
+                    2 => TransactionVerdict::Defer,
+                    _ => TransactionVerdict::Reject("Starts with 1".to_owned()),
+                };
+                (txn, verdict)
+            })
+            .collect()
+    }
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_transaction_verdicts() {
+    let hooks = Arc::new(VerdictHooks::default());
+    let global_state = GlobalState::new(BuilderConfig::test(), Arc::clone(&hooks));
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender.clone());
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    let accepted = TestTransaction::new(vec![0]);
+    let rejected = TestTransaction::new(vec![1]);
+    let deferred = TestTransaction::new(vec![2]);
+    let results = proxy_global_state
+        .submit_txns_with_results(vec![accepted.clone(), rejected.clone(), deferred.clone()])
+        .await;
+    assert_eq!(results[0].as_ref().unwrap(), &accepted.commit());
+    assert!(results[1].is_err());
+    assert_eq!(results[2].as_ref().unwrap(), &deferred.commit());
+
+    assert_eq!(
+        proxy_global_state
+            .txn_status(rejected.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Rejected {
+            reason: "Starts with 1".to_owned()
+        }
+    );
+    assert_eq!(
+        proxy_global_state
+            .txn_status(deferred.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Pending
+    );
+
+    let bundle = proxy_global_state
+        .bundle(
+            *builder_state_id.parent_view,
+            &builder_state_id.parent_commitment,
+            1,
+        )
+        .await
+        .unwrap();
+    assert_eq!(bundle.transactions, vec![accepted.clone()]);
+
+    // Deferred transactions are evaluated again once the view finishes
+    hooks.released.store(true, Ordering::SeqCst);
+    event_stream_sender
+        .broadcast(Event {
+            view_number: ViewNumber::new(1),
+            event: EventType::ViewFinished {
+                view_number: ViewNumber::new(1),
+            },
+        })
+        .await
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let bundle = proxy_global_state
+        .bundle(
+            *builder_state_id.parent_view,
+            &builder_state_id.parent_commitment,
+            2,
+        )
+        .await
+        .unwrap();
+    assert_eq!(bundle.transactions, vec![accepted, deferred]);
+}
+
+/// Check that submissions through the API are rejected as a whole
+/// if any of the transactions is rejected
+#[tokio::test]
+#[traced_test]
+async fn test_rejected_batch_not_submitted() {
+    let hooks = Arc::new(VerdictHooks::default());
+    let global_state = GlobalState::new(BuilderConfig::test(), Arc::clone(&hooks));
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let accepted = TestTransaction::new(vec![0]);
+    let rejected = TestTransaction::new(vec![1]);
+    let deferred = TestTransaction::new(vec![2]);
+    let err = proxy_global_state
+        .submit_txns(vec![accepted.clone(), rejected.clone(), deferred.clone()])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Starts with 1"), "{err}");
+
+    assert!(matches!(
+        proxy_global_state
+            .txn_status(rejected.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Rejected { .. }
+    ));
+    // Nothing else was passed on to the builder or deferred
+    for txn in [accepted, deferred] {
+        assert_eq!(
+            proxy_global_state.txn_status(txn.commit()).await.unwrap(),
+            TransactionStatus::Unknown
+        );
+    }
+    assert!(global_state.shutdown().await.is_empty());
+}
+
+/// Check that deferred transactions are rejected once [`DeferralPolicy`] limits are reached
+#[tokio::test]
+#[traced_test]
+async fn test_deferral_policy() {
+    let hooks = Arc::new(VerdictHooks::default());
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            deferral_policy: DeferralPolicy {
+                capacity: 1,
+                max_deferrals: 2,
+            },
+            ..BuilderConfig::test()
+        },
+        Arc::clone(&hooks),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+
+    let deferred = TestTransaction::new(vec![2, 0]);
+    let over_capacity = TestTransaction::new(vec![2, 1]);
+    let results = proxy_global_state
+        .submit_txns_with_results(vec![deferred.clone(), over_capacity.clone()])
+        .await;
+    assert_eq!(results[0].as_ref().unwrap(), &deferred.commit());
+    assert!(results[1].is_err());
+    assert_eq!(
+        proxy_global_state
+            .txn_status(over_capacity.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Rejected {
+            reason: RejectionReason::DeferralCapacity.to_string()
+        }
+    );
+
+    let finish_view = |view| {
+        let event_stream_sender = event_stream_sender.clone();
+        async move {
+            event_stream_sender
+                .broadcast(Event {
+                    view_number: ViewNumber::new(view),
+                    event: EventType::ViewFinished {
+                        view_number: ViewNumber::new(view),
+                    },
+                })
+                .await
+                .unwrap();
+            sleep(Duration::from_millis(100)).await;
+        }
+    };
+
+    // Deferred for the second time, still within limits
+    finish_view(1).await;
+    assert_eq!(
+        proxy_global_state
+            .txn_status(deferred.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Pending
+    );
+
+    finish_view(2).await;
+    assert_eq!(
+        proxy_global_state
+            .txn_status(deferred.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Rejected {
+            reason: RejectionReason::DeferredTooLong { max_deferrals: 2 }.to_string()
+        }
+    );
+}
+
+/// Check that concurrent batch submissions can't defer more transactions
+/// than [`DeferralPolicy::capacity`] allows between them
+#[tokio::test(flavor = "multi_thread")]
+#[traced_test]
+async fn test_concurrent_batches_respect_deferral_capacity() {
+    const BATCH_SIZE: u8 = 2;
+    const NUM_BATCHES: u8 = 8;
+
+    let hooks = Arc::new(VerdictHooks::default());
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            deferral_policy: DeferralPolicy {
+                capacity: BATCH_SIZE.into(),
+                ..Default::default()
+            },
+            ..BuilderConfig::test()
+        },
+        Arc::clone(&hooks),
+    );
+
+    let submissions = (0..NUM_BATCHES).map(|batch| {
+        let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+        let txns = (0..BATCH_SIZE)
+            .map(|i| TestTransaction::new(vec![2, batch, i]))
+            .collect::<Vec<_>>();
+        tokio::spawn(async move { proxy_global_state.submit_txns(txns).await })
+    });
+    let results = futures::future::join_all(submissions).await;
+
+    // Only one batch fits, the rest are rejected as a whole
+    assert_eq!(
+        results
+            .into_iter()
+            .filter(|result| result.as_ref().unwrap().is_ok())
+            .count(),
+        1
+    );
+    assert_eq!(global_state.shutdown().await.len(), BATCH_SIZE.into());
+}
+
+/// Check that transactions filtered out by `process_transactions` are reported as rejected
+#[tokio::test]
+#[traced_test]
+async fn test_filtered_out_transactions_rejected() {
+    struct Filter;
+
+    #[async_trait]
+    impl BuilderHooks<TestTypes> for Filter {
+        async fn process_transactions(
+            &self,
+            transactions: Vec<TestTransaction>,
+        ) -> Vec<TestTransaction> {
+            transactions
+                .into_iter()
+                .filter(|txn| txn.bytes()[0] == 0)
+                .collect()
+        }
+    }
+
+    let global_state = GlobalState::new(BuilderConfig::test(), Filter);
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let filtered_out = TestTransaction::new(vec![1]);
+    proxy_global_state
+        .submit_txns(vec![TestTransaction::new(vec![0]), filtered_out.clone()])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        proxy_global_state
+            .txn_status(filtered_out.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Rejected {
+            reason: FILTERED_OUT_REASON.to_owned()
+        }
+    );
+}
@@ -0,0 +1,107 @@
+//! Minimal in-process solver accepting bids, for testing [`crate::bidding`]
+
+use std::{sync::Arc, time::Duration};
+
+use async_lock::RwLock;
+use async_trait::async_trait;
+use futures::{future::BoxFuture, FutureExt};
+use hotshot_types::traits::node_implementation::NodeType;
+use surf_disco::Client;
+use tide_disco::{
+    error::ServerError,
+    method::{ReadState, WriteState},
+    Api, App, Error as _,
+};
+use tokio::{spawn, task::JoinHandle};
+use url::Url;
+
+use crate::bidding::{BidTx, SolverApiVersion};
+
+const SOLVER_API: &str = r#"
+[route.submit_bid]
+PATH = ["submit_bid"]
+METHOD = "POST"
+DOC = "Submit a signed bid"
+"#;
+
+/// Records all bids submitted to it
+pub struct MockSolver<Types: NodeType> {
+    bids: Arc<RwLock<Vec<BidTx<Types>>>>,
+}
+
+impl<Types: NodeType> Clone for MockSolver<Types> {
+    fn clone(&self) -> Self {
+        Self {
+            bids: Arc::clone(&self.bids),
+        }
+    }
+}
+
+impl<Types: NodeType> MockSolver<Types> {
+    /// Start serving solver API at `url` and wait until it's ready to accept bids.
+    /// Returns the solver, which can be used to inspect received bids,
+    /// and the handle of the server task.
+    pub async fn spawn(url: Url) -> (Self, JoinHandle<()>) {
+        let solver = Self {
+            bids: Arc::new(RwLock::new(Vec::new())),
+        };
+
+        let mut api = Api::<Self, ServerError, SolverApiVersion>::new(
+            toml::from_str::<toml::Value>(SOLVER_API).unwrap(),
+        )
+        .unwrap();
+        api.post("submit_bid", |req, bids| {
+            async move {
+                let bid = req
+                    .body_auto::<BidTx<Types>, SolverApiVersion>(SolverApiVersion {})
+                    .map_err(ServerError::from_request_error)?;
+                bids.push(bid);
+                Ok(())
+            }
+            .boxed()
+        })
+        .unwrap();
+
+        let mut app = App::<Self, ServerError>::with_state(solver.clone());
+        app.register_module("marketplace-solver", api).unwrap();
+        let handle = spawn({
+            let url = url.clone();
+            async move { app.serve(url, SolverApiVersion {}).await.unwrap() }
+        });
+
+        let client = Client::<ServerError, SolverApiVersion>::new(url);
+        assert!(
+            client.connect(Some(Duration::from_secs(5))).await,
+            "Mock solver didn't start"
+        );
+
+        (solver, handle)
+    }
+
+    /// Bids received so far, in order of arrival
+    pub async fn bids(&self) -> Vec<BidTx<Types>> {
+        self.bids.read().await.clone()
+    }
+}
+
+#[async_trait]
+impl<Types: NodeType> ReadState for MockSolver<Types> {
+    type State = Vec<BidTx<Types>>;
+
+    async fn read<T>(
+        &self,
+        op: impl Send + for<'a> FnOnce(&'a Self::State) -> BoxFuture<'a, T> + 'async_trait,
+    ) -> T {
+        op(&*self.bids.read().await).await
+    }
+}
+
+#[async_trait]
+impl<Types: NodeType> WriteState for MockSolver<Types> {
+    async fn write<T>(
+        &self,
+        op: impl Send + for<'a> FnOnce(&'a mut Self::State) -> BoxFuture<'a, T> + 'async_trait,
+    ) -> T {
+        op(&mut *self.bids.write().await).await
+    }
+}
@@ -1,3 +1,17 @@
+pub mod auth_test;
 pub mod basic_test;
+pub mod bidding_test;
+pub mod bundle_cache_test;
+pub mod bundle_size_test;
+pub mod estimate_test;
+pub mod fee_ledger_test;
+pub mod fee_test;
+pub mod hook_chain_test;
+pub mod hooks_test;
 pub mod integration;
+pub mod mock_solver;
+pub mod namespace_test;
+pub mod offer_test;
 pub mod order_test;
+pub mod shutdown_test;
+pub mod submit_limit_test;
@@ -0,0 +1,189 @@
+use std::{collections::BTreeSet, marker::PhantomData, sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use committable::Committable;
+use hotshot::types::{Event, EventType};
+use hotshot_builder_api::{
+    v0_2::builder::TransactionStatus,
+    v0_99::data_source::{AcceptsTxnSubmits, BuilderDataSource},
+};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use marketplace_builder_shared::testing::consensus::SimulatedChainState;
+use tokio::time::sleep;
+use tracing_test::traced_test;
+
+use crate::{
+    hooks::NoHooks,
+    namespace::{NamespaceConfig, NamespaceId},
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+/// Mock namespace declaration: first byte of the transaction
+fn first_byte_namespace(txn: &TestTransaction) -> NamespaceId {
+    txn.bytes().first().copied().unwrap_or_default().into()
+}
+
+fn namespace_config() -> NamespaceConfig<TestTypes> {
+    NamespaceConfig {
+        served: BTreeSet::from([1, 2]),
+        namespace_of: Arc::new(first_byte_namespace),
+    }
+}
+
+/// Check that private submissions for namespaces the builder doesn't serve
+/// are rejected, with the reason visible through transaction status API
+#[tokio::test]
+#[traced_test]
+async fn test_unserved_namespace_submission_rejected() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            namespaces: Some(namespace_config()),
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let served = TestTransaction::new(vec![1, 0]);
+    proxy_global_state
+        .submit_txns(vec![served.clone()])
+        .await
+        .unwrap();
+    assert_eq!(
+        proxy_global_state
+            .txn_status(served.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Pending
+    );
+
+    let unserved = TestTransaction::new(vec![3, 0]);
+    let err = proxy_global_state
+        .submit_txns(vec![TestTransaction::new(vec![2, 0]), unserved.clone()])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("namespace 3"), "{err}");
+    assert!(matches!(
+        proxy_global_state
+            .txn_status(unserved.commit())
+            .await
+            .unwrap(),
+        TransactionStatus::Rejected { .. }
+    ));
+}
+
+/// Check that bundles only include transactions from served namespaces,
+/// including ones received from the public mempool
+#[tokio::test]
+#[traced_test]
+async fn test_bundle_filters_namespaces() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            namespaces: Some(namespace_config()),
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender.clone());
+
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    let public_transactions = vec![
+        TestTransaction::new(vec![1, 1]),
+        TestTransaction::new(vec![3, 1]),
+        TestTransaction::new(vec![2, 1]),
+        TestTransaction::new(vec![4, 1]),
+    ];
+    event_stream_sender
+        .broadcast(Event {
+            view_number: ViewNumber::genesis(),
+            event: EventType::Transactions {
+                transactions: public_transactions.clone(),
+            },
+        })
+        .await
+        .unwrap();
+
+    // Let the event loop enqueue transactions
+    sleep(Duration::from_millis(100)).await;
+
+    let bundle = proxy_global_state
+        .bundle(
+            *builder_state_id.parent_view,
+            &builder_state_id.parent_commitment,
+            1,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        bundle.transactions,
+        vec![
+            public_transactions[0].clone(),
+            public_transactions[2].clone()
+        ]
+    );
+}
+
+/// Check that transactions from namespaces the builder doesn't serve
+/// don't cut waiting for transactions short
+#[tokio::test]
+#[traced_test]
+async fn test_bundle_waits_for_served_namespace() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            namespaces: Some(namespace_config()),
+            tx_capture_timeout: Duration::from_secs(1),
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender.clone());
+
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    let send_transaction = |transaction: TestTransaction| {
+        let event_stream_sender = event_stream_sender.clone();
+        async move {
+            event_stream_sender
+                .broadcast(Event {
+                    view_number: ViewNumber::genesis(),
+                    event: EventType::Transactions {
+                        transactions: vec![transaction],
+                    },
+                })
+                .await
+                .unwrap();
+        }
+    };
+
+    send_transaction(TestTransaction::new(vec![3, 1])).await;
+    // Let the event loop enqueue the transaction
+    sleep(Duration::from_millis(100)).await;
+
+    let bundle = tokio::spawn(async move {
+        proxy_global_state
+            .bundle(
+                *builder_state_id.parent_view,
+                &builder_state_id.parent_commitment,
+                1,
+            )
+            .await
+    });
+
+    sleep(Duration::from_millis(200)).await;
+    let served = TestTransaction::new(vec![1, 1]);
+    send_transaction(served.clone()).await;
+
+    let bundle = bundle.await.unwrap().unwrap();
+    assert_eq!(bundle.transactions, vec![served]);
+}
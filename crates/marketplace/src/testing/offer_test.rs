@@ -0,0 +1,110 @@
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use hotshot_builder_api::v0_99::data_source::{AcceptsTxnSubmits, BuilderDataSource};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use marketplace_builder_shared::{
+    block::BuilderStateId, coordinator::offers::OfferPolicy,
+    testing::consensus::SimulatedChainState,
+};
+use tracing_test::traced_test;
+
+use crate::{
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+type TestProxy = ProxyGlobalState<TestTypes, NoHooks<TestTypes>>;
+
+/// Start a builder with given offer policy, submit a transaction and simulate a consensus round,
+/// returning the builder, the transaction and the state to request bundles from
+async fn setup(policy: OfferPolicy) -> (TestProxy, TestTransaction, BuilderStateId<TestTypes>) {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            offer_policy: policy,
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    global_state.start_event_loop(event_stream);
+    let mut chain_state = SimulatedChainState::new(event_stream_sender);
+
+    let transaction = TestTransaction::new(vec![0]);
+    proxy_global_state
+        .submit_txns(vec![transaction.clone()])
+        .await
+        .unwrap();
+    let builder_state_id = chain_state.simulate_consensus_round(None).await;
+
+    (proxy_global_state, transaction, builder_state_id)
+}
+
+/// Request bundles for `views` in order, returning number of transactions in each
+async fn bundle_lens(
+    proxy_global_state: &TestProxy,
+    state_id: &BuilderStateId<TestTypes>,
+    views: impl IntoIterator<Item = u64>,
+) -> Vec<usize> {
+    let mut lens = Vec::new();
+    for view in views {
+        let bundle = proxy_global_state
+            .bundle(*state_id.parent_view, &state_id.parent_commitment, view)
+            .await
+            .unwrap();
+        lens.push(bundle.transactions.len());
+    }
+    lens
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_allow_duplicates() {
+    let (proxy_global_state, _, state_id) = setup(OfferPolicy::AllowDuplicates).await;
+    assert_eq!(
+        bundle_lens(&proxy_global_state, &state_id, [2, 1, 3]).await,
+        vec![1, 1, 1]
+    );
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_exclude_offered_for_higher_view() {
+    let (proxy_global_state, transaction, state_id) =
+        setup(OfferPolicy::ExcludeOfferedForHigherView).await;
+
+    // Lower view is excluded, the same and higher views aren't
+    assert_eq!(
+        bundle_lens(&proxy_global_state, &state_id, [2, 1, 2, 3]).await,
+        vec![1, 0, 1, 1]
+    );
+
+    let bundle = proxy_global_state
+        .bundle(*state_id.parent_view, &state_id.parent_commitment, 3)
+        .await
+        .unwrap();
+    assert_eq!(bundle.transactions, vec![transaction]);
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_exclude_until_decided_or_expired() {
+    const TTL: Duration = Duration::from_millis(500);
+
+    let (proxy_global_state, _, state_id) =
+        setup(OfferPolicy::ExcludeUntilDecidedOrExpired { ttl: TTL }).await;
+
+    assert_eq!(
+        bundle_lens(&proxy_global_state, &state_id, [1, 2, 1]).await,
+        vec![1, 0, 1]
+    );
+
+    // Once the offer expires, the transaction can be offered for other views
+    tokio::time::sleep(TTL).await;
+    assert_eq!(
+        bundle_lens(&proxy_global_state, &state_id, [3]).await,
+        vec![1]
+    );
+}
@@ -0,0 +1,119 @@
+use std::{sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use async_trait::async_trait;
+use committable::Committable;
+use hotshot::types::{Event, EventType};
+use hotshot_builder_api::{
+    v0_2::builder::TransactionStatus,
+    v0_99::{builder::BuildError, data_source::AcceptsTxnSubmits},
+};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use marketplace_builder_shared::{error::Error, shutdown::ShutdownPhase};
+use tokio::time::{sleep, timeout};
+use tracing_test::traced_test;
+
+use crate::{
+    hooks::{BuilderHooks, TransactionVerdict},
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Hooks deferring transactions starting with 2
+struct DeferringHooks;
+
+#[async_trait]
+impl BuilderHooks<TestTypes> for DeferringHooks {
+    async fn transaction_verdicts(
+        &self,
+        transactions: Vec<TestTransaction>,
+    ) -> Vec<(TestTransaction, TransactionVerdict)> {
+        transactions
+            .into_iter()
+            .map(|txn| {
+                let verdict = if txn.bytes()[0] == 2 {
+                    TransactionVerdict::Defer
+                } else {
+                    TransactionVerdict::Accept
+                };
+                (txn, verdict)
+            })
+            .collect()
+    }
+}
+
+/// Shutdown should reject new private submissions, wait for in-flight requests,
+/// export pending and deferred transactions and stop the event loop
+#[tokio::test]
+#[traced_test]
+async fn test_graceful_shutdown() {
+    let global_state = GlobalState::new(BuilderConfig::test(), DeferringHooks);
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let (event_stream_sender, event_stream) = broadcast(1024);
+    let event_loop = global_state.start_event_loop(event_stream);
+
+    let accepted = TestTransaction::new(vec![0]);
+    let deferred = TestTransaction::new(vec![2]);
+    proxy_global_state
+        .submit_txns(vec![accepted.clone(), deferred.clone()])
+        .await
+        .unwrap();
+
+    // An in-flight request holds up the shutdown
+    let in_flight = global_state.shutdown_handle().track();
+    let shutdown = Arc::clone(&global_state).shutdown_on(std::future::ready(()));
+    timeout(
+        TIMEOUT,
+        global_state
+            .shutdown_handle()
+            .reached(ShutdownPhase::Draining),
+    )
+    .await
+    .unwrap();
+
+    let results = proxy_global_state
+        .submit_txns_with_results(vec![TestTransaction::new(vec![0, 1])])
+        .await;
+    let BuildError::Error(expected) = Error::<TestTypes>::ShuttingDown.into() else {
+        panic!("Unexpected conversion of Error to BuildError");
+    };
+    assert!(matches!(&results[0], Err(BuildError::Error(message)) if *message == expected));
+
+    // Public transactions are still accepted and exported
+    let public = TestTransaction::new(vec![0, 2]);
+    event_stream_sender
+        .broadcast(Event {
+            view_number: ViewNumber::genesis(),
+            event: EventType::Transactions {
+                transactions: vec![public.clone()],
+            },
+        })
+        .await
+        .unwrap();
+    timeout(TIMEOUT, async {
+        while !matches!(
+            proxy_global_state.txn_status(public.commit()).await,
+            Ok(TransactionStatus::Pending)
+        ) {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Public transaction wasn't accepted while shutting down");
+
+    sleep(Duration::from_millis(100)).await;
+    assert!(!shutdown.is_finished());
+
+    drop(in_flight);
+    let pending = timeout(TIMEOUT, shutdown).await.unwrap().unwrap();
+    assert_eq!(pending, vec![accepted, public, deferred]);
+
+    timeout(TIMEOUT, event_loop)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
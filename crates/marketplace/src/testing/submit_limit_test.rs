@@ -0,0 +1,311 @@
+use std::{collections::HashMap, marker::PhantomData, sync::Arc, time::Duration};
+
+use committable::{Commitment, Committable};
+use hotshot_builder_api::{
+    v0_2::builder::TransactionStatus,
+    v0_99::builder::{submit_api, BuildError, Error as BuilderApiError},
+};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use marketplace_builder_shared::{
+    rate_limit::RateLimit,
+    submit_limit::{
+        PrivateMempoolApiVersion, SubmitLimiterMetrics, SubmitLimits, PRIVATE_MEMPOOL_MODULE,
+    },
+    utils::RetryAfterListener,
+};
+use surf_disco::Client;
+use tide_disco::{App, Error as _, StatusCode};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    spawn,
+};
+use tracing_test::traced_test;
+use url::Url;
+use vbs::{version::StaticVersion, Serializer};
+
+use crate::{
+    hooks::NoHooks,
+    service::{BuilderConfig, GlobalState, ProxyGlobalState},
+};
+
+fn transactions(first: u8, num: u8) -> Vec<TestTransaction> {
+    (first..first + num)
+        .map(|i| TestTransaction::new(vec![i; 8]))
+        .collect()
+}
+
+/// Private submissions should be limited per client, rejecting whole batches
+/// over the limits with an error describing the violation
+#[tokio::test]
+#[traced_test]
+async fn test_submit_limits() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            submit_limits: SubmitLimits {
+                transactions: Some(RateLimit {
+                    burst: 4,
+                    replenish_period: Duration::from_secs(3600),
+                }),
+                max_batch_size: Some(3),
+                ..Default::default()
+            },
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(global_state);
+
+    let assert_rejected = |results: Vec<Result<_, BuildError>>, expected: &str| {
+        for result in results {
+            let Err(BuildError::Error(message)) = result else {
+                panic!("Submission should be rejected");
+            };
+            assert!(message.contains(expected), "{message}");
+        }
+    };
+
+    assert_rejected(
+        proxy_global_state
+            .submit_txns_with_results(transactions(0, 4))
+            .await,
+        "Too many transactions",
+    );
+
+    let results = proxy_global_state
+        .submit_txns_with_results(transactions(0, 3))
+        .await;
+    assert!(results.iter().all(Result::is_ok));
+
+    assert_rejected(
+        proxy_global_state
+            .submit_txns_with_results(transactions(3, 2))
+            .await,
+        "Rate limit exceeded",
+    );
+
+    // Other clients have their own allowance
+    let results = proxy_global_state
+        .submit_txns_from("client", transactions(5, 2))
+        .await;
+    assert!(results.iter().all(Result::is_ok));
+
+    assert_eq!(
+        proxy_global_state.submit_limiter_metrics(),
+        SubmitLimiterMetrics {
+            tracked_clients: 2,
+            accepted: 2,
+            rate_limited: 1,
+            oversized: 1,
+        }
+    );
+}
+
+/// Submissions through the private mempool API should be limited per client address,
+/// with rejections reported through distinct statuses
+#[tokio::test]
+#[traced_test]
+async fn test_submit_limits_api() {
+    let global_state = GlobalState::new(
+        BuilderConfig {
+            submit_limits: SubmitLimits {
+                transactions: Some(RateLimit {
+                    burst: 4,
+                    replenish_period: Duration::from_secs(3600),
+                }),
+                ..Default::default()
+            },
+            ..BuilderConfig::test()
+        },
+        NoHooks(PhantomData),
+    );
+    let proxy_global_state = ProxyGlobalState(Arc::clone(&global_state));
+
+    let port = portpicker::pick_unused_port().unwrap();
+    let url: Url = format!("http://localhost:{port}/{PRIVATE_MEMPOOL_MODULE}")
+        .parse()
+        .unwrap();
+    let app = Arc::clone(&global_state).into_app().unwrap();
+    spawn(app.serve(
+        RetryAfterListener(format!("http://localhost:{port}").parse::<Url>().unwrap()),
+        StaticVersion::<0, 1> {},
+    ));
+
+    let connect = || async {
+        let client = Client::<BuilderApiError, PrivateMempoolApiVersion>::new(url.clone());
+        assert!(client.connect(Some(Duration::from_secs(1))).await);
+        client
+    };
+    let submit = |client: Client<BuilderApiError, PrivateMempoolApiVersion>,
+                  txns: Vec<TestTransaction>| async move {
+        client
+            .post::<Vec<Commitment<TestTransaction>>>("batch")
+            .body_binary(&txns)
+            .unwrap()
+            .send()
+            .await
+    };
+
+    // Batch costs more than the client's allowance could ever hold
+    let err = submit(connect().await, transactions(0, 5))
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let txns = transactions(0, 4);
+    let commitments = submit(connect().await, txns.clone()).await.unwrap();
+    assert_eq!(
+        commitments,
+        txns.iter().map(Committable::commit).collect::<Vec<_>>()
+    );
+
+    // New connections from the same address share the allowance
+    let err = submit(connect().await, transactions(4, 1))
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Other clients have their own allowance
+    let results = proxy_global_state
+        .submit_txns_from("client", transactions(5, 1))
+        .await;
+    assert!(results.iter().all(Result::is_ok));
+
+    let metrics = connect()
+        .await
+        .get::<SubmitLimiterMetrics>("limiter")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        metrics,
+        SubmitLimiterMetrics {
+            tracked_clients: 2,
+            accepted: 2,
+            rate_limited: 1,
+            oversized: 1,
+        }
+    );
+
+    // Rejections tell when to retry in a header. Raw requests may connect
+    // from a different address, so keep submitting until rate-limited.
+    let mut retry_after = None;
+    for first in 6..11 {
+        let (status, headers) = submit_raw(port, &transactions(first, 1)).await;
+        if status == 429 {
+            retry_after = headers.get("retry-after").cloned();
+            break;
+        }
+    }
+    let retry_after: u64 = retry_after
+        .expect("Rate-limited response should have a Retry-After header")
+        .parse()
+        .unwrap();
+    assert!((1..=3600).contains(&retry_after));
+}
+
+/// Submit a batch of `txns` to the private mempool served at `port` in a raw HTTP request,
+/// returning the response status and headers with lowercase names,
+/// as `surf_disco` clients don't expose response headers
+async fn submit_raw(port: u16, txns: &[TestTransaction]) -> (u16, HashMap<String, String>) {
+    let body = Serializer::<PrivateMempoolApiVersion>::serialize(&txns.to_vec()).unwrap();
+    let mut stream = TcpStream::connect(("localhost", port)).await.unwrap();
+    let head = format!(
+        "POST /{PRIVATE_MEMPOOL_MODULE}/batch HTTP/1.1\r\n\
+         Host: localhost:{port}\r\n\
+         Content-Type: application/octet-stream\r\n\
+         Accept: application/octet-stream\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream.write_all(&body).await.unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    let response = String::from_utf8_lossy(&response);
+    let (head, _body) = response.split_once("\r\n\r\n").unwrap();
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap()
+        .parse()
+        .unwrap();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_owned()))
+        .collect();
+    (status, headers)
+}
+
+/// Private mempool should serve HotShot's `txn_submit` routes the same way
+/// HotShot's own API does
+#[tokio::test]
+#[traced_test]
+async fn test_private_mempool_api_matches_upstream() {
+    let serve = |app: App<ProxyGlobalState<TestTypes, NoHooks<TestTypes>>, BuilderApiError>| {
+        let port = portpicker::pick_unused_port().unwrap();
+        spawn(app.serve(
+            format!("http://localhost:{port}").parse::<Url>().unwrap(),
+            StaticVersion::<0, 1> {},
+        ));
+        Client::<BuilderApiError, PrivateMempoolApiVersion>::new(
+            format!("http://localhost:{port}/{PRIVATE_MEMPOOL_MODULE}")
+                .parse()
+                .unwrap(),
+        )
+    };
+
+    let upstream_state = GlobalState::new(BuilderConfig::test(), NoHooks(PhantomData));
+    let mut upstream_app = App::with_state(ProxyGlobalState(upstream_state));
+    upstream_app
+        .register_module(
+            PRIVATE_MEMPOOL_MODULE,
+            submit_api::<
+                ProxyGlobalState<TestTypes, NoHooks<TestTypes>>,
+                TestTypes,
+                PrivateMempoolApiVersion,
+            >(&Default::default())
+            .unwrap(),
+        )
+        .unwrap();
+    let upstream = serve(upstream_app);
+    let ours = serve(
+        GlobalState::new(BuilderConfig::test(), NoHooks(PhantomData))
+            .into_app()
+            .unwrap(),
+    );
+
+    for client in [&upstream, &ours] {
+        assert!(client.connect(Some(Duration::from_secs(1))).await);
+    }
+
+    let txn = TestTransaction::new(vec![0; 8]);
+    let batch = transactions(1, 2);
+    let mut responses = Vec::new();
+    for client in [&upstream, &ours] {
+        let commitment = client
+            .post::<Commitment<TestTransaction>>("submit")
+            .body_binary(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        let commitments = client
+            .post::<Vec<Commitment<TestTransaction>>>("batch")
+            .body_binary(&batch)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        let status = client
+            .get::<TransactionStatus>(&format!("status/{commitment}"))
+            .send()
+            .await
+            .unwrap();
+        responses.push((commitment, commitments, format!("{status:?}")));
+    }
+    assert_eq!(responses[0], responses[1]);
+}
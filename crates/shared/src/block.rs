@@ -82,6 +82,14 @@ pub struct BuilderStateId<Types: NodeType> {
     pub parent_commitment: VidCommitment,
 }
 
+impl<Types: NodeType> BuilderStateId<Types> {
+    /// View in which blocks built on top of this state are proposed,
+    /// assuming the view immediately following the parent doesn't fail
+    pub fn proposal_view(&self) -> Types::View {
+        Types::View::new(*self.parent_view + 1)
+    }
+}
+
 impl<Types: NodeType> std::fmt::Display for BuilderStateId<Types> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -0,0 +1,353 @@
+use atomic::Atomic;
+use coarsetime::{Duration, Instant};
+use std::sync::{atomic::Ordering, Mutex};
+
+use crate::latency_model::{LatencyModel, LatencyModelConfig, LatencySample};
+
+/// State of [`BlockSizeLimits`] that changes as the limits are adjusted
+#[derive(Debug, Clone, Copy, bytemuck::NoUninit)]
+#[repr(C)]
+pub(crate) struct MutableState {
+    /// Current block size limits
+    pub(crate) max_block_size: u64,
+    /// Last time we've incremented the max block size, obtained
+    /// as [`coarsetime::Instant::as_ticks()`]
+    pub(crate) last_block_size_increment: u64,
+}
+
+/// How [`BlockSizeLimits`] adjusts the limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlockSizeController {
+    /// Change the limit by its value divided by [`BlockSizeLimits::MAX_BLOCK_SIZE_CHANGE_DIVISOR`]
+    /// on timeouts and periodically
+    #[default]
+    Stepwise,
+    /// Set the limit to the largest block size predicted by a [`LatencyModel`]
+    /// to be precomputed in time, see [`BlockSizeLimits::record_vid_latency`]
+    LatencyModel(LatencyModelConfig),
+}
+
+/// Adjustable limits for block size ceiled by maximum block size allowed by the protocol.
+/// We will avoid build blocks over this size limit for performance reasons: computing VID
+/// for bigger blocks could be too costly and lead to API timeouts.
+///
+/// Will be decremented if we fail to respond to `claim_block_header_input` request in time,
+/// and periodically incremented in two cases
+/// - we've served a response to `claim_block_header_input` in time and the block we've served
+///   was truncated because of our current max block size policy.
+/// - we've served a response to `claim_block_header_input` in time and [`Self::increment_period`]
+///   has passed since last time we've incremented the block limits
+///
+/// With [`BlockSizeController::LatencyModel`], increments set the limit to the size predicted
+/// by the model instead, once it has enough measurements to be fitted. Predictions are only
+/// applied once [`Self::increment_period`] has passed, even for truncated blocks. Decrements
+/// on timeouts work the same way for both controllers, but with the model they also restart
+/// the increment period, as a timeout means the model was too optimistic and its prediction
+/// shouldn't be restored until it's had time to take in new measurements.
+#[derive(Debug)]
+pub struct BlockSizeLimits {
+    /// Current limits, adjusted atomically
+    pub(crate) mutable_state: Atomic<MutableState>,
+    /// Maximum block size as defined by protocol. We'll never increment beyound that
+    pub protocol_max_block_size: u64,
+    /// Period between optimistic increments of the block size
+    pub increment_period: Duration,
+    /// Model of VID precomputation latency, if [`BlockSizeController::LatencyModel`] is used
+    pub(crate) latency_model: Option<Mutex<LatencyModel>>,
+}
+
+impl BlockSizeLimits {
+    /// Never go lower than 10 kilobytes
+    pub const MAX_BLOCK_SIZE_FLOOR: u64 = 10_000;
+    /// When adjusting max block size, it will be decremented or incremented
+    /// by current value / `MAX_BLOCK_SIZE_CHANGE_DIVISOR`
+    pub const MAX_BLOCK_SIZE_CHANGE_DIVISOR: u64 = 10;
+
+    pub fn new(protocol_max_block_size: u64, increment_period: std::time::Duration) -> Self {
+        Self::with_controller(
+            protocol_max_block_size,
+            increment_period,
+            BlockSizeController::Stepwise,
+            // Only used by the latency model
+            std::time::Duration::ZERO,
+        )
+    }
+
+    /// Create limits adjusted by `controller`. With [`BlockSizeController::LatencyModel`],
+    /// blocks are sized for VID precomputation to finish within `max_response_time`
+    /// minus [`LatencyModelConfig::safety_margin`].
+    pub fn with_controller(
+        protocol_max_block_size: u64,
+        increment_period: std::time::Duration,
+        controller: BlockSizeController,
+        max_response_time: std::time::Duration,
+    ) -> Self {
+        Self {
+            protocol_max_block_size,
+            increment_period: increment_period.into(),
+            mutable_state: Atomic::new(MutableState {
+                max_block_size: protocol_max_block_size,
+                last_block_size_increment: Instant::now().as_ticks(),
+            }),
+            latency_model: match controller {
+                BlockSizeController::Stepwise => None,
+                BlockSizeController::LatencyModel(config) => {
+                    Some(Mutex::new(LatencyModel::new(config, max_response_time)))
+                }
+            },
+        }
+    }
+
+    pub fn max_block_size(&self) -> u64 {
+        self.mutable_state
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .max_block_size
+    }
+
+    /// Override [`Self::max_block_size`], e.g. to simulate earlier adjustments in tests.
+    /// Restarts the increment period.
+    pub fn set_max_block_size(&self, max_block_size: u64) {
+        self.mutable_state.store(
+            MutableState {
+                max_block_size,
+                last_block_size_increment: Instant::now().as_ticks(),
+            },
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Whether limits are adjusted by [`BlockSizeController::LatencyModel`],
+    /// in which case [`Self::record_vid_latency`] should be fed measurements
+    pub fn uses_latency_model(&self) -> bool {
+        self.latency_model.is_some()
+    }
+
+    /// Record time it took to precompute VID for a block of `block_size` bytes for `num_nodes`.
+    /// Ignored unless [`BlockSizeController::LatencyModel`] is used.
+    pub fn record_vid_latency(
+        &self,
+        block_size: u64,
+        num_nodes: usize,
+        duration: std::time::Duration,
+    ) {
+        if let Some(model) = &self.latency_model {
+            model
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .record(LatencySample {
+                    block_size,
+                    num_nodes,
+                    duration,
+                });
+        }
+    }
+
+    /// Block size predicted by the latency model, clamped to
+    /// [`Self::MAX_BLOCK_SIZE_FLOOR`] and [`Self::protocol_max_block_size`]
+    fn predicted_max_block_size(&self) -> Option<u64> {
+        let predicted = self
+            .latency_model
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .max_block_size()?;
+        Some(predicted.clamp(
+            Self::MAX_BLOCK_SIZE_FLOOR.min(self.protocol_max_block_size),
+            self.protocol_max_block_size,
+        ))
+    }
+
+    /// If the latency model can be fitted and increment period has elapsed,
+    /// set [`Self::max_block_size`] to its prediction.
+    ///
+    /// Otherwise, if increment period has elapsed or `force` flag is set,
+    /// increment [`Self::max_block_size`] by current value * [`Self::MAX_BLOCK_SIZE_CHANGE_DIVISOR`]
+    /// with [`Self::protocol_max_block_size`] as a ceiling
+    pub fn try_increment_block_size(&self, force: bool) {
+        let period_elapsed = Instant::now().as_ticks().saturating_sub(
+            self.mutable_state
+                .load(Ordering::Relaxed)
+                .last_block_size_increment,
+        ) >= self.increment_period.as_ticks();
+
+        if let Some(max_block_size) = self.predicted_max_block_size() {
+            // Not forced by truncated blocks, which are expected right after a decrement
+            if period_elapsed {
+                self.mutable_state.store(
+                    MutableState {
+                        max_block_size,
+                        last_block_size_increment: Instant::now().as_ticks(),
+                    },
+                    Ordering::Relaxed,
+                );
+            }
+            return;
+        }
+
+        if force || period_elapsed {
+            self.mutable_state
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |previous| {
+                    let max_block_size = std::cmp::min(
+                        previous.max_block_size
+                            + previous
+                                .max_block_size
+                                .div_ceil(Self::MAX_BLOCK_SIZE_CHANGE_DIVISOR),
+                        self.protocol_max_block_size,
+                    );
+                    let last_block_size_increment = Instant::now().as_ticks();
+                    Some(MutableState {
+                        max_block_size,
+                        last_block_size_increment,
+                    })
+                })
+                .expect("Closure always returns Some");
+        }
+    }
+
+    /// Decrement [`Self::max_block_size`] by current value * [`Self::MAX_BLOCK_SIZE_CHANGE_DIVISOR`]
+    /// with [`Self::MAX_BLOCK_SIZE_FLOOR`] as a floor. With the latency model,
+    /// also restarts the increment period.
+    pub fn decrement_block_size(&self) {
+        let now = Instant::now().as_ticks();
+        self.mutable_state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |previous| {
+                let max_block_size = std::cmp::max(
+                    previous.max_block_size.saturating_sub(
+                        previous
+                            .max_block_size
+                            .div_ceil(Self::MAX_BLOCK_SIZE_CHANGE_DIVISOR),
+                    ),
+                    Self::MAX_BLOCK_SIZE_FLOOR,
+                );
+                Some(MutableState {
+                    max_block_size,
+                    last_block_size_increment: if self.uses_latency_model() {
+                        now
+                    } else {
+                        previous.last_block_size_increment
+                    },
+                })
+            })
+            .expect("Closure always returns Some");
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use crate::testing::constants::{
+        TEST_MAX_BLOCK_SIZE_INCREMENT_PERIOD, TEST_PROTOCOL_MAX_BLOCK_SIZE,
+    };
+    use tracing_test::traced_test;
+
+    use super::*;
+
+    #[test]
+    #[traced_test]
+    fn test_increment_block_size() {
+        let mut block_size_limits = BlockSizeLimits::new(
+            TEST_PROTOCOL_MAX_BLOCK_SIZE,
+            std::time::Duration::from_millis(25),
+        );
+        // Simulate decreased limits
+        block_size_limits.mutable_state = Atomic::new(MutableState {
+            max_block_size: TEST_PROTOCOL_MAX_BLOCK_SIZE / 2,
+            last_block_size_increment: Instant::now().as_ticks(),
+        });
+
+        // Shouldn't increment, increment period hasn't passed yet
+        block_size_limits.try_increment_block_size(false);
+        assert!(block_size_limits.max_block_size() == TEST_PROTOCOL_MAX_BLOCK_SIZE / 2);
+
+        // Should increment, increment period hasn't passed yet, but force flag is set
+        block_size_limits.try_increment_block_size(true);
+        assert!(block_size_limits.max_block_size() > TEST_PROTOCOL_MAX_BLOCK_SIZE / 2);
+        let new_size = block_size_limits.max_block_size();
+
+        std::thread::sleep(std::time::Duration::from_millis(30));
+
+        // Should increment, increment period has passed
+        block_size_limits.try_increment_block_size(false);
+        assert!(block_size_limits.max_block_size() > new_size);
+    }
+
+    #[test]
+    #[traced_test]
+    fn test_decrement_block_size() {
+        let block_size_limits = BlockSizeLimits::new(
+            TEST_PROTOCOL_MAX_BLOCK_SIZE,
+            TEST_MAX_BLOCK_SIZE_INCREMENT_PERIOD,
+        );
+        block_size_limits.decrement_block_size();
+        assert!(block_size_limits.max_block_size() < TEST_PROTOCOL_MAX_BLOCK_SIZE);
+    }
+
+    #[test]
+    #[traced_test]
+    fn test_latency_model_controller() {
+        const INCREMENT_PERIOD: std::time::Duration = std::time::Duration::from_millis(25);
+
+        let block_size_limits = BlockSizeLimits::with_controller(
+            TEST_PROTOCOL_MAX_BLOCK_SIZE,
+            INCREMENT_PERIOD,
+            BlockSizeController::LatencyModel(LatencyModelConfig {
+                safety_margin: std::time::Duration::from_millis(200),
+                window: 16,
+            }),
+            std::time::Duration::from_millis(1000),
+        );
+
+        // Model can't be fitted yet, falls back to stepwise adjustments
+        block_size_limits.decrement_block_size();
+        let decremented = block_size_limits.max_block_size();
+        assert!(decremented < TEST_PROTOCOL_MAX_BLOCK_SIZE);
+        block_size_limits.try_increment_block_size(true);
+        assert!(block_size_limits.max_block_size() > decremented);
+
+        // 100ms + 10ms per kilobyte, so 70 kilobytes fit into 800ms budget
+        for (block_size, millis) in [(10_000, 200), (20_000, 300), (40_000, 500)] {
+            block_size_limits.record_vid_latency(
+                block_size,
+                10,
+                std::time::Duration::from_millis(millis),
+            );
+        }
+        // Prediction waits for the increment period
+        block_size_limits.try_increment_block_size(true);
+        assert!(block_size_limits.max_block_size() > 70_000);
+        std::thread::sleep(INCREMENT_PERIOD + std::time::Duration::from_millis(5));
+        block_size_limits.try_increment_block_size(false);
+        assert!(block_size_limits.max_block_size().abs_diff(70_000) <= 1);
+
+        // Timeouts still decrement the limit, and the prediction isn't restored right away
+        block_size_limits.decrement_block_size();
+        let decremented = block_size_limits.max_block_size();
+        assert!(decremented < 69_000);
+        block_size_limits.try_increment_block_size(true);
+        assert_eq!(block_size_limits.max_block_size(), decremented);
+
+        // Block size no longer seems to matter, prediction is capped by protocol maximum
+        block_size_limits.record_vid_latency(80_000, 10, std::time::Duration::from_millis(100));
+        block_size_limits.record_vid_latency(90_000, 10, std::time::Duration::from_millis(100));
+        std::thread::sleep(INCREMENT_PERIOD + std::time::Duration::from_millis(5));
+        block_size_limits.try_increment_block_size(false);
+        assert_eq!(
+            block_size_limits.max_block_size(),
+            TEST_PROTOCOL_MAX_BLOCK_SIZE
+        );
+    }
+
+    #[test]
+    #[traced_test]
+    fn test_max_block_size_floor() {
+        let block_size_limits = BlockSizeLimits::new(
+            BlockSizeLimits::MAX_BLOCK_SIZE_FLOOR + 1,
+            TEST_MAX_BLOCK_SIZE_INCREMENT_PERIOD,
+        );
+        block_size_limits.decrement_block_size();
+        assert_eq!(
+            block_size_limits.max_block_size(),
+            BlockSizeLimits::MAX_BLOCK_SIZE_FLOOR
+        );
+    }
+}
@@ -1,8 +1,11 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, HashMap, HashSet},
     ops::Bound,
-    sync::Arc,
-    time::Duration,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 use async_broadcast::Sender;
@@ -14,22 +17,35 @@ use hotshot_builder_api::v0_1::builder::TransactionStatus;
 use hotshot_types::{
     data::{DaProposal, QuorumProposal2},
     event::LeafInfo,
+    message::Proposal,
     traits::{
         block_contents::BlockHeader,
         node_implementation::{ConsensusTime, NodeType},
     },
+    utils::BuilderCommitment,
+};
+use offers::{OfferPolicy, OfferedTransactions};
+use proposal_validation::{
+    validate_da_proposal, validate_leader, validate_quorum_proposal, LeaderOracle,
+    ProposalRejection, RejectedProposals,
 };
 use quick_cache::sync::Cache;
+use tentative::TentativeInclusions;
 use tiered_view_map::TieredViewMap;
 use tracing::{error, info, warn};
 
 use crate::{
     block::{BuilderStateId, ParentBlockReferences, ReceivedTransaction},
     error::Error,
+    fee_ledger::FeeLedger,
+    inclusion_estimate::{InclusionLatencies, LatencyPercentiles},
     state::BuilderState,
-    utils::ProposalId,
+    utils::{EventGap, GapCallback, ProposalId},
 };
 
+pub mod offers;
+pub mod proposal_validation;
+pub mod tentative;
 pub mod tiered_view_map;
 
 type ProposalMap<Types> =
@@ -65,6 +81,10 @@ where
 /// - Spawning new builder states
 /// - Distributing transactions to builder states through a broadcast channel
 /// - Removing outdated builder states
+/// - Tracking transactions offered for pending views, see [`OfferPolicy`]
+/// - Tracking transactions of blocks claimed for pending views, see [`TentativeInclusions`]
+/// - Reconciling offered fees with decided leaves, see [`FeeLedger`]
+/// - Measuring time until transactions are decided, see [`InclusionLatencies`]
 ///
 /// <div class="warning">
 ///
@@ -76,17 +96,36 @@ where
 /// For the coordinator to function correctly, the following handler functions
 /// must be invoked when receiving corresponding HotShot events:
 /// - [`Self::handle_decide`]
-/// - [`Self::handle_quorum_proposal`]
-/// - [`Self::handle_da_proposal`]
+/// - [`Self::handle_signed_quorum_proposal`] or [`Self::handle_quorum_proposal`]
+/// - [`Self::handle_signed_da_proposal`] or [`Self::handle_da_proposal`]
 /// - [`Self::handle_transaction`]
+///
+/// If the event stream can lose events, e.g. when reconnecting, gaps it reports to
+/// [`Self::event_gap_handler`] should be handled by calling [`Self::handle_pending_event_gap`]
+/// before each event. Handlers for events before the gap may still be running at that point,
+/// which is fine: once the gap is handled, proposals for views before it are ignored.
+///
+/// Builders serving claimed blocks should also invoke [`Self::handle_view_timeout`].
 pub struct BuilderStateCoordinator<Types>
 where
     Types: NodeType,
 {
     builder_states: RwLock<BuilderStateMap<Types>>,
     tx_status: quick_cache::sync::Cache<Commitment<Types::Transaction>, TransactionStatus>,
+    received_at: quick_cache::sync::Cache<Commitment<Types::Transaction>, Instant>,
     transaction_sender: Sender<Arc<ReceivedTransaction<Types>>>,
     proposals: Mutex<ProposalMap<Types>>,
+    leader_oracle: Option<LeaderOracle<Types>>,
+    rejected_proposals: RejectedProposals,
+    offered: Mutex<OfferedTransactions<Types>>,
+    tentative: Mutex<TentativeInclusions<Types>>,
+    fee_ledger: FeeLedger<Types>,
+    inclusion_latencies: Mutex<InclusionLatencies>,
+    /// View events resumed at after the latest unhandled gap, see [`Self::event_gap_handler`]
+    pending_gap: std::sync::Mutex<Option<Types::View>>,
+    /// View events resumed at after the latest handled gap. Proposals for earlier views
+    /// are ignored, see [`Self::handle_event_gap`]. Only updated with [`Self::proposals`] locked.
+    resumed_view: AtomicU64,
 }
 
 impl<Types> BuilderStateCoordinator<Types>
@@ -119,9 +158,50 @@ where
             builder_states: RwLock::new(builder_states),
             proposals: Mutex::new(ProposalMap::new()),
             tx_status: Cache::new(tx_status_cache_capacity),
+            received_at: Cache::new(tx_status_cache_capacity),
+            leader_oracle: None,
+            rejected_proposals: RejectedProposals::default(),
+            offered: Mutex::new(OfferedTransactions::new(OfferPolicy::default())),
+            tentative: Mutex::new(TentativeInclusions::new()),
+            fee_ledger: FeeLedger::default(),
+            inclusion_latencies: Mutex::new(InclusionLatencies::default()),
+            pending_gap: std::sync::Mutex::new(None),
+            resumed_view: AtomicU64::new(0),
         }
     }
 
+    /// Check that senders of signed proposals are leaders for their views according to `oracle`.
+    /// See [`Self::handle_signed_da_proposal`] and [`Self::handle_signed_quorum_proposal`].
+    pub fn with_leader_oracle(mut self, oracle: LeaderOracle<Types>) -> Self {
+        self.leader_oracle = Some(oracle);
+        self
+    }
+
+    /// Exclude transactions already offered for other views from new offers according to `policy`.
+    /// See [`Self::record_offered`] and [`Self::excluded_offers`].
+    pub fn with_offer_policy(mut self, policy: OfferPolicy) -> Self {
+        self.offered = Mutex::new(OfferedTransactions::new(policy));
+        self
+    }
+
+    /// Ledger of fees offered by builders using this coordinator.
+    /// Fees are reconciled against decided leaves in [`Self::handle_decide`].
+    pub fn fee_ledger(&self) -> &FeeLedger<Types> {
+        &self.fee_ledger
+    }
+
+    /// Percentiles of time between receiving transactions and deciding their inclusion,
+    /// `None` if no transactions received by this coordinator were decided yet
+    pub async fn inclusion_latencies(&self) -> Option<LatencyPercentiles> {
+        self.inclusion_latencies.lock().await.percentiles()
+    }
+
+    /// Counters of proposals rejected by [`Self::handle_signed_da_proposal`]
+    /// and [`Self::handle_signed_quorum_proposal`]
+    pub fn rejected_proposals(&self) -> &RejectedProposals {
+        &self.rejected_proposals
+    }
+
     /// This function should be called whenever new decide events are received from HotShot.
     /// Its main responsibility is to perform garbage collection of [`BuilderState`]s for older views.
     /// The function returns the [`BuilderState`]s that have been garbage collected.
@@ -131,22 +211,45 @@ where
         leaf_chain: Arc<Vec<LeafInfo<Types>>>,
     ) -> BuilderStateMap<Types> {
         let latest_decide_view_num = leaf_chain[0].leaf.view_number();
+        let decided_at = Instant::now();
+        let mut latencies = Vec::new();
 
         for leaf_info in leaf_chain.iter() {
             if let Some(payload) = leaf_info.leaf.block_payload() {
-                for commitment in
-                    payload.transaction_commitments(leaf_info.leaf.block_header().metadata())
-                {
+                let commitments =
+                    payload.transaction_commitments(leaf_info.leaf.block_header().metadata());
+                for commitment in &commitments {
+                    if let Some((_, time_in)) = self.received_at.remove(commitment) {
+                        latencies.push(decided_at.saturating_duration_since(time_in));
+                    }
                     self.update_txn_status(
-                        &commitment,
+                        commitment,
                         TransactionStatus::Sequenced {
                             leaf: leaf_info.leaf.block_header().block_number(),
                         },
                     );
                 }
+                self.fee_ledger
+                    .reconcile(
+                        *leaf_info.leaf.view_number(),
+                        &leaf_info.leaf.block_header().builder_commitment(),
+                        &commitments.into_iter().collect(),
+                    )
+                    .await;
+            }
+        }
+
+        {
+            let mut inclusion_latencies = self.inclusion_latencies.lock().await;
+            for latency in latencies {
+                inclusion_latencies.record(latency);
             }
         }
 
+        self.offered.lock().await.prune(latest_decide_view_num);
+        // Claims for decided views are either sequenced now or lost to a different block
+        self.tentative.lock().await.prune(latest_decide_view_num);
+
         let pruned = {
             let mut builder_states_write_guard = self.builder_states.write().await;
             let highest_active_view_num = builder_states_write_guard
@@ -165,6 +268,67 @@ where
         pruned
     }
 
+    /// This function should be called whenever the HotShot event stream reports
+    /// that events were lost, e.g. while reconnecting to the events API.
+    ///
+    /// Proposals for views before `resumed_view` will never be matched, as their
+    /// counterparts were lost, so they are discarded, including ones handled after
+    /// this call by handlers racing with it. Proposals after the gap are
+    /// likely to extend blocks we haven't seen, so builder states are re-anchored
+    /// at the highest view we have: every lower view is pruned, making the
+    /// highest view states the fallback parents for new proposals.
+    /// The function returns the [`BuilderState`]s that have been pruned.
+    #[tracing::instrument(skip_all, fields(?resumed_view))]
+    pub async fn handle_event_gap(&self, resumed_view: Types::View) -> BuilderStateMap<Types> {
+        // Held until builder states are re-anchored, so that concurrent proposal handlers
+        // either spawn their builder state before that or see the updated `resumed_view`
+        let mut proposals = self.proposals.lock().await;
+        self.resumed_view.fetch_max(*resumed_view, Ordering::AcqRel);
+        proposals.retain(|proposal_id, _| proposal_id.view_number() >= resumed_view);
+
+        let pruned = {
+            let mut builder_states_write_guard = self.builder_states.write().await;
+            let Some(anchor) = builder_states_write_guard.highest_view() else {
+                return BuilderStateMap::new();
+            };
+            tracing::info!(?anchor, "Re-anchoring builder states after event gap");
+            builder_states_write_guard.prune(anchor)
+        };
+        tracing::info!(num_states_pruned = pruned.len(), "Pruned builder state map");
+        pruned
+    }
+
+    /// Returns a callback recording views lost while reconnecting to the events API.
+    /// Should be passed as `EventServiceStreamConfig::on_gap` when connecting to it.
+    ///
+    /// The stream reports a gap right before yielding the first event after it,
+    /// so the gap is handled by [`Self::handle_pending_event_gap`] before that event is,
+    /// rather than concurrently with events after it. Handlers spawned for events before
+    /// the gap can still race with it, see [`Self::handle_event_gap`].
+    pub fn event_gap_handler(self: &Arc<Self>) -> GapCallback {
+        let coordinator = Arc::clone(self);
+        Arc::new(move |gap: EventGap| {
+            let resumed_view = Types::View::new(gap.resumed_view);
+            let mut pending_gap = coordinator
+                .pending_gap
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            *pending_gap = Some(pending_gap.map_or(resumed_view, |view| view.max(resumed_view)));
+        })
+    }
+
+    /// Handle the latest gap recorded by [`Self::event_gap_handler`], if there is one.
+    /// Should be called before handling each event from the stream.
+    /// See [`Self::handle_event_gap`] for details.
+    pub async fn handle_pending_event_gap(&self) -> Option<BuilderStateMap<Types>> {
+        let resumed_view = self
+            .pending_gap
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()?;
+        Some(self.handle_event_gap(resumed_view).await)
+    }
+
     /// Enqueue new transaction in all builder states managed by this coordinator.
     ///
     /// Builder states will automatically filter transactions already included from
@@ -183,6 +347,7 @@ where
         transaction: ReceivedTransaction<Types>,
     ) -> Result<(), Error<Types>> {
         let commit = transaction.commit;
+        let time_in = transaction.time_in;
 
         let maybe_evicted = match self.transaction_sender.try_broadcast(Arc::new(transaction)) {
             Ok(maybe_evicted) => maybe_evicted,
@@ -199,6 +364,7 @@ where
         };
 
         self.update_txn_status(&commit, TransactionStatus::Pending);
+        self.received_at.insert(commit, time_in);
 
         if let Some(evicted) = maybe_evicted {
             warn!(
@@ -210,6 +376,58 @@ where
         Ok(())
     }
 
+    /// This function should be called whenever transactions are offered to a proposer
+    /// for `view`, e.g. in a bundle or a block.
+    pub async fn record_offered(
+        &self,
+        view: Types::View,
+        commitments: impl IntoIterator<Item = Commitment<Types::Transaction>>,
+    ) {
+        self.offered.lock().await.record(view, commitments);
+    }
+
+    /// Transactions that shouldn't be offered for `view`, because they were already offered
+    /// for another view. Depends on the [`OfferPolicy`] set with [`Self::with_offer_policy`],
+    /// by default nothing is excluded.
+    pub async fn excluded_offers(
+        &self,
+        view: Types::View,
+    ) -> HashSet<Commitment<Types::Transaction>> {
+        self.offered.lock().await.excluded(view)
+    }
+
+    /// This function should be called whenever a block with transactions `commitments`
+    /// is claimed in a request for `view`, the view of the block's parent. Until the block's
+    /// proposal is decided or times out, these transactions are excluded from blocks built
+    /// for other views, see [`Self::tentatively_included`] and [`TentativeInclusions`].
+    pub async fn mark_tentatively_included(
+        &self,
+        view: Types::View,
+        builder_commitment: BuilderCommitment,
+        commitments: impl IntoIterator<Item = Commitment<Types::Transaction>>,
+    ) {
+        self.tentative
+            .lock()
+            .await
+            .mark(view, builder_commitment, commitments);
+    }
+
+    /// Transactions of blocks claimed for views other than `view`, which
+    /// shouldn't be packed into blocks for `view`
+    pub async fn tentatively_included(
+        &self,
+        view: Types::View,
+    ) -> HashSet<Commitment<Types::Transaction>> {
+        self.tentative.lock().await.excluded(view)
+    }
+
+    /// This function should be called whenever HotShot reports that `view` timed out.
+    /// Transactions of blocks claimed on parents before it are no longer considered
+    /// tentatively included, see [`TentativeInclusions::discard`].
+    pub async fn handle_view_timeout(&self, view: Types::View) {
+        self.tentative.lock().await.discard(view);
+    }
+
     /// This function should be called whenever new DA Proposal is recieved from HotShot.
     /// Coordinator uses matching Quorum and DA proposals to track creation of new blocks
     /// and spawning corresponding builder states for those.
@@ -228,6 +446,69 @@ where
             .await;
     }
 
+    /// Same as [`Self::handle_da_proposal`], but checks that the proposal is signed by
+    /// `sender` and, if the coordinator has a [`LeaderOracle`], that `sender` is the leader
+    /// for the proposal's view. Rejected proposals are logged, counted and otherwise ignored.
+    pub async fn handle_signed_da_proposal(
+        &self,
+        da_proposal: Proposal<Types, DaProposal<Types>>,
+        sender: Types::SignatureKey,
+    ) -> Result<(), ProposalRejection> {
+        let view_number = da_proposal.data.view_number;
+        self.check_proposal(
+            validate_da_proposal(&da_proposal, &sender),
+            view_number,
+            &sender,
+        )?;
+        self.handle_da_proposal(da_proposal.data).await;
+        Ok(())
+    }
+
+    /// Same as [`Self::handle_quorum_proposal`], but checks that the proposal is signed by
+    /// `sender` and, if the coordinator has a [`LeaderOracle`], that `sender` is the leader
+    /// for the proposal's view. Rejected proposals are logged, counted and otherwise ignored.
+    pub async fn handle_signed_quorum_proposal(
+        &self,
+        quorum_proposal: Proposal<Types, QuorumProposal2<Types>>,
+        sender: Types::SignatureKey,
+    ) -> Result<(), ProposalRejection> {
+        let view_number = quorum_proposal.data.view_number;
+        self.check_proposal(
+            validate_quorum_proposal(&quorum_proposal, &sender),
+            view_number,
+            &sender,
+        )?;
+        self.handle_quorum_proposal(quorum_proposal.data).await;
+        Ok(())
+    }
+
+    /// Combine result of signature validation with leader check,
+    /// recording and logging the rejection if either fails
+    fn check_proposal(
+        &self,
+        signature_check: Result<(), ProposalRejection>,
+        view_number: Types::View,
+        sender: &Types::SignatureKey,
+    ) -> Result<(), ProposalRejection> {
+        let result = signature_check.and_then(|_| match &self.leader_oracle {
+            Some(oracle) => validate_leader(oracle, view_number, sender),
+            None => Ok(()),
+        });
+
+        if let Err(rejection) = result {
+            self.rejected_proposals.record(rejection);
+            warn!(
+                ?view_number,
+                ?sender,
+                %rejection,
+                total_rejected = self.rejected_proposals.total(),
+                "Rejected proposal"
+            );
+        }
+
+        result
+    }
+
     /// Generalized function to handle Quorum and DA proposals. The behavior is as follows:
     ///
     /// - If a matching proposal of the other kind exists for this [`ProposalId`], remove it
@@ -240,7 +521,14 @@ where
         proposal_id: ProposalId<Types>,
         proposal: Either<QuorumProposal2<Types>, DaProposal<Types>>,
     ) {
-        match self.proposals.lock().await.entry(proposal_id) {
+        let mut proposals = self.proposals.lock().await;
+        if *proposal_id.view_number() < self.resumed_view.load(Ordering::Acquire) {
+            // Counterpart was lost in an event gap, see `handle_event_gap`
+            tracing::debug!(?proposal_id, "Ignoring proposal from before event gap");
+            return;
+        }
+
+        match proposals.entry(proposal_id) {
             Entry::Occupied(entry) => {
                 if entry.get().is_left() == proposal.is_left() {
                     // Duplicate proposal, ignore.
@@ -304,6 +592,17 @@ where
             .cloned()
     }
 
+    /// Transactions not yet included from the point of view of the highest view builder state,
+    /// in the order they were received, including ones still in the channel.
+    /// Doesn't modify the builder state, see [`BuilderState::pending_txns`].
+    /// Used to export the pending queue on shutdown.
+    pub async fn pending_transactions(&self) -> Vec<Arc<ReceivedTransaction<Types>>> {
+        let Some(builder_state) = self.highest_view_builder().await else {
+            return Vec::new();
+        };
+        builder_state.pending_txns().await
+    }
+
     /// Spawn a new builder state off of matching pair of Quorum and DA proposals, store it in [`Self::builder_states`]
     async fn spawn_builder_state(
         &self,
@@ -519,11 +818,13 @@ where
 #[cfg_attr(coverage_nightly, coverage(off))]
 #[cfg(test)]
 mod tests {
-    use std::time::Instant;
+    use std::{marker::PhantomData, time::Instant};
 
     use committable::Committable;
+    use hotshot::types::{BLSPubKey, SignatureKey};
     use hotshot_example_types::node_types::TestTypes;
-    use hotshot_types::data::ViewNumber;
+    use hotshot_types::data::{Leaf2, ViewNumber};
+    use sha2::{Digest, Sha256};
     use tracing_test::traced_test;
 
     use crate::{
@@ -753,6 +1054,205 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_event_gap_reanchors_builder_states() {
+        let coordinator = BuilderStateCoordinator::new(
+            TEST_CHANNEL_BUFFER_SIZE,
+            TEST_INCLUDED_TX_GC_PERIOD,
+            TEST_TX_STATUS_CACHE_CAPACITY,
+        );
+
+        for view in 0..10 {
+            let (da_proposal, quorum_proposal) = mock::proposals(view).await;
+            coordinator.handle_quorum_proposal(quorum_proposal).await;
+            coordinator.handle_da_proposal(da_proposal).await;
+        }
+
+        // Proposal that lost its counterpart during the gap
+        let (orphaned_proposal, _) = mock::proposals(12).await;
+        coordinator.handle_da_proposal(orphaned_proposal).await;
+
+        let pruned = coordinator.handle_event_gap(ViewNumber::new(15)).await;
+
+        assert_eq!(
+            pruned.len(),
+            10,
+            "Bootstrap state and states for views 0 to 8 should be pruned"
+        );
+        assert_eq!(coordinator.builder_states.read().await.len(), 1);
+        assert_eq!(*coordinator.lowest_view().await, 9);
+        assert!(
+            coordinator.proposals.lock().await.is_empty(),
+            "Proposals from before the gap should be discarded"
+        );
+
+        // Proposals from before the gap handled late, e.g. by handlers spawned
+        // before it, are ignored rather than spawning states off pruned ones
+        let (da_proposal, quorum_proposal) = mock::proposals(12).await;
+        coordinator.handle_quorum_proposal(quorum_proposal).await;
+        coordinator.handle_da_proposal(da_proposal).await;
+        assert_eq!(coordinator.builder_states.read().await.len(), 1);
+        assert!(coordinator.proposals.lock().await.is_empty());
+
+        // New proposal after the gap extends the anchor
+        let (da_proposal, quorum_proposal) = mock::proposals(15).await;
+        coordinator.handle_quorum_proposal(quorum_proposal).await;
+        coordinator.handle_da_proposal(da_proposal).await;
+
+        assert_eq!(coordinator.builder_states.read().await.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[traced_test]
+    async fn test_event_gap_races_with_proposal_handlers() {
+        let coordinator = Arc::new(BuilderStateCoordinator::new(
+            TEST_CHANNEL_BUFFER_SIZE,
+            TEST_INCLUDED_TX_GC_PERIOD,
+            TEST_TX_STATUS_CACHE_CAPACITY,
+        ));
+
+        for view in 0..10 {
+            let (da_proposal, quorum_proposal) = mock::proposals(view).await;
+            coordinator.handle_quorum_proposal(quorum_proposal).await;
+            coordinator.handle_da_proposal(da_proposal).await;
+        }
+
+        // Handlers spawned by the event loop for proposals received right before the gap
+        let (da_proposal, quorum_proposal) = mock::proposals(12).await;
+        let handlers = [
+            tokio::spawn({
+                let coordinator = Arc::clone(&coordinator);
+                async move { coordinator.handle_quorum_proposal(quorum_proposal).await }
+            }),
+            tokio::spawn({
+                let coordinator = Arc::clone(&coordinator);
+                async move { coordinator.handle_da_proposal(da_proposal).await }
+            }),
+        ];
+        coordinator.handle_event_gap(ViewNumber::new(15)).await;
+        for handler in handlers {
+            handler.await.unwrap();
+        }
+
+        // Whichever order they ran in, builder states are re-anchored at a single view
+        // and nothing from before the gap is left waiting for a counterpart
+        assert_eq!(coordinator.builder_states.read().await.len(), 1);
+        assert!(coordinator.proposals.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_pending_event_gap() {
+        let coordinator = Arc::new(BuilderStateCoordinator::new(
+            TEST_CHANNEL_BUFFER_SIZE,
+            TEST_INCLUDED_TX_GC_PERIOD,
+            TEST_TX_STATUS_CACHE_CAPACITY,
+        ));
+
+        for view in 0..5 {
+            let (da_proposal, quorum_proposal) = mock::proposals(view).await;
+            coordinator.handle_quorum_proposal(quorum_proposal).await;
+            coordinator.handle_da_proposal(da_proposal).await;
+        }
+
+        assert!(coordinator.handle_pending_event_gap().await.is_none());
+
+        let on_gap = coordinator.event_gap_handler();
+        on_gap(EventGap {
+            last_seen_view: 4,
+            resumed_view: 8,
+        });
+        // Nothing changes until the gap is handled in-band
+        assert_eq!(coordinator.builder_states.read().await.len(), 6);
+
+        let pruned = coordinator.handle_pending_event_gap().await.unwrap();
+        assert_eq!(pruned.len(), 5);
+        assert_eq!(*coordinator.lowest_view().await, 4);
+
+        // Gap is handled once
+        assert!(coordinator.handle_pending_event_gap().await.is_none());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_signed_proposal_validation() {
+        let (leader_key, leader_private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+        let (impostor_key, impostor_private_key) =
+            BLSPubKey::generated_from_seed_indexed([0u8; 32], 1);
+        type PrivateKey = <BLSPubKey as SignatureKey>::PrivateKey;
+
+        let coordinator = BuilderStateCoordinator::new(
+            TEST_CHANNEL_BUFFER_SIZE,
+            TEST_INCLUDED_TX_GC_PERIOD,
+            TEST_TX_STATUS_CACHE_CAPACITY,
+        )
+        .with_leader_oracle(LeaderOracle::new(move |_| Some(leader_key)));
+
+        let sign_da = |da_proposal: &DaProposal<TestTypes>, private_key: &PrivateKey| Proposal {
+            data: da_proposal.clone(),
+            signature: BLSPubKey::sign(
+                private_key,
+                Sha256::digest(&da_proposal.encoded_transactions).as_ref(),
+            )
+            .unwrap(),
+            _pd: PhantomData,
+        };
+        let sign_quorum = |quorum_proposal: &QuorumProposal2<TestTypes>,
+                           private_key: &PrivateKey| Proposal {
+            data: quorum_proposal.clone(),
+            signature: BLSPubKey::sign(
+                private_key,
+                Leaf2::from_quorum_proposal(quorum_proposal)
+                    .commit()
+                    .as_ref(),
+            )
+            .unwrap(),
+            _pd: PhantomData,
+        };
+
+        let (da_proposal, quorum_proposal) = mock::proposals(7).await;
+
+        // Signature doesn't match the sender
+        assert_eq!(
+            coordinator
+                .handle_signed_da_proposal(sign_da(&da_proposal, &impostor_private_key), leader_key)
+                .await,
+            Err(ProposalRejection::InvalidSignature)
+        );
+
+        // Signature is valid, but the sender isn't the leader
+        assert_eq!(
+            coordinator
+                .handle_signed_quorum_proposal(
+                    sign_quorum(&quorum_proposal, &impostor_private_key),
+                    impostor_key
+                )
+                .await,
+            Err(ProposalRejection::UnexpectedLeader)
+        );
+
+        assert!(coordinator.proposals.lock().await.is_empty());
+        assert_eq!(coordinator.rejected_proposals().invalid_signature(), 1);
+        assert_eq!(coordinator.rejected_proposals().unexpected_leader(), 1);
+
+        // Valid proposals from the leader spawn a new builder state
+        coordinator
+            .handle_signed_da_proposal(sign_da(&da_proposal, &leader_private_key), leader_key)
+            .await
+            .unwrap();
+        coordinator
+            .handle_signed_quorum_proposal(
+                sign_quorum(&quorum_proposal, &leader_private_key),
+                leader_key,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(coordinator.builder_states.read().await.len(), 2);
+        assert_eq!(coordinator.rejected_proposals().total(), 2);
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_transaction_status() {
@@ -829,6 +1329,51 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_inclusion_latencies() {
+        const NUM_TRANSACTIONS: usize = 8;
+
+        let coordinator = BuilderStateCoordinator::new(
+            TEST_CHANNEL_BUFFER_SIZE,
+            TEST_INCLUDED_TX_GC_PERIOD,
+            TEST_TX_STATUS_CACHE_CAPACITY,
+        );
+
+        let received_transactions = (0..NUM_TRANSACTIONS)
+            .map(|_| mock::transaction())
+            .collect::<Vec<_>>();
+        for tx in received_transactions.iter() {
+            coordinator
+                .handle_transaction(ReceivedTransaction::new(
+                    tx.clone(),
+                    TransactionSource::Public,
+                ))
+                .await
+                .unwrap();
+        }
+
+        // Transaction included by a different builder, never received by us
+        let external_transaction = mock::transaction();
+
+        let leaf_chain = mock::decide_leaf_chain_with_transactions(
+            *ViewNumber::genesis(),
+            received_transactions
+                .into_iter()
+                .chain(std::iter::once(external_transaction))
+                .collect(),
+        )
+        .await;
+        assert_eq!(coordinator.inclusion_latencies().await, None);
+        coordinator.handle_decide(leaf_chain).await;
+
+        // Inclusion latency is only measured for transactions we've received
+        assert_eq!(
+            coordinator.inclusion_latencies().await.unwrap().samples,
+            NUM_TRANSACTIONS
+        );
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_transaction_overflow() {
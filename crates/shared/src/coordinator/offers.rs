@@ -0,0 +1,191 @@
+//! Tracking of transactions offered in bundles and blocks, used to avoid
+//! offering the same transaction for competing proposals
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Bound,
+    time::{Duration, Instant},
+};
+
+use committable::Commitment;
+use hotshot_types::traits::node_implementation::{ConsensusTime, NodeType};
+
+/// Policy on offering transactions that were already offered for a different view.
+///
+/// Offers for the view being built are never excluded, so repeated
+/// requests for the same view see the same transactions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OfferPolicy {
+    /// Offer transactions regardless of previous offers
+    #[default]
+    AllowDuplicates,
+    /// Don't offer transactions already offered for a higher view
+    ExcludeOfferedForHigherView,
+    /// Don't offer transactions already offered for another view until
+    /// that view is decided or `ttl` elapses since the offer
+    ExcludeUntilDecidedOrExpired { ttl: Duration },
+}
+
+/// Transactions offered for pending views, see [`OfferPolicy`]
+#[derive(Debug)]
+pub struct OfferedTransactions<Types: NodeType> {
+    policy: OfferPolicy,
+    offers: BTreeMap<Types::View, HashMap<Commitment<Types::Transaction>, Instant>>,
+}
+
+impl<Types: NodeType> OfferedTransactions<Types> {
+    /// Create an empty tracker enforcing `policy`
+    pub fn new(policy: OfferPolicy) -> Self {
+        Self {
+            policy,
+            offers: BTreeMap::new(),
+        }
+    }
+
+    /// Policy enforced by this tracker
+    pub fn policy(&self) -> OfferPolicy {
+        self.policy
+    }
+
+    /// Record transactions offered for `view`
+    pub fn record(
+        &mut self,
+        view: Types::View,
+        commitments: impl IntoIterator<Item = Commitment<Types::Transaction>>,
+    ) {
+        match self.policy {
+            // Nothing will ever be excluded, no need to keep track
+            OfferPolicy::AllowDuplicates => return,
+            OfferPolicy::ExcludeOfferedForHigherView => {}
+            OfferPolicy::ExcludeUntilDecidedOrExpired { ttl } => self.prune_expired(ttl),
+        }
+        let now = Instant::now();
+        self.offers
+            .entry(view)
+            .or_default()
+            .extend(commitments.into_iter().map(|commitment| (commitment, now)));
+    }
+
+    /// Transactions that shouldn't be offered for `view` under the policy
+    pub fn excluded(&self, view: Types::View) -> HashSet<Commitment<Types::Transaction>> {
+        match self.policy {
+            OfferPolicy::AllowDuplicates => HashSet::new(),
+            OfferPolicy::ExcludeOfferedForHigherView => self
+                .offers
+                .range((Bound::Excluded(view), Bound::Unbounded))
+                .flat_map(|(_, offers)| offers.keys().copied())
+                .collect(),
+            OfferPolicy::ExcludeUntilDecidedOrExpired { ttl } => self
+                .offers
+                .iter()
+                .filter(|(offer_view, _)| **offer_view != view)
+                .flat_map(|(_, offers)| offers.iter())
+                .filter(|(_, offered_at)| offered_at.elapsed() < ttl)
+                .map(|(commitment, _)| *commitment)
+                .collect(),
+        }
+    }
+
+    /// Forget offers for views up to and including `decided_view`
+    pub fn prune(&mut self, decided_view: Types::View) {
+        self.offers = self.offers.split_off(&Types::View::new(*decided_view + 1));
+    }
+
+    /// Number of views we have offers recorded for
+    pub fn len(&self) -> usize {
+        self.offers.len()
+    }
+
+    /// Whether there are no offers recorded
+    pub fn is_empty(&self) -> bool {
+        self.offers.is_empty()
+    }
+
+    fn prune_expired(&mut self, ttl: Duration) {
+        self.offers.retain(|_, offers| {
+            offers.retain(|_, offered_at| offered_at.elapsed() < ttl);
+            !offers.is_empty()
+        });
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use committable::Committable;
+    use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+    use hotshot_types::data::ViewNumber;
+
+    use super::*;
+
+    type OfferedTransactions = super::OfferedTransactions<TestTypes>;
+
+    fn commitment(byte: u8) -> Commitment<TestTransaction> {
+        TestTransaction::new(vec![byte]).commit()
+    }
+
+    #[test]
+    fn test_allow_duplicates() {
+        let mut offered = OfferedTransactions::new(OfferPolicy::AllowDuplicates);
+        offered.record(ViewNumber::new(2), [commitment(1)]);
+        assert!(offered.is_empty());
+        assert!(offered.excluded(ViewNumber::new(1)).is_empty());
+    }
+
+    #[test]
+    fn test_exclude_offered_for_higher_view() {
+        let mut offered = OfferedTransactions::new(OfferPolicy::ExcludeOfferedForHigherView);
+        offered.record(ViewNumber::new(2), [commitment(1)]);
+        offered.record(ViewNumber::new(3), [commitment(2)]);
+
+        assert_eq!(
+            offered.excluded(ViewNumber::new(1)),
+            HashSet::from([commitment(1), commitment(2)])
+        );
+        assert_eq!(
+            offered.excluded(ViewNumber::new(2)),
+            HashSet::from([commitment(2)])
+        );
+        assert!(offered.excluded(ViewNumber::new(3)).is_empty());
+
+        offered.prune(ViewNumber::new(2));
+        assert_eq!(offered.len(), 1);
+        assert_eq!(
+            offered.excluded(ViewNumber::new(1)),
+            HashSet::from([commitment(2)])
+        );
+    }
+
+    #[test]
+    fn test_exclude_until_decided_or_expired() {
+        const TTL: Duration = Duration::from_millis(100);
+
+        let mut offered =
+            OfferedTransactions::new(OfferPolicy::ExcludeUntilDecidedOrExpired { ttl: TTL });
+        offered.record(ViewNumber::new(2), [commitment(1)]);
+        offered.record(ViewNumber::new(3), [commitment(2)]);
+
+        // Offers for other views are excluded regardless of whether they're higher or lower
+        assert_eq!(
+            offered.excluded(ViewNumber::new(2)),
+            HashSet::from([commitment(2)])
+        );
+        assert_eq!(
+            offered.excluded(ViewNumber::new(4)),
+            HashSet::from([commitment(1), commitment(2)])
+        );
+
+        // Decided offers are forgotten
+        offered.prune(ViewNumber::new(2));
+        assert_eq!(
+            offered.excluded(ViewNumber::new(4)),
+            HashSet::from([commitment(2)])
+        );
+
+        // Expired offers are no longer excluded and pruned on next record
+        std::thread::sleep(TTL);
+        assert!(offered.excluded(ViewNumber::new(4)).is_empty());
+        offered.record(ViewNumber::new(4), [commitment(3)]);
+        assert_eq!(offered.len(), 1);
+    }
+}
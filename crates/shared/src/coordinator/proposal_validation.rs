@@ -0,0 +1,137 @@
+//! Validation of proposals received from HotShot before they're used
+//! to spawn new builder states
+
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use committable::Committable;
+use hotshot_types::{
+    data::{DaProposal, Leaf2, QuorumProposal2},
+    message::Proposal,
+    traits::{node_implementation::NodeType, signature_key::SignatureKey},
+};
+use sha2::{Digest, Sha256};
+
+/// Source of information about the expected leader for a view.
+///
+/// Returning `None` means the leader is unknown, in which case
+/// the membership check is skipped.
+pub struct LeaderOracle<Types: NodeType>(
+    Arc<dyn Fn(Types::View) -> Option<Types::SignatureKey> + Send + Sync>,
+);
+
+impl<Types: NodeType> LeaderOracle<Types> {
+    /// Create a new oracle from a function returning the leader for a view
+    pub fn new(
+        leader: impl Fn(Types::View) -> Option<Types::SignatureKey> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(leader))
+    }
+
+    /// Returns the expected leader for `view`, if known
+    pub fn leader(&self, view: Types::View) -> Option<Types::SignatureKey> {
+        (self.0)(view)
+    }
+}
+
+impl<Types: NodeType> Clone for LeaderOracle<Types> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<Types: NodeType> Debug for LeaderOracle<Types> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LeaderOracle").finish_non_exhaustive()
+    }
+}
+
+/// Reason for a proposal being rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ProposalRejection {
+    /// Proposal signature doesn't match the sender
+    #[error("Invalid proposal signature")]
+    InvalidSignature,
+    /// Sender isn't the leader for the proposal's view
+    #[error("Proposal sender isn't the leader for the view")]
+    UnexpectedLeader,
+}
+
+/// Counters of proposals rejected by the coordinator
+#[derive(Debug, Default)]
+pub struct RejectedProposals {
+    invalid_signature: AtomicU64,
+    unexpected_leader: AtomicU64,
+}
+
+impl RejectedProposals {
+    /// Record a rejected proposal
+    pub fn record(&self, rejection: ProposalRejection) {
+        let counter = match rejection {
+            ProposalRejection::InvalidSignature => &self.invalid_signature,
+            ProposalRejection::UnexpectedLeader => &self.unexpected_leader,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of proposals rejected due to invalid signature
+    pub fn invalid_signature(&self) -> u64 {
+        self.invalid_signature.load(Ordering::Relaxed)
+    }
+
+    /// Number of proposals rejected due to the sender not being the expected leader
+    pub fn unexpected_leader(&self) -> u64 {
+        self.unexpected_leader.load(Ordering::Relaxed)
+    }
+
+    /// Total number of rejected proposals
+    pub fn total(&self) -> u64 {
+        self.invalid_signature() + self.unexpected_leader()
+    }
+}
+
+/// Check that DA proposal is signed by `sender`.
+/// DA proposals are signed over the hash of encoded transactions.
+pub fn validate_da_proposal<Types: NodeType>(
+    proposal: &Proposal<Types, DaProposal<Types>>,
+    sender: &Types::SignatureKey,
+) -> Result<(), ProposalRejection> {
+    let encoded_txns_hash = Sha256::digest(&proposal.data.encoded_transactions);
+    if sender.validate(&proposal.signature, &encoded_txns_hash) {
+        Ok(())
+    } else {
+        Err(ProposalRejection::InvalidSignature)
+    }
+}
+
+/// Check that quorum proposal is signed by `sender`.
+/// Quorum proposals are signed over the commitment of the leaf they propose.
+pub fn validate_quorum_proposal<Types: NodeType>(
+    proposal: &Proposal<Types, QuorumProposal2<Types>>,
+    sender: &Types::SignatureKey,
+) -> Result<(), ProposalRejection> {
+    let leaf = Leaf2::from_quorum_proposal(&proposal.data);
+    if sender.validate(&proposal.signature, leaf.commit().as_ref()) {
+        Ok(())
+    } else {
+        Err(ProposalRejection::InvalidSignature)
+    }
+}
+
+/// Check that `sender` is the leader for `view` according to `oracle`.
+/// Passes if the oracle doesn't know the leader.
+pub fn validate_leader<Types: NodeType>(
+    oracle: &LeaderOracle<Types>,
+    view: Types::View,
+    sender: &Types::SignatureKey,
+) -> Result<(), ProposalRejection> {
+    match oracle.leader(view) {
+        Some(leader) if &leader != sender => Err(ProposalRejection::UnexpectedLeader),
+        _ => Ok(()),
+    }
+}
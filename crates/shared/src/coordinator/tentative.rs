@@ -0,0 +1,172 @@
+//! Tracking of transactions in claimed blocks, used to avoid offering them again
+//! from builder states that don't know about the claimed block yet
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use committable::Commitment;
+use hotshot_types::{
+    traits::node_implementation::{ConsensusTime, NodeType},
+    utils::BuilderCommitment,
+};
+
+/// Transactions of blocks claimed for pending views.
+///
+/// Once a leader claims a block, its transactions are likely to be proposed,
+/// but builder states other than the one that built it won't learn of that
+/// until matching proposals arrive. Until the block's proposal is decided or
+/// times out, such transactions are considered tentatively included.
+///
+/// Claims are keyed by the view number the leader sends in its requests,
+/// which is the view of the block's parent. After timeouts that may be any view
+/// before the one the block is proposed in.
+#[derive(Debug)]
+pub struct TentativeInclusions<Types: NodeType> {
+    claims:
+        BTreeMap<Types::View, HashMap<BuilderCommitment, HashSet<Commitment<Types::Transaction>>>>,
+}
+
+impl<Types: NodeType> Default for TentativeInclusions<Types> {
+    fn default() -> Self {
+        Self {
+            claims: BTreeMap::new(),
+        }
+    }
+}
+
+impl<Types: NodeType> TentativeInclusions<Types> {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record transactions of block `builder_commitment` claimed in a request for `view`
+    pub fn mark(
+        &mut self,
+        view: Types::View,
+        builder_commitment: BuilderCommitment,
+        commitments: impl IntoIterator<Item = Commitment<Types::Transaction>>,
+    ) {
+        self.claims
+            .entry(view)
+            .or_default()
+            .entry(builder_commitment)
+            .or_default()
+            .extend(commitments);
+    }
+
+    /// Transactions that shouldn't be packed into blocks for `view`.
+    ///
+    /// Blocks claimed for `view` itself are not taken into account, so repeated
+    /// requests for the same view see the same transactions.
+    pub fn excluded(&self, view: Types::View) -> HashSet<Commitment<Types::Transaction>> {
+        self.claims
+            .iter()
+            .filter(|(claim_view, _)| **claim_view != view)
+            .flat_map(|(_, blocks)| blocks.values().flatten().copied())
+            .collect()
+    }
+
+    /// Forget blocks claimed on parents before `timed_out_view`. Such blocks could
+    /// only have been proposed in views up to and including `timed_out_view`, and
+    /// the next leader builds on a parent before it, claiming its block anew.
+    ///
+    /// If the timeout is handled after the next leader's claim on a parent before
+    /// `timed_out_view`, that claim is forgotten as well, so its transactions may be
+    /// offered again before its proposal arrives. This is benign, as they can't be
+    /// included twice.
+    pub fn discard(&mut self, timed_out_view: Types::View) {
+        self.claims = self.claims.split_off(&timed_out_view);
+    }
+
+    /// Forget claims on parents before `decided_view`, as their blocks were
+    /// proposed in views up to and including `decided_view`
+    pub fn prune(&mut self, decided_view: Types::View) {
+        self.claims = self.claims.split_off(&decided_view);
+    }
+
+    /// Number of views we have claims recorded for
+    pub fn len(&self) -> usize {
+        self.claims.len()
+    }
+
+    /// Whether there are no claims recorded
+    pub fn is_empty(&self) -> bool {
+        self.claims.is_empty()
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use committable::Committable;
+    use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+    use hotshot_types::data::ViewNumber;
+
+    use super::*;
+
+    type TentativeInclusions = super::TentativeInclusions<TestTypes>;
+
+    fn commitment(byte: u8) -> Commitment<TestTransaction> {
+        TestTransaction::new(vec![byte]).commit()
+    }
+
+    fn block(byte: u8) -> BuilderCommitment {
+        BuilderCommitment::from_bytes([byte])
+    }
+
+    #[test]
+    fn test_excluded() {
+        let mut tentative = TentativeInclusions::new();
+        tentative.mark(ViewNumber::new(2), block(1), [commitment(1)]);
+        tentative.mark(ViewNumber::new(2), block(2), [commitment(2)]);
+        tentative.mark(ViewNumber::new(3), block(3), [commitment(3)]);
+
+        // Claims for the view being built aren't excluded
+        assert_eq!(
+            tentative.excluded(ViewNumber::new(2)),
+            HashSet::from([commitment(3)])
+        );
+        assert_eq!(
+            tentative.excluded(ViewNumber::new(4)),
+            HashSet::from([commitment(1), commitment(2), commitment(3)])
+        );
+    }
+
+    #[test]
+    fn test_discard_and_prune() {
+        let mut tentative = TentativeInclusions::new();
+        tentative.mark(ViewNumber::new(2), block(1), [commitment(1)]);
+        tentative.mark(ViewNumber::new(3), block(2), [commitment(2)]);
+        tentative.mark(ViewNumber::new(4), block(3), [commitment(3)]);
+
+        // View 3 timed out, so block built on 2 won't be proposed
+        tentative.discard(ViewNumber::new(3));
+        assert_eq!(
+            tentative.excluded(ViewNumber::new(5)),
+            HashSet::from([commitment(2), commitment(3)])
+        );
+
+        // View 4 decided, including block built on 3
+        tentative.prune(ViewNumber::new(4));
+        assert_eq!(tentative.len(), 1);
+        assert_eq!(
+            tentative.excluded(ViewNumber::new(5)),
+            HashSet::from([commitment(3)])
+        );
+    }
+
+    #[test]
+    fn test_consecutive_timeouts() {
+        let mut tentative = TentativeInclusions::new();
+
+        // Leader of view 5 builds on 4 and times out
+        tentative.mark(ViewNumber::new(4), block(1), [commitment(1)]);
+        tentative.discard(ViewNumber::new(5));
+        assert!(tentative.is_empty());
+
+        // Leader of view 6 builds on 4 as well and times out too
+        tentative.mark(ViewNumber::new(4), block(1), [commitment(1)]);
+        tentative.discard(ViewNumber::new(6));
+        assert!(tentative.is_empty());
+    }
+}
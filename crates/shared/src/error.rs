@@ -1,12 +1,13 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_broadcast::TrySendError;
 use hotshot::traits::BlockPayload;
 use hotshot_builder_api::v0_99::builder::BuildError;
 use hotshot_types::traits::{node_implementation::NodeType, signature_key::BuilderSignatureKey};
 use thiserror::Error;
+use tide_disco::StatusCode;
 
-use crate::block::ReceivedTransaction;
+use crate::{block::ReceivedTransaction, utils::retry_after::report_retry_after};
 
 #[derive(Error, Debug)]
 pub enum Error<Types: NodeType> {
@@ -26,6 +27,49 @@ pub enum Error<Types: NodeType> {
     TxnSender(TrySendError<Arc<ReceivedTransaction<Types>>>),
     #[error("Transaction too big ({len}/{max_tx_len})")]
     TxTooBig { len: u64, max_tx_len: u64 },
+    #[error("Request sender isn't authorized")]
+    Unauthorized,
+    #[error("Rate limit exceeded, retry after {}ms", retry_after.as_millis())]
+    RateLimited { retry_after: Duration },
+    #[error("Builder is shutting down")]
+    ShuttingDown,
+    #[error("Too many transactions in a single submission ({len}/{max})")]
+    BatchTooLarge { len: usize, max: usize },
+    #[error("Submission too big ({len}/{max} bytes)")]
+    BodyTooLarge { len: u64, max: u64 },
+    #[error("{0}")]
+    SubmissionRejected(String),
+}
+
+impl<Types: NodeType> Error<Types> {
+    /// HTTP status best describing this error
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::SignatureValidation | Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::AlreadyDecided => StatusCode::GONE,
+            Error::TxTooBig { .. } | Error::BatchTooLarge { .. } | Error::BodyTooLarge { .. } => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            Error::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::SubmissionRejected(_) => StatusCode::BAD_REQUEST,
+            Error::ApiTimeout | Error::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Signing(_) | Error::BuildBlock(_) | Error::TxnSender(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// Convert into an API error with [`Self::status`]. Unlike conversion into
+    /// [`BuildError`], this lets API clients tell e.g. rate limiting from other failures.
+    /// Delay of [`Self::RateLimited`] is also reported in a `Retry-After` header,
+    /// see [`RetryAfterListener`](crate::utils::RetryAfterListener).
+    pub fn into_api_error<E: tide_disco::Error>(self) -> E {
+        if let Error::RateLimited { retry_after } = &self {
+            report_retry_after(*retry_after);
+        }
+        E::catch_all(self.status(), self.to_string())
+    }
 }
 
 impl<Types: NodeType> From<Error<Types>> for BuildError {
@@ -45,6 +89,19 @@ impl<Types: NodeType> From<Error<Types>> for BuildError {
             Error::TxTooBig { len, max_tx_len } => {
                 BuildError::Error(format!("Transaction too big ({len}/{max_tx_len}"))
             }
+            Error::Unauthorized => BuildError::Error("Request sender isn't authorized".to_owned()),
+            Error::RateLimited { retry_after } => BuildError::Error(format!(
+                "Rate limit exceeded, retry after {}ms",
+                retry_after.as_millis()
+            )),
+            Error::ShuttingDown => BuildError::Error("Builder is shutting down".to_owned()),
+            Error::BatchTooLarge { len, max } => BuildError::Error(format!(
+                "Too many transactions in a single submission ({len}/{max})"
+            )),
+            Error::BodyTooLarge { len, max } => {
+                BuildError::Error(format!("Submission too big ({len}/{max} bytes)"))
+            }
+            Error::SubmissionRejected(reason) => BuildError::Error(reason),
         }
     }
 }
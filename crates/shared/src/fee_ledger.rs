@@ -0,0 +1,457 @@
+//! Record of fees the builder committed to paying.
+//!
+//! Every signed fee commitment is recorded in [`FeeLedger`] as [`FeeStatus::Offered`].
+//! Block fees are marked [`FeeStatus::Claimed`] once the proposer claims the block header
+//! input, and both block and bundle fees are marked [`FeeStatus::Charged`] once a decided
+//! leaf shows them included. Records are keyed by the view number the request was made for. Records can be queried by view range, either directly or
+//! through the API defined by [`define_api`], and exported as CSV.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, Write},
+    ops::RangeBounds,
+};
+
+use async_lock::RwLock;
+use chrono::{DateTime, Utc};
+use committable::Commitment;
+use futures::FutureExt;
+use hotshot_types::{traits::node_implementation::NodeType, utils::BuilderCommitment};
+use serde::{Deserialize, Serialize};
+use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, RequestParams};
+use vbs::version::StaticVersion;
+
+/// Number of views fee records are retained for by default
+pub const DEFAULT_FEE_LEDGER_RETENTION: u64 = 10_000;
+
+/// Name of the API module serving fee reports
+pub const FEE_LEDGER_MODULE: &str = "fee_ledger";
+
+/// Version of the fee ledger API
+pub type FeeLedgerApiVersion = StaticVersion<0, 1>;
+
+/// Definition of the fee ledger API
+const FEE_LEDGER_API: &str = r#"
+[route.fees]
+PATH = ["fees/:from/:to"]
+":from" = "Integer"
+":to" = "Integer"
+METHOD = "GET"
+DOC = "Get fee records for views from `from` (inclusive) to `to` (exclusive)"
+
+[route.fees_csv]
+PATH = ["fees/:from/:to/csv"]
+":from" = "Integer"
+":to" = "Integer"
+METHOD = "GET"
+DOC = "Get fee records for views from `from` (inclusive) to `to` (exclusive) as CSV"
+"#;
+
+/// What a fee was offered for
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum FeeSubject<Types: NodeType> {
+    /// A block with given builder commitment
+    Block(BuilderCommitment),
+    /// A bundle of transactions with given commitments
+    Bundle(Vec<Commitment<Types::Transaction>>),
+}
+
+/// Status of a fee commitment
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum FeeStatus {
+    /// Fee commitment was signed and sent out
+    Offered,
+    /// Proposer claimed the block header input for the block the fee was offered for
+    Claimed,
+    /// A decided leaf includes the block or bundle the fee was offered for
+    Charged,
+}
+
+/// A single fee commitment
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct FeeRecord<Types: NodeType> {
+    /// View the fee was offered for
+    pub view: u64,
+    /// What the fee was offered for
+    pub subject: FeeSubject<Types>,
+    /// Fee amount
+    pub amount: u64,
+    /// Account the fee is paid from
+    pub account: Types::BuilderSignatureKey,
+    /// Current status of the fee
+    pub status: FeeStatus,
+    /// When the fee commitment was signed
+    pub offered_at: DateTime<Utc>,
+}
+
+/// Ledger of fee commitments, see module documentation for details
+#[derive(Debug)]
+pub struct FeeLedger<Types: NodeType> {
+    retention: u64,
+    records: RwLock<BTreeMap<u64, Vec<FeeRecord<Types>>>>,
+}
+
+impl<Types: NodeType> Default for FeeLedger<Types> {
+    fn default() -> Self {
+        Self::new(DEFAULT_FEE_LEDGER_RETENTION)
+    }
+}
+
+impl<Types: NodeType> FeeLedger<Types> {
+    /// Create an empty ledger, retaining records for `retention` views
+    /// before the latest decided one
+    pub fn new(retention: u64) -> Self {
+        Self {
+            retention,
+            records: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Record a signed fee commitment. Offering the same block for the same view
+    /// again, e.g. when serving it from cache, doesn't add another record.
+    pub async fn record_offered(
+        &self,
+        view: u64,
+        subject: FeeSubject<Types>,
+        amount: u64,
+        account: Types::BuilderSignatureKey,
+    ) {
+        let mut records = self.records.write().await;
+        let view_records = records.entry(view).or_default();
+        if matches!(subject, FeeSubject::Block(_))
+            && view_records.iter().any(|record| record.subject == subject)
+        {
+            return;
+        }
+        view_records.push(FeeRecord {
+            view,
+            subject,
+            amount,
+            account,
+            status: FeeStatus::Offered,
+            offered_at: Utc::now(),
+        });
+    }
+
+    /// Mark fees offered for the block with `builder_commitment` in `view` as claimed.
+    /// Returns whether any were found.
+    pub async fn mark_claimed(&self, view: u64, builder_commitment: &BuilderCommitment) -> bool {
+        let mut records = self.records.write().await;
+        let mut found = false;
+        for record in records.get_mut(&view).into_iter().flatten() {
+            if record.subject == FeeSubject::Block(builder_commitment.clone()) {
+                record.status = record.status.max(FeeStatus::Claimed);
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Reconcile fees with a leaf decided in `view`, with given `builder_commitment`
+    /// and including `transactions`.
+    /// Block fees are keyed by the view the proposer requested the block for, which may be
+    /// any view before the decided one after timeouts, so the latest block fee offered
+    /// for `builder_commitment` up to `view` is charged. Bundle fees offered for `view`
+    /// are charged if any of the bundle's transactions were included.
+    /// Records for views more than the retention period before `view` are pruned.
+    pub async fn reconcile(
+        &self,
+        view: u64,
+        builder_commitment: &BuilderCommitment,
+        transactions: &HashSet<Commitment<Types::Transaction>>,
+    ) {
+        let mut records = self.records.write().await;
+        let block = FeeSubject::Block(builder_commitment.clone());
+        for (_, view_records) in records.range_mut(..=view).rev() {
+            if let Some(record) = view_records
+                .iter_mut()
+                .find(|record| record.subject == block)
+            {
+                record.status = FeeStatus::Charged;
+                break;
+            }
+        }
+        for record in records.get_mut(&view).into_iter().flatten() {
+            if let FeeSubject::Bundle(commitments) = &record.subject {
+                if commitments
+                    .iter()
+                    .any(|commitment| transactions.contains(commitment))
+                {
+                    record.status = FeeStatus::Charged;
+                }
+            }
+        }
+
+        let cutoff = view.saturating_sub(self.retention);
+        *records = records.split_off(&cutoff);
+    }
+
+    /// Fee records for views in `views`, ordered by view
+    pub async fn report(&self, views: impl RangeBounds<u64>) -> Vec<FeeRecord<Types>> {
+        self.records
+            .read()
+            .await
+            .range(views)
+            .flat_map(|(_, records)| records.iter().cloned())
+            .collect()
+    }
+
+    /// Write fee records for views in `views` to `writer` as CSV
+    pub async fn export_csv(
+        &self,
+        views: impl RangeBounds<u64>,
+        writer: impl Write,
+    ) -> io::Result<()> {
+        write_csv(&self.report(views).await, writer)
+    }
+}
+
+/// Write `records` to `writer` as CSV with a header row.
+/// Bundle transactions are listed in a single column separated by `;`.
+pub fn write_csv<Types: NodeType>(
+    records: &[FeeRecord<Types>],
+    mut writer: impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "view,kind,subject,amount,account,status,offered_at")?;
+    for record in records {
+        let (kind, subject) = match &record.subject {
+            FeeSubject::Block(commitment) => ("block", commitment.to_string()),
+            FeeSubject::Bundle(commitments) => (
+                "bundle",
+                commitments
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(";"),
+            ),
+        };
+        writeln!(
+            writer,
+            "{},{kind},{subject},{},{},{:?},{}",
+            record.view,
+            record.amount,
+            record.account,
+            record.status,
+            record.offered_at.to_rfc3339(),
+        )?;
+    }
+    Ok(())
+}
+
+/// State serving the fee ledger API
+pub trait FeeLedgerDataSource<Types: NodeType> {
+    /// Ledger to serve reports from
+    fn fee_ledger(&self) -> &FeeLedger<Types>;
+}
+
+fn view_range(req: &RequestParams) -> Result<std::ops::Range<u64>, RequestError> {
+    let from: u64 = req.integer_param("from")?;
+    let to: u64 = req.integer_param("to")?;
+    Ok(from..to)
+}
+
+/// Define the fee ledger API, to be registered in [`FEE_LEDGER_MODULE`]
+pub fn define_api<State, Types, Error>() -> Result<Api<State, Error, FeeLedgerApiVersion>, ApiError>
+where
+    Types: NodeType,
+    State: 'static + Send + Sync + ReadState,
+    <State as ReadState>::State: Send + Sync + FeeLedgerDataSource<Types>,
+    Error: 'static + tide_disco::Error + From<RequestError>,
+{
+    let mut api = Api::new(
+        toml::from_str::<toml::Value>(FEE_LEDGER_API)
+            .expect("Fee ledger API definition should be valid TOML"),
+    )?;
+    api.get("fees", |req, state| {
+        async move { Ok(state.fee_ledger().report(view_range(&req)?).await) }.boxed()
+    })?
+    .get("fees_csv", |req, state| {
+        async move {
+            let mut csv = Vec::new();
+            state
+                .fee_ledger()
+                .export_csv(view_range(&req)?, &mut csv)
+                .await
+                .map_err(|err| {
+                    Error::catch_all(
+                        tide_disco::StatusCode::INTERNAL_SERVER_ERROR,
+                        err.to_string(),
+                    )
+                })?;
+            // CSV we write is always valid UTF-8
+            Ok(String::from_utf8_lossy(&csv).into_owned())
+        }
+        .boxed()
+    })?;
+    Ok(api)
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use committable::Committable;
+    use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+    use hotshot_types::traits::signature_key::BuilderSignatureKey;
+
+    use super::*;
+
+    type FeeLedger = super::FeeLedger<TestTypes>;
+
+    fn account() -> <TestTypes as NodeType>::BuilderSignatureKey {
+        <TestTypes as NodeType>::BuilderSignatureKey::generated_from_seed_indexed([0; 32], 0).0
+    }
+
+    fn block(byte: u8) -> BuilderCommitment {
+        BuilderCommitment::from_bytes([byte])
+    }
+
+    fn statuses(records: &[FeeRecord<TestTypes>]) -> Vec<(u64, FeeStatus)> {
+        records
+            .iter()
+            .map(|record| (record.view, record.status))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_fee_lifecycle() {
+        let ledger = FeeLedger::default();
+        let included = TestTransaction::new(vec![1]).commit();
+        let excluded = TestTransaction::new(vec![2]).commit();
+
+        ledger
+            .record_offered(1, FeeSubject::Block(block(1)), 10, account())
+            .await;
+        ledger
+            .record_offered(1, FeeSubject::Block(block(2)), 20, account())
+            .await;
+        ledger
+            .record_offered(2, FeeSubject::Bundle(vec![included]), 30, account())
+            .await;
+        ledger
+            .record_offered(2, FeeSubject::Bundle(vec![excluded]), 40, account())
+            .await;
+
+        assert!(ledger.mark_claimed(1, &block(1)).await);
+        assert!(!ledger.mark_claimed(1, &block(3)).await);
+        assert_eq!(
+            statuses(&ledger.report(..).await),
+            vec![
+                (1, FeeStatus::Claimed),
+                (1, FeeStatus::Offered),
+                (2, FeeStatus::Offered),
+                (2, FeeStatus::Offered)
+            ]
+        );
+
+        ledger.reconcile(1, &block(1), &HashSet::new()).await;
+        ledger
+            .reconcile(2, &block(3), &HashSet::from([included]))
+            .await;
+        assert_eq!(
+            statuses(&ledger.report(..).await),
+            vec![
+                (1, FeeStatus::Charged),
+                (1, FeeStatus::Offered),
+                (2, FeeStatus::Charged),
+                (2, FeeStatus::Offered)
+            ]
+        );
+
+        // Claiming doesn't downgrade charged fees
+        ledger.mark_claimed(1, &block(1)).await;
+        assert_eq!(ledger.report(1..2).await[0].status, FeeStatus::Charged);
+
+        assert_eq!(
+            ledger
+                .report(2..)
+                .await
+                .iter()
+                .map(|record| record.amount)
+                .collect::<Vec<_>>(),
+            vec![30, 40]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_fees_charged_by_commitment() {
+        let ledger = FeeLedger::default();
+
+        // Same block offered on an older parent, then on the latest one twice
+        ledger
+            .record_offered(1, FeeSubject::Block(block(1)), 10, account())
+            .await;
+        ledger
+            .record_offered(3, FeeSubject::Block(block(1)), 20, account())
+            .await;
+        ledger
+            .record_offered(3, FeeSubject::Block(block(1)), 20, account())
+            .await;
+        ledger
+            .record_offered(3, FeeSubject::Block(block(2)), 30, account())
+            .await;
+
+        // Decided two views later, after a timeout
+        ledger.reconcile(5, &block(1), &HashSet::new()).await;
+        assert_eq!(
+            statuses(&ledger.report(..).await),
+            vec![
+                (1, FeeStatus::Offered),
+                (3, FeeStatus::Charged),
+                (3, FeeStatus::Offered)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retention() {
+        let ledger = FeeLedger::new(5);
+        for view in 0..10 {
+            ledger
+                .record_offered(view, FeeSubject::Block(block(0)), view, account())
+                .await;
+        }
+        ledger.reconcile(10, &block(1), &HashSet::new()).await;
+        assert_eq!(
+            ledger
+                .report(..)
+                .await
+                .iter()
+                .map(|record| record.view)
+                .collect::<Vec<_>>(),
+            (5..10).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_export() {
+        let ledger = FeeLedger::default();
+        let transactions = vec![
+            TestTransaction::new(vec![1]).commit(),
+            TestTransaction::new(vec![2]).commit(),
+        ];
+        ledger
+            .record_offered(1, FeeSubject::Block(block(1)), 10, account())
+            .await;
+        ledger
+            .record_offered(2, FeeSubject::Bundle(transactions.clone()), 20, account())
+            .await;
+
+        let mut csv = Vec::new();
+        ledger.export_csv(.., &mut csv).await.unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "view,kind,subject,amount,account,status,offered_at"
+        );
+        assert!(lines[1].starts_with(&format!("1,block,{},10,{},Offered,", block(1), account())));
+        assert!(lines[2].starts_with(&format!(
+            "2,bundle,{};{},20,",
+            transactions[0], transactions[1]
+        )));
+    }
+}
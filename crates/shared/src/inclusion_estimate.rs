@@ -0,0 +1,316 @@
+//! Estimates of fee and time until inclusion for transactions submitted to the builder.
+//!
+//! Estimates are based on the queue of the highest view [`BuilderState`](crate::state::BuilderState),
+//! the current maximum block size and [`InclusionLatencies`] of recently decided transactions,
+//! as recorded by [`BuilderStateCoordinator`]. Inputs are refreshed at most once per
+//! [`InclusionEstimator`] refresh period, as estimates are served to unauthenticated
+//! clients through the API defined by [`define_api`].
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+use futures::FutureExt;
+use hotshot_types::traits::node_implementation::NodeType;
+use serde::{Deserialize, Serialize};
+use tide_disco::{api::ApiError, method::ReadState, Api, RequestError};
+use vbs::version::StaticVersion;
+
+use crate::{coordinator::BuilderStateCoordinator, state::QueueStatistics};
+
+/// Number of most recent inclusion latencies kept by default
+pub const DEFAULT_INCLUSION_LATENCY_WINDOW: usize = 1000;
+
+/// Default period for which [`InclusionEstimator`] reuses [`EstimateInputs`]
+pub const DEFAULT_ESTIMATE_REFRESH_PERIOD: Duration = Duration::from_secs(1);
+
+/// Name of the API module serving inclusion estimates
+pub const INCLUSION_ESTIMATE_MODULE: &str = "estimate";
+
+/// Version of the inclusion estimate API
+pub type InclusionEstimateApiVersion = StaticVersion<0, 1>;
+
+/// Definition of the inclusion estimate API
+const INCLUSION_ESTIMATE_API: &str = r#"
+[route.inclusion]
+PATH = ["inclusion/:size"]
+":size" = "Integer"
+METHOD = "GET"
+DOC = "Estimate fee and time until inclusion for a transaction of `size` bytes submitted now"
+"#;
+
+/// Percentiles of time between receiving a transaction and its inclusion being decided
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    /// Number of measurements percentiles are computed from
+    pub samples: usize,
+    /// Median latency
+    pub p50: Duration,
+    /// 90th percentile latency
+    pub p90: Duration,
+    /// 99th percentile latency
+    pub p99: Duration,
+}
+
+/// Time between receiving and deciding recently included transactions
+#[derive(Clone, Debug)]
+pub struct InclusionLatencies {
+    window: usize,
+    samples: VecDeque<Duration>,
+}
+
+impl Default for InclusionLatencies {
+    fn default() -> Self {
+        Self::new(DEFAULT_INCLUSION_LATENCY_WINDOW)
+    }
+}
+
+impl InclusionLatencies {
+    /// Create a tracker keeping at most `window` latest measurements
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            samples: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Record a measurement, evicting the oldest one if the window is full
+    pub fn record(&mut self, latency: Duration) {
+        if self.window == 0 {
+            return;
+        }
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    /// Percentiles of recorded latencies, `None` if nothing was recorded yet
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest-rank percentile
+        let percentile = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        Some(LatencyPercentiles {
+            samples: sorted.len(),
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+        })
+    }
+
+    /// Number of measurements recorded
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether nothing was recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Estimate of fee and time until inclusion for a transaction
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionEstimate {
+    /// Size of the transaction, in bytes
+    pub transaction_size: u64,
+    /// Number of transactions queued ahead of it
+    pub queue_len: usize,
+    /// Total size of transactions queued ahead of it, in bytes
+    pub queue_bytes: u64,
+    /// Current maximum block size, in bytes
+    pub max_block_size: u64,
+    /// Number of views until a block including the transaction is offered,
+    /// assuming every view takes a full block from the queue.
+    /// `None` if the transaction doesn't fit in a block at all.
+    pub expected_views: Option<u64>,
+    /// Fee the builder would offer for the space the transaction takes in a block
+    pub estimated_fee: u64,
+    /// Recent time between receiving a transaction and its inclusion being decided,
+    /// `None` if no transactions were decided yet
+    pub latency: Option<LatencyPercentiles>,
+}
+
+impl InclusionEstimate {
+    /// Estimate inclusion of a transaction of `transaction_size` bytes queued after
+    /// transactions described by `queue`, with blocks of at most `max_block_size` bytes
+    /// and builder offering `estimated_fee` for the transaction's space
+    pub fn new(
+        transaction_size: u64,
+        queue: QueueStatistics,
+        max_block_size: u64,
+        estimated_fee: u64,
+        latency: Option<LatencyPercentiles>,
+    ) -> Self {
+        let expected_views = (transaction_size <= max_block_size && max_block_size > 0)
+            .then(|| (queue.total_bytes + transaction_size).div_ceil(max_block_size));
+        Self {
+            transaction_size,
+            queue_len: queue.len,
+            queue_bytes: queue.total_bytes,
+            max_block_size,
+            expected_views,
+            estimated_fee,
+            latency,
+        }
+    }
+}
+
+/// State of the builder inclusion estimates are based on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EstimateInputs {
+    /// Statistics of the highest view builder state's queue
+    pub queue: QueueStatistics,
+    /// View a block or bundle built from that queue would be proposed in,
+    /// zero if there are no builder states yet
+    pub proposal_view: u64,
+    /// Recent inclusion latencies, see [`InclusionEstimate::latency`]
+    pub latency: Option<LatencyPercentiles>,
+}
+
+/// Source of [`EstimateInputs`], caching them for a refresh period so that estimate
+/// requests don't dequeue and scan the transaction queue every time.
+/// Concurrent requests after the period has passed wait on a single refresh.
+#[derive(Debug)]
+pub struct InclusionEstimator {
+    refresh_period: Duration,
+    collect_timeout: Duration,
+    cached: Mutex<Option<(Instant, EstimateInputs)>>,
+}
+
+impl InclusionEstimator {
+    /// Create an estimator reusing inputs for `refresh_period`. On refresh, transactions
+    /// still in the channel are dequeued for up to `collect_timeout` to be accounted for.
+    pub fn new(refresh_period: Duration, collect_timeout: Duration) -> Self {
+        Self {
+            refresh_period,
+            collect_timeout,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Current inputs, refreshed from `coordinator` if older than the refresh period
+    pub async fn inputs<Types: NodeType>(
+        &self,
+        coordinator: &BuilderStateCoordinator<Types>,
+    ) -> EstimateInputs {
+        let mut cached = self.cached.lock().await;
+        if let Some((refreshed_at, inputs)) = *cached {
+            if refreshed_at.elapsed() < self.refresh_period {
+                return inputs;
+            }
+        }
+
+        let (queue, proposal_view) = match coordinator.highest_view_builder().await {
+            Some(builder_state) => {
+                // Account for transactions that weren't dequeued yet
+                builder_state
+                    .collect_txns(Instant::now() + self.collect_timeout)
+                    .await;
+                (
+                    builder_state.queue_statistics().await,
+                    *builder_state.id().proposal_view(),
+                )
+            }
+            None => Default::default(),
+        };
+        let inputs = EstimateInputs {
+            queue,
+            proposal_view,
+            latency: coordinator.inclusion_latencies().await,
+        };
+        *cached = Some((Instant::now(), inputs));
+        inputs
+    }
+}
+
+/// State serving the inclusion estimate API
+#[async_trait]
+pub trait InclusionEstimateDataSource {
+    /// Estimate inclusion of a transaction of `transaction_size` bytes submitted now
+    async fn estimate_inclusion(&self, transaction_size: u64) -> InclusionEstimate;
+}
+
+/// Define the inclusion estimate API, to be registered in [`INCLUSION_ESTIMATE_MODULE`]
+pub fn define_api<State, Error>() -> Result<Api<State, Error, InclusionEstimateApiVersion>, ApiError>
+where
+    State: 'static + Send + Sync + ReadState,
+    <State as ReadState>::State: Send + Sync + InclusionEstimateDataSource,
+    Error: 'static + tide_disco::Error + From<RequestError>,
+{
+    let mut api = Api::new(
+        toml::from_str::<toml::Value>(INCLUSION_ESTIMATE_API)
+            .expect("Inclusion estimate API definition should be valid TOML"),
+    )?;
+    api.get("inclusion", |req, state| {
+        async move {
+            let size: u64 = req.integer_param("size")?;
+            Ok(state.estimate_inclusion(size).await)
+        }
+        .boxed()
+    })?;
+    Ok(api)
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let mut latencies = InclusionLatencies::new(100);
+        assert_eq!(latencies.percentiles(), None);
+
+        for millis in (1..=100).rev() {
+            latencies.record(Duration::from_millis(millis));
+        }
+        let percentiles = latencies.percentiles().unwrap();
+        assert_eq!(percentiles.samples, 100);
+        assert_eq!(percentiles.p50, Duration::from_millis(50));
+        assert_eq!(percentiles.p90, Duration::from_millis(90));
+        assert_eq!(percentiles.p99, Duration::from_millis(99));
+
+        // Oldest measurements are evicted
+        for _ in 0..100 {
+            latencies.record(Duration::from_millis(1));
+        }
+        assert_eq!(latencies.len(), 100);
+        assert_eq!(
+            latencies.percentiles().unwrap().p99,
+            Duration::from_millis(1)
+        );
+    }
+
+    #[test]
+    fn test_estimate() {
+        let queue = QueueStatistics {
+            len: 10,
+            total_bytes: 2500,
+            oldest_wait: None,
+        };
+
+        let estimate = InclusionEstimate::new(100, queue, 1000, 200, None);
+        assert_eq!(estimate.expected_views, Some(3));
+        assert_eq!(estimate.estimated_fee, 200);
+        assert_eq!(estimate.queue_len, 10);
+
+        // Fits exactly into the third block
+        let estimate = InclusionEstimate::new(500, queue, 1000, 2, None);
+        assert_eq!(estimate.expected_views, Some(3));
+
+        // Doesn't fit into a block at all
+        let estimate = InclusionEstimate::new(1001, queue, 1000, 2, None);
+        assert_eq!(estimate.expected_views, None);
+
+        let estimate = InclusionEstimate::new(100, QueueStatistics::default(), 1000, 2, None);
+        assert_eq!(estimate.expected_views, Some(1));
+    }
+}
@@ -0,0 +1,223 @@
+//! Model of VID precomputation latency as a function of block size,
+//! used by [`BlockSizeLimits`](crate::block_size_limits::BlockSizeLimits)
+//! to pick the largest block size it can serve in time.
+
+use std::{collections::VecDeque, time::Duration};
+
+/// Parameters of a [`LatencyModel`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyModelConfig {
+    /// Margin subtracted from the model's target latency, i.e. the API timeout,
+    /// to account for prediction errors and the rest of request handling
+    pub safety_margin: Duration,
+    /// Number of most recent measurements the model is fitted to
+    pub window: usize,
+}
+
+/// A single measurement of VID precomputation latency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySample {
+    /// Size of the block in bytes
+    pub block_size: u64,
+    /// Number of nodes VID was computed for
+    pub num_nodes: usize,
+    /// Time precomputation took
+    pub duration: Duration,
+}
+
+/// Linear cost model fitted by [`LatencyModel::fit`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostModel {
+    /// Number of nodes the model applies to
+    pub num_nodes: usize,
+    /// Fixed cost of precomputation, in seconds
+    pub base_secs: f64,
+    /// Cost of every byte of the block, in seconds
+    pub secs_per_byte: f64,
+}
+
+impl CostModel {
+    /// Predicted precomputation time for a block of `block_size` bytes
+    pub fn predict(&self, block_size: u64) -> Duration {
+        Duration::from_secs_f64((self.base_secs + self.secs_per_byte * block_size as f64).max(0.0))
+    }
+
+    /// Largest block size predicted to be precomputed within `budget`.
+    /// Returns `None` if even an empty block is predicted to take longer.
+    pub fn max_block_size_within(&self, budget: Duration) -> Option<u64> {
+        let budget = budget.as_secs_f64() - self.base_secs;
+        if budget < 0.0 {
+            return None;
+        }
+        if self.secs_per_byte <= 0.0 {
+            // Block size doesn't seem to affect latency at all
+            return Some(u64::MAX);
+        }
+        // Saturating conversion
+        Some((budget / self.secs_per_byte) as u64)
+    }
+}
+
+/// Model of VID precomputation latency, fitted with least squares to recent
+/// measurements for the most recently seen number of nodes
+#[derive(Debug, Clone)]
+pub struct LatencyModel {
+    config: LatencyModelConfig,
+    target_latency: Duration,
+    samples: VecDeque<LatencySample>,
+}
+
+impl LatencyModel {
+    /// Create a model without any measurements for VID precomputation
+    /// that must finish within `target_latency`, normally the API timeout
+    pub fn new(config: LatencyModelConfig, target_latency: Duration) -> Self {
+        Self {
+            config,
+            target_latency,
+            samples: VecDeque::with_capacity(config.window),
+        }
+    }
+
+    /// Parameters of this model
+    pub fn config(&self) -> &LatencyModelConfig {
+        &self.config
+    }
+
+    /// Time VID precomputation must finish in
+    pub fn target_latency(&self) -> Duration {
+        self.target_latency
+    }
+
+    /// Record a measurement, evicting the oldest one if the window is full
+    pub fn record(&mut self, sample: LatencySample) {
+        if self.config.window == 0 {
+            return;
+        }
+        if self.samples.len() == self.config.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Fit a cost model to measurements for the most recent number of nodes.
+    /// Returns `None` until there are measurements for at least two different block sizes.
+    pub fn fit(&self) -> Option<CostModel> {
+        let num_nodes = self.samples.back()?.num_nodes;
+        let points = self
+            .samples
+            .iter()
+            .filter(|sample| sample.num_nodes == num_nodes)
+            .map(|sample| (sample.block_size as f64, sample.duration.as_secs_f64()));
+
+        let (mut n, mut sum_x, mut sum_y, mut sum_xx, mut sum_xy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (x, y) in points {
+            n += 1.0;
+            sum_x += x;
+            sum_y += y;
+            sum_xx += x * x;
+            sum_xy += x * y;
+        }
+
+        let denominator = n * sum_xx - sum_x * sum_x;
+        // All measurements are for the same block size, can't tell how size affects latency
+        if denominator <= f64::EPSILON * n * sum_xx {
+            return None;
+        }
+        let secs_per_byte = (n * sum_xy - sum_x * sum_y) / denominator;
+        let base_secs = (sum_y - secs_per_byte * sum_x) / n;
+
+        Some(CostModel {
+            num_nodes,
+            base_secs,
+            secs_per_byte,
+        })
+    }
+
+    /// Largest block size predicted to be precomputed within the target latency
+    /// minus safety margin. Returns `None` if the model can't be fitted yet.
+    pub fn max_block_size(&self) -> Option<u64> {
+        let budget = self
+            .target_latency
+            .saturating_sub(self.config.safety_margin);
+        Some(self.fit()?.max_block_size_within(budget).unwrap_or(0))
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_LATENCY: Duration = Duration::from_millis(1000);
+
+    const CONFIG: LatencyModelConfig = LatencyModelConfig {
+        safety_margin: Duration::from_millis(200),
+        window: 8,
+    };
+
+    fn sample(block_size: u64, num_nodes: usize, millis: u64) -> LatencySample {
+        LatencySample {
+            block_size,
+            num_nodes,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn test_fit() {
+        let mut model = LatencyModel::new(CONFIG, TARGET_LATENCY);
+        assert_eq!(model.fit(), None);
+
+        // Not enough to tell how size affects latency
+        model.record(sample(1000, 10, 110));
+        model.record(sample(1000, 10, 110));
+        assert_eq!(model.fit(), None);
+
+        // 100ms + 10ms per kilobyte
+        model.record(sample(2000, 10, 120));
+        model.record(sample(5000, 10, 150));
+        let cost = model.fit().unwrap();
+        assert_eq!(cost.num_nodes, 10);
+        assert!((cost.base_secs - 0.1).abs() < 1e-9);
+        assert!((cost.secs_per_byte - 1e-5).abs() < 1e-12);
+
+        // 800ms budget, 700ms after base cost
+        let max_block_size = model.max_block_size().unwrap();
+        assert!(max_block_size.abs_diff(70_000) <= 1);
+        assert!((cost.predict(50_000).as_secs_f64() - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_num_nodes_change() {
+        let mut model = LatencyModel::new(CONFIG, TARGET_LATENCY);
+        model.record(sample(1000, 10, 110));
+        model.record(sample(2000, 10, 120));
+        assert!(model.fit().is_some());
+
+        // Measurements for other node counts aren't used
+        model.record(sample(1000, 20, 220));
+        assert_eq!(model.fit(), None);
+        model.record(sample(2000, 20, 240));
+        let cost = model.fit().unwrap();
+        assert_eq!(cost.num_nodes, 20);
+        assert!((cost.secs_per_byte - 2e-5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_window_and_budget() {
+        let mut model = LatencyModel::new(
+            LatencyModelConfig {
+                window: 2,
+                ..CONFIG
+            },
+            TARGET_LATENCY,
+        );
+        model.record(sample(1000, 10, 110));
+        model.record(sample(2000, 10, 120));
+
+        // Old measurements are evicted, new ones leave no budget at all
+        model.record(sample(1000, 10, 950));
+        model.record(sample(2000, 10, 1050));
+        assert_eq!(model.max_block_size(), Some(0));
+    }
+}
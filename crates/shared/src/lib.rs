@@ -1,9 +1,17 @@
 #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
 
 pub mod block;
+pub mod block_size_limits;
 pub mod coordinator;
 pub mod error;
+pub mod fee_ledger;
+pub mod inclusion_estimate;
+pub mod latency_model;
+pub mod rate_limit;
+pub mod receipt;
+pub mod shutdown;
 pub mod state;
+pub mod submit_limit;
 #[cfg_attr(coverage_nightly, coverage(off))]
 pub mod testing;
 pub mod utils;
@@ -0,0 +1,308 @@
+//! Token bucket rate limiting for API requests
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// Parameters of a [`RateLimiter`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Maximum number of requests allowed in quick succession
+    pub burst: u32,
+    /// Time it takes to replenish allowance for a single request
+    pub replenish_period: Duration,
+}
+
+impl RateLimit {
+    /// Limit allowing `rate` requests per second, all of which can be made at once.
+    /// Zero `rate` means no limit.
+    pub fn per_second(rate: u32) -> Self {
+        Self {
+            burst: rate,
+            replenish_period: Duration::from_secs(1).checked_div(rate).unwrap_or_default(),
+        }
+    }
+
+    /// Whether this limit allows any number of requests
+    pub fn is_unlimited(&self) -> bool {
+        self.replenish_period.is_zero()
+    }
+}
+
+/// Allowance of a single rate-limited caller
+#[derive(Debug)]
+pub(crate) struct Bucket {
+    tokens: u32,
+    last_replenished: Instant,
+}
+
+impl Bucket {
+    /// Bucket with full allowance under `limit`
+    pub(crate) fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst,
+            last_replenished: now,
+        }
+    }
+
+    /// Add allowance replenished since the last call, up to [`RateLimit::burst`]
+    pub(crate) fn replenish(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_replenished);
+        let replenished = elapsed.as_nanos() / limit.replenish_period.as_nanos();
+        if replenished > 0 {
+            let tokens = (u128::from(self.tokens) + replenished).min(limit.burst.into());
+            // Can't overflow, as it's capped by `burst`
+            self.tokens = tokens as u32;
+            self.last_replenished = if self.tokens == limit.burst {
+                now
+            } else {
+                // Can't overflow, as less than `burst` tokens were replenished
+                self.last_replenished + limit.replenish_period * replenished as u32
+            };
+        }
+    }
+
+    /// Whether the allowance is full, i.e. the caller hasn't made any requests lately
+    pub(crate) fn is_full(&self, limit: &RateLimit) -> bool {
+        self.tokens >= limit.burst
+    }
+
+    /// Time until allowance for `cost` requests is available, zero if it already is.
+    /// Allowance never exceeds [`RateLimit::burst`], so larger costs are never available
+    /// and should be rejected before getting here.
+    pub(crate) fn wait_for(&self, limit: &RateLimit, cost: u32, now: Instant) -> Duration {
+        if self.tokens >= cost {
+            return Duration::ZERO;
+        }
+        if cost > limit.burst {
+            return Duration::MAX;
+        }
+        let since_replenished = now.saturating_duration_since(self.last_replenished);
+        limit
+            .replenish_period
+            .checked_mul(cost - self.tokens)
+            .unwrap_or(Duration::MAX)
+            .saturating_sub(since_replenished)
+    }
+
+    /// Use up allowance for `cost` requests, which must be available
+    pub(crate) fn take(&mut self, cost: u32) {
+        self.tokens -= cost;
+    }
+}
+
+/// Token bucket rate limiter. Starts full, with [`RateLimit::burst`] requests allowed,
+/// replenishing allowance for one request every [`RateLimit::replenish_period`].
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// Create a new limiter enforcing `limit`
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket::full(&limit, Instant::now())),
+        }
+    }
+
+    /// Limit enforced by this limiter
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Account for a single request. If the request is over the limit,
+    /// returns time after which it can be retried.
+    pub fn check(&self) -> Result<(), Duration> {
+        if self.limit.is_unlimited() {
+            return Ok(());
+        }
+
+        // Poisoning isn't a concern, as the bucket is always left in a consistent state
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+
+        bucket.replenish(&self.limit, now);
+        let retry_after = bucket.wait_for(&self.limit, 1, now);
+        if !retry_after.is_zero() {
+            return Err(retry_after);
+        }
+
+        bucket.take(1);
+        Ok(())
+    }
+}
+
+/// Number of callers tracked by default by [`KeyedRateLimiter`] before idle ones are forgotten
+pub const DEFAULT_MAX_TRACKED_CALLERS: usize = 10_000;
+
+/// Token bucket rate limiter keeping a separate allowance for every caller,
+/// so that a single caller going over the limit doesn't affect others.
+/// Once more than `max_tracked` callers are tracked, ones with full allowance
+/// are forgotten. Active callers are never forgotten, so this isn't a hard limit.
+#[derive(Debug)]
+pub struct KeyedRateLimiter<K> {
+    limit: RateLimit,
+    max_tracked: usize,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Hash + Eq + Clone> KeyedRateLimiter<K> {
+    /// Create a new limiter enforcing `limit` for every caller
+    pub fn new(limit: RateLimit) -> Self {
+        Self::with_max_tracked(limit, DEFAULT_MAX_TRACKED_CALLERS)
+    }
+
+    /// Create a new limiter enforcing `limit` for every caller,
+    /// tracking up to `max_tracked` idle callers
+    pub fn with_max_tracked(limit: RateLimit, max_tracked: usize) -> Self {
+        Self {
+            limit,
+            max_tracked,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limit enforced by this limiter
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Account for a single request by `caller`. If the request is over the caller's limit,
+    /// returns time after which it can be retried.
+    pub fn check(&self, caller: &K) -> Result<(), Duration> {
+        if self.limit.is_unlimited() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.lock_buckets();
+        if !buckets.contains_key(caller) && buckets.len() >= self.max_tracked {
+            buckets.retain(|_, bucket| {
+                bucket.replenish(&self.limit, now);
+                !bucket.is_full(&self.limit)
+            });
+        }
+        let bucket = buckets
+            .entry(caller.clone())
+            .or_insert_with(|| Bucket::full(&self.limit, now));
+
+        bucket.replenish(&self.limit, now);
+        let retry_after = bucket.wait_for(&self.limit, 1, now);
+        if !retry_after.is_zero() {
+            return Err(retry_after);
+        }
+
+        bucket.take(1);
+        Ok(())
+    }
+
+    /// Number of callers currently tracked
+    pub fn tracked_callers(&self) -> usize {
+        self.lock_buckets().len()
+    }
+
+    fn lock_buckets(&self) -> MutexGuard<'_, HashMap<K, Bucket>> {
+        // Poisoning isn't a concern, as buckets are always left in a consistent state
+        self.buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+
+    use super::*;
+
+    #[test]
+    fn test_burst_and_replenish() {
+        const PERIOD: Duration = Duration::from_millis(100);
+
+        let limiter = RateLimiter::new(RateLimit {
+            burst: 3,
+            replenish_period: PERIOD,
+        });
+
+        for _ in 0..3 {
+            limiter.check().unwrap();
+        }
+        let retry_after = limiter.check().unwrap_err();
+        assert!(retry_after <= PERIOD);
+
+        sleep(retry_after);
+        limiter.check().unwrap();
+        limiter.check().unwrap_err();
+
+        // Allowance never goes over burst size
+        sleep(PERIOD * 5);
+        for _ in 0..3 {
+            limiter.check().unwrap();
+        }
+        limiter.check().unwrap_err();
+    }
+
+    #[test]
+    fn test_zero_period_is_unlimited() {
+        let limiter = RateLimiter::new(RateLimit {
+            burst: 0,
+            replenish_period: Duration::ZERO,
+        });
+        for _ in 0..100 {
+            limiter.check().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_keyed_limits_are_separate() {
+        let limiter = KeyedRateLimiter::new(RateLimit {
+            burst: 2,
+            replenish_period: Duration::from_secs(60),
+        });
+
+        limiter.check(&"a").unwrap();
+        limiter.check(&"a").unwrap();
+        limiter.check(&"a").unwrap_err();
+
+        // Another caller has its own allowance
+        limiter.check(&"b").unwrap();
+        limiter.check(&"b").unwrap();
+        limiter.check(&"b").unwrap_err();
+    }
+
+    #[test]
+    fn test_keyed_forgets_idle_callers() {
+        const PERIOD: Duration = Duration::from_millis(50);
+
+        let limiter = KeyedRateLimiter::with_max_tracked(
+            RateLimit {
+                burst: 1,
+                replenish_period: PERIOD,
+            },
+            2,
+        );
+
+        limiter.check(&1).unwrap();
+        limiter.check(&2).unwrap();
+        assert_eq!(limiter.tracked_callers(), 2);
+
+        // Callers that are still limited aren't forgotten
+        limiter.check(&3).unwrap();
+        assert_eq!(limiter.tracked_callers(), 3);
+        limiter.check(&1).unwrap_err();
+
+        sleep(PERIOD * 2);
+        limiter.check(&4).unwrap();
+        assert_eq!(limiter.tracked_callers(), 1);
+    }
+}
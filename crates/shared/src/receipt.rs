@@ -0,0 +1,420 @@
+//! Signed receipts proving the builder offered a transaction in a claimed block.
+//!
+//! Once a block is claimed, its transactions are recorded in [`ReceiptStore`]. Clients can then
+//! fetch an [`InclusionReceipt`] for their transaction through the API defined by [`define_api`].
+//! The receipt commits to the block, the view and the transaction's position in the block,
+//! and carries an [`InclusionProof`] against the Merkle root of the block's transactions.
+//!
+//! Receipts are signed with the same key as block commitments and bids, so the signed bytes
+//! start with [`RECEIPT_SIGNING_PREFIX`] to keep a receipt signature from being valid
+//! as any other builder message.
+
+use std::sync::{Arc, OnceLock};
+
+use committable::Commitment;
+use futures::FutureExt;
+use hotshot_types::{
+    traits::{node_implementation::NodeType, signature_key::BuilderSignatureKey},
+    utils::BuilderCommitment,
+};
+use quick_cache::sync::Cache;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, StatusCode};
+use vbs::version::StaticVersion;
+
+use crate::{error::Error, utils::BuilderKeys};
+
+/// Name of the API module serving inclusion receipts
+pub const INCLUSION_RECEIPT_MODULE: &str = "receipt";
+
+/// Version of the inclusion receipt API
+pub type InclusionReceiptApiVersion = StaticVersion<0, 1>;
+
+/// Definition of the inclusion receipt API
+const INCLUSION_RECEIPT_API: &str = r#"
+[route.receipt]
+PATH = ["receipt/:commitment"]
+":commitment" = "TaggedBase64"
+METHOD = "GET"
+DOC = "Get a signed receipt for transaction `commitment` if it was offered in a claimed block"
+"#;
+
+/// Domain separation prefix of [`ReceiptBody::signing_bytes`]
+pub const RECEIPT_SIGNING_PREFIX: &[u8] = b"marketplace-builder/inclusion-receipt/v1";
+
+/// Domain separation prefixes for Merkle tree hashes
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Hash of a node of the Merkle tree over a block's transactions
+pub type MerkleHash = [u8; 32];
+
+fn leaf_hash<Types: NodeType>(commitment: &Commitment<Types::Transaction>) -> MerkleHash {
+    Sha256::new()
+        .chain_update([LEAF_PREFIX])
+        .chain_update(commitment.as_ref())
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+    Sha256::new()
+        .chain_update([NODE_PREFIX])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// A step of an [`InclusionProof`], from the leaf towards the root
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    /// Hash of the sibling node
+    pub sibling: MerkleHash,
+    /// Whether the sibling is the left child of their parent
+    pub sibling_is_left: bool,
+}
+
+/// Merkle proof of a transaction's inclusion in a block.
+///
+/// Leaves are transaction commitments in block order. Nodes without
+/// a sibling are carried to the next level of the tree unchanged.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Merkle root of the block's transactions
+    pub root: MerkleHash,
+    /// Path from the transaction's leaf to the root
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    /// Prove inclusion of the transaction at `position` in `transactions`.
+    /// Returns `None` if `position` is out of bounds.
+    pub fn new<Types: NodeType>(
+        transactions: &[Commitment<Types::Transaction>],
+        position: usize,
+    ) -> Option<Self> {
+        if position >= transactions.len() {
+            return None;
+        }
+
+        let mut level: Vec<_> = transactions.iter().map(leaf_hash::<Types>).collect();
+        let mut index = position;
+        let mut path = Vec::new();
+        while level.len() > 1 {
+            let sibling = index ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                path.push(ProofStep {
+                    sibling: *hash,
+                    sibling_is_left: sibling < index,
+                });
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!("chunks are never empty"),
+                })
+                .collect();
+            index /= 2;
+        }
+
+        Some(Self {
+            root: level[0],
+            path,
+        })
+    }
+
+    /// Check that this proof shows `transaction` under [`Self::root`]
+    pub fn verify<Types: NodeType>(&self, transaction: &Commitment<Types::Transaction>) -> bool {
+        let computed = self
+            .path
+            .iter()
+            .fold(leaf_hash::<Types>(transaction), |hash, step| {
+                if step.sibling_is_left {
+                    node_hash(&step.sibling, &hash)
+                } else {
+                    node_hash(&hash, &step.sibling)
+                }
+            });
+        computed == self.root
+    }
+}
+
+/// Contents of an inclusion receipt
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ReceiptBody<Types: NodeType> {
+    /// Builder that offered the block
+    pub builder: Types::BuilderSignatureKey,
+    /// Commitment of the transaction
+    pub transaction: Commitment<Types::Transaction>,
+    /// Builder commitment of the block the transaction was offered in
+    pub builder_commitment: BuilderCommitment,
+    /// View number the block was claimed with, as sent by the leader in its request
+    pub view: u64,
+    /// Position of the transaction in the block
+    pub position: u64,
+    /// Proof of the transaction's inclusion in the block
+    pub proof: InclusionProof,
+}
+
+impl<Types: NodeType> ReceiptBody<Types> {
+    /// Serialized form of the body prefixed with [`RECEIPT_SIGNING_PREFIX`],
+    /// which is what the signature is over
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = RECEIPT_SIGNING_PREFIX.to_vec();
+        // Serializing plain data into a buffer can't fail
+        bincode::serialize_into(&mut bytes, self).expect("Failed to serialize receipt body");
+        bytes
+    }
+
+    /// Sign the receipt with builder's private key
+    pub fn sign(
+        self,
+        private_key: &<Types::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
+    ) -> Result<InclusionReceipt<Types>, Error<Types>> {
+        let signature = <Types::BuilderSignatureKey as BuilderSignatureKey>::sign_builder_message(
+            private_key,
+            &self.signing_bytes(),
+        )
+        .map_err(Error::Signing)?;
+        Ok(InclusionReceipt {
+            body: self,
+            signature,
+        })
+    }
+}
+
+/// A signed inclusion receipt
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct InclusionReceipt<Types: NodeType> {
+    /// Contents of the receipt
+    pub body: ReceiptBody<Types>,
+    /// Signature over [`ReceiptBody::signing_bytes`] by [`ReceiptBody::builder`]
+    pub signature: <Types::BuilderSignatureKey as BuilderSignatureKey>::BuilderSignature,
+}
+
+impl<Types: NodeType> InclusionReceipt<Types> {
+    /// Check that the receipt is signed by its builder and the proof is valid
+    pub fn verify(&self) -> bool {
+        self.body
+            .builder
+            .validate_builder_signature(&self.signature, &self.body.signing_bytes())
+            && self.body.proof.verify::<Types>(&self.body.transaction)
+    }
+}
+
+/// A claimed block, shared by receipts for all of its transactions
+#[derive(Debug)]
+struct ClaimedBlock<Types: NodeType> {
+    builder_commitment: BuilderCommitment,
+    view: u64,
+    transactions: Vec<Commitment<Types::Transaction>>,
+    /// Receipts signed so far, by position in the block
+    signed: Vec<OnceLock<InclusionReceipt<Types>>>,
+}
+
+/// Transactions of recently claimed blocks receipts can be issued for
+pub struct ReceiptStore<Types: NodeType> {
+    blocks: Cache<Commitment<Types::Transaction>, Arc<ClaimedBlock<Types>>>,
+}
+
+impl<Types: NodeType> ReceiptStore<Types> {
+    /// Create a store keeping receipts for at most `capacity` transactions
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            blocks: Cache::new(capacity),
+        }
+    }
+
+    /// Record `transactions` of block `builder_commitment` claimed in a request for `view`,
+    /// in block order.
+    /// Transactions offered in several claimed blocks get a receipt for the latest one.
+    pub fn record_claimed(
+        &self,
+        view: u64,
+        builder_commitment: BuilderCommitment,
+        transactions: Vec<Commitment<Types::Transaction>>,
+    ) {
+        let block = Arc::new(ClaimedBlock {
+            builder_commitment,
+            view,
+            signed: transactions.iter().map(|_| OnceLock::new()).collect(),
+            transactions,
+        });
+        for transaction in &block.transactions {
+            self.blocks.insert(*transaction, Arc::clone(&block));
+        }
+    }
+
+    /// Signed receipt for `transaction`, `None` if it wasn't offered in a recently claimed block.
+    /// Receipts are signed on first request and served from the store afterwards.
+    pub fn receipt(
+        &self,
+        transaction: &Commitment<Types::Transaction>,
+        builder_keys: &BuilderKeys<Types>,
+    ) -> Result<Option<InclusionReceipt<Types>>, Error<Types>> {
+        let Some(block) = self.blocks.get(transaction) else {
+            return Ok(None);
+        };
+        let Some(position) = block
+            .transactions
+            .iter()
+            .position(|commitment| commitment == transaction)
+        else {
+            return Ok(None);
+        };
+        if let Some(receipt) = block.signed[position].get() {
+            return Ok(Some(receipt.clone()));
+        }
+        let Some(proof) = InclusionProof::new::<Types>(&block.transactions, position) else {
+            return Ok(None);
+        };
+        let receipt = ReceiptBody {
+            builder: builder_keys.0.clone(),
+            transaction: *transaction,
+            builder_commitment: block.builder_commitment.clone(),
+            view: block.view,
+            position: position as u64,
+            proof,
+        }
+        .sign(&builder_keys.1)?;
+        // If another request signed it concurrently, either receipt is as good
+        Ok(Some(block.signed[position].get_or_init(|| receipt).clone()))
+    }
+}
+
+/// State serving the inclusion receipt API
+pub trait InclusionReceiptDataSource<Types: NodeType> {
+    /// Signed receipt for `transaction`, `None` if there isn't one
+    fn inclusion_receipt(
+        &self,
+        transaction: &Commitment<Types::Transaction>,
+    ) -> Result<Option<InclusionReceipt<Types>>, Error<Types>>;
+}
+
+/// Define the inclusion receipt API, to be registered in [`INCLUSION_RECEIPT_MODULE`]
+pub fn define_api<State, Types, Error>(
+) -> Result<Api<State, Error, InclusionReceiptApiVersion>, ApiError>
+where
+    Types: NodeType,
+    State: 'static + Send + Sync + ReadState,
+    <State as ReadState>::State: Send + Sync + InclusionReceiptDataSource<Types>,
+    Error: 'static + tide_disco::Error + From<RequestError>,
+{
+    let mut api = Api::new(
+        toml::from_str::<toml::Value>(INCLUSION_RECEIPT_API)
+            .expect("Inclusion receipt API definition should be valid TOML"),
+    )?;
+    api.get("receipt", |req, state| {
+        async move {
+            let commitment: Commitment<Types::Transaction> = req.blob_param("commitment")?;
+            match state.inclusion_receipt(&commitment) {
+                Ok(Some(receipt)) => Ok(receipt),
+                Ok(None) => Err(Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    format!("No receipt for transaction {commitment}"),
+                )),
+                Err(err) => Err(Error::catch_all(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    err.to_string(),
+                )),
+            }
+        }
+        .boxed()
+    })?;
+    Ok(api)
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use committable::Committable;
+    use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+
+    use super::*;
+
+    fn commitment(byte: u8) -> Commitment<TestTransaction> {
+        TestTransaction::new(vec![byte]).commit()
+    }
+
+    fn keys() -> BuilderKeys<TestTypes> {
+        <TestTypes as NodeType>::BuilderSignatureKey::generated_from_seed_indexed([0; 32], 0)
+    }
+
+    #[test]
+    fn test_inclusion_proof() {
+        for num_transactions in 1..=9 {
+            let transactions: Vec<_> = (0..num_transactions).map(commitment).collect();
+            let root = InclusionProof::new::<TestTypes>(&transactions, 0)
+                .unwrap()
+                .root;
+            for (position, transaction) in transactions.iter().enumerate() {
+                let proof = InclusionProof::new::<TestTypes>(&transactions, position).unwrap();
+                assert_eq!(proof.root, root);
+                assert!(proof.verify::<TestTypes>(transaction));
+                assert!(!proof.verify::<TestTypes>(&commitment(u8::MAX)));
+            }
+            assert!(InclusionProof::new::<TestTypes>(&transactions, transactions.len()).is_none());
+        }
+    }
+
+    #[test]
+    fn test_receipt() {
+        let keys = keys();
+        let store = ReceiptStore::<TestTypes>::new(16);
+        let block = BuilderCommitment::from_bytes([1]);
+        store.record_claimed(
+            5,
+            block.clone(),
+            vec![commitment(1), commitment(2), commitment(3)],
+        );
+
+        assert!(store.receipt(&commitment(4), &keys).unwrap().is_none());
+
+        let receipt = store.receipt(&commitment(2), &keys).unwrap().unwrap();
+        assert!(receipt.verify());
+        assert_eq!(receipt.body.builder_commitment, block);
+        assert_eq!(receipt.body.view, 5);
+        assert_eq!(receipt.body.position, 1);
+
+        // Receipt is signed once and served from the store afterwards
+        assert_eq!(
+            store.receipt(&commitment(2), &keys).unwrap().unwrap(),
+            receipt
+        );
+
+        // Signature is domain-separated from other builder messages
+        let body_bytes = bincode::serialize(&receipt.body).unwrap();
+        assert!(!keys
+            .0
+            .validate_builder_signature(&receipt.signature, &body_bytes));
+        let undomained_signature =
+            <TestTypes as NodeType>::BuilderSignatureKey::sign_builder_message(
+                &keys.1,
+                &body_bytes,
+            )
+            .unwrap();
+        assert!(!InclusionReceipt {
+            body: receipt.body.clone(),
+            signature: undomained_signature,
+        }
+        .verify());
+
+        // Tampering with the receipt invalidates the signature
+        let mut tampered = receipt.clone();
+        tampered.body.position = 0;
+        assert!(!tampered.verify());
+
+        // Transaction offered again in a later claimed block
+        store.record_claimed(6, BuilderCommitment::from_bytes([2]), vec![commitment(2)]);
+        let receipt = store.receipt(&commitment(2), &keys).unwrap().unwrap();
+        assert!(receipt.verify());
+        assert_eq!(receipt.body.view, 6);
+        assert_eq!(receipt.body.position, 0);
+    }
+}
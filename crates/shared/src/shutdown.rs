@@ -0,0 +1,180 @@
+//! Coordination of graceful builder shutdown.
+//!
+//! Shutdown proceeds in phases, see [`ShutdownPhase`]. Once it begins, services stop
+//! accepting new transactions and wait for in-flight requests tracked with
+//! [`Shutdown::track`] to finish. Then they export their pending transactions and
+//! [`Shutdown::stop`] their event loops.
+
+use std::{future::Future, sync::Arc};
+
+use tokio::sync::watch;
+
+/// Phase of builder shutdown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownPhase {
+    /// Builder is serving requests as usual
+    #[default]
+    Running,
+    /// New transactions are rejected, in-flight requests are allowed to finish
+    Draining,
+    /// Event loop is stopped
+    Stopped,
+}
+
+/// Handle coordinating graceful shutdown of a builder service. Clones refer to the same shutdown.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    phase: Arc<watch::Sender<ShutdownPhase>>,
+    in_flight: Arc<watch::Sender<usize>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    /// Create a handle for a running service
+    pub fn new() -> Self {
+        Self {
+            phase: Arc::new(watch::Sender::new(ShutdownPhase::Running)),
+            in_flight: Arc::new(watch::Sender::new(0)),
+        }
+    }
+
+    /// Current phase of shutdown
+    pub fn phase(&self) -> ShutdownPhase {
+        *self.phase.borrow()
+    }
+
+    /// Whether shutdown has begun, in which case new transactions should be rejected
+    pub fn is_shutting_down(&self) -> bool {
+        self.phase() != ShutdownPhase::Running
+    }
+
+    /// Number of tracked requests currently in flight
+    pub fn in_flight(&self) -> usize {
+        *self.in_flight.borrow()
+    }
+
+    /// Track an in-flight request until the returned guard is dropped.
+    /// [`Self::drained`] won't resolve while there are tracked requests.
+    pub fn track(&self) -> InFlightGuard {
+        self.in_flight.send_modify(|count| *count += 1);
+        InFlightGuard {
+            in_flight: Arc::clone(&self.in_flight),
+        }
+    }
+
+    /// Enter [`ShutdownPhase::Draining`], unless shutdown has already begun
+    pub fn begin(&self) {
+        self.advance(ShutdownPhase::Draining);
+    }
+
+    /// Enter [`ShutdownPhase::Stopped`], stopping event loops
+    pub fn stop(&self) {
+        self.advance(ShutdownPhase::Stopped);
+    }
+
+    /// Wait until there are no tracked requests in flight
+    pub async fn drained(&self) {
+        wait_until(self.in_flight.subscribe(), |count| *count == 0).await
+    }
+
+    /// Wait until shutdown reaches `phase`
+    pub fn reached(&self, phase: ShutdownPhase) -> impl Future<Output = ()> + Send + 'static {
+        wait_until(self.phase.subscribe(), move |current| *current >= phase)
+    }
+
+    fn advance(&self, phase: ShutdownPhase) {
+        self.phase.send_if_modified(|current| {
+            if *current < phase {
+                *current = phase;
+                true
+            } else {
+                false
+            }
+        });
+    }
+}
+
+/// Wait until the watched value satisfies `condition`. Returns early if the sender is gone,
+/// which only happens once every handle to the shutdown is dropped.
+async fn wait_until<T>(mut receiver: watch::Receiver<T>, condition: impl Fn(&T) -> bool) {
+    loop {
+        let satisfied = condition(&receiver.borrow_and_update());
+        if satisfied || receiver.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Marks a request as in flight until dropped, see [`Shutdown::track`]
+#[derive(Debug)]
+pub struct InFlightGuard {
+    in_flight: Arc<watch::Sender<usize>>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.send_modify(|count| *count -= 1);
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    #[tokio::test]
+    async fn test_shutdown_phases() {
+        let shutdown = Shutdown::new();
+        assert_eq!(shutdown.phase(), ShutdownPhase::Running);
+        assert!(!shutdown.is_shutting_down());
+
+        let stopped = tokio::spawn(shutdown.reached(ShutdownPhase::Stopped));
+
+        shutdown.begin();
+        assert!(shutdown.is_shutting_down());
+        timeout(TIMEOUT, shutdown.reached(ShutdownPhase::Draining))
+            .await
+            .unwrap();
+        assert!(!stopped.is_finished());
+
+        shutdown.stop();
+        timeout(TIMEOUT, stopped).await.unwrap().unwrap();
+
+        // Shutdown can't go back
+        shutdown.begin();
+        assert_eq!(shutdown.phase(), ShutdownPhase::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        let shutdown = Shutdown::new();
+        timeout(TIMEOUT, shutdown.drained()).await.unwrap();
+
+        let first = shutdown.track();
+        let second = shutdown.clone().track();
+        assert_eq!(shutdown.in_flight(), 2);
+
+        let drained = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.drained().await }
+        });
+        drop(first);
+        tokio::time::sleep(TIMEOUT).await;
+        assert!(!drained.is_finished());
+
+        drop(second);
+        timeout(TIMEOUT, drained).await.unwrap().unwrap();
+        assert_eq!(shutdown.in_flight(), 0);
+    }
+}
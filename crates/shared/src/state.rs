@@ -17,6 +17,17 @@ use hotshot_types::{
     traits::{block_contents::BlockHeader, node_implementation::NodeType},
 };
 
+/// Snapshot of [`TransactionQueue`] statistics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStatistics {
+    /// Number of transactions in the queue
+    pub len: usize,
+    /// Total estimated size of queued transactions, in bytes
+    pub total_bytes: u64,
+    /// Time the oldest queued transaction has spent waiting
+    pub oldest_wait: Option<Duration>,
+}
+
 #[derive(derive_more::Debug, Clone)]
 pub struct TransactionQueue<Types>
 where
@@ -78,6 +89,10 @@ where
         Some(transaction)
     }
 
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.commits.is_empty()
     }
@@ -85,6 +100,18 @@ where
     pub fn iter(&self) -> impl Iterator<Item = &Arc<ReceivedTransaction<Types>>> {
         self.transactions.iter()
     }
+
+    pub fn statistics(&self) -> QueueStatistics {
+        QueueStatistics {
+            len: self.transactions.len(),
+            total_bytes: self.transactions.iter().map(|txn| txn.min_block_size).sum(),
+            oldest_wait: self
+                .transactions
+                .iter()
+                .map(|txn| txn.time_in.elapsed())
+                .max(),
+        }
+    }
 }
 
 #[derive(derive_more::Debug)]
@@ -192,6 +219,41 @@ where
         })
     }
 
+    /// Statistics of this builder state's transaction queue
+    pub async fn queue_statistics(&self) -> QueueStatistics {
+        self.txn_queue.read().await.statistics()
+    }
+
+    /// Number of transactions in this builder state's queue plus ones waiting
+    /// in the channel to be collected, without collecting them.
+    /// May overcount, as transactions in the channel aren't checked against included ones.
+    pub async fn pending_len(&self) -> usize {
+        self.txn_queue.read().await.len() + self.txn_receiver.lock().await.len()
+    }
+
+    /// Transactions in this builder state's queue followed by ones waiting in the channel,
+    /// in the order they were received. Unlike [`Self::collect_txns`], reads the channel
+    /// through a clone of the receiver, leaving both the queue and the channel untouched.
+    pub async fn pending_txns(&self) -> Vec<Arc<ReceivedTransaction<Types>>> {
+        let mut pending = self.txn_queue.read().await.clone();
+        let mut receiver = self.txn_receiver.lock().await.clone();
+        loop {
+            match receiver.try_recv() {
+                Ok(txn) => {
+                    if !self.included_txns.contains(&txn.commit) {
+                        pending.insert(txn);
+                    }
+                }
+                Err(async_broadcast::TryRecvError::Empty)
+                | Err(async_broadcast::TryRecvError::Closed) => break,
+                Err(async_broadcast::TryRecvError::Overflowed(lost)) => {
+                    tracing::warn!("Missed {lost} transactions due to backlog");
+                }
+            }
+        }
+        pending.iter().cloned().collect()
+    }
+
     // collect outstanding transactions
     pub async fn collect_txns(&self, timeout_after: Instant) -> bool {
         let mut queue_empty = self.txn_queue.read().await.is_empty();
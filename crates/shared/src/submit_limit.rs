@@ -0,0 +1,543 @@
+//! Limits on submissions to the private mempool.
+//!
+//! [`SubmitLimiter`] enforces [`SubmitLimits`] for every client separately, tracking
+//! transactions and bytes submitted with the same token buckets as
+//! [`RateLimiter`](crate::rate_limit::RateLimiter). Clients are identified by an arbitrary
+//! string, such as a remote address or an API key.
+//!
+//! Builders serve the private mempool through the API defined by [`define_api`], which has
+//! the same routes as HotShot's `txn_submit` API, but identifies clients by their IP address,
+//! see [`remote_client`], and fails with a status telling why a submission was rejected,
+//! e.g. `429 Too Many Requests` with the time after which to retry in a `Retry-After` header
+//! if served with [`RetryAfterListener`](crate::utils::RetryAfterListener).
+//! It also reports [`SubmitLimiterMetrics`].
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use futures::FutureExt;
+use hotshot_builder_api::v0_1::builder::TransactionStatus;
+use hotshot_types::traits::node_implementation::NodeType;
+use serde::{Deserialize, Serialize};
+use tide_disco::{api::ApiError, Api, RequestError};
+use vbs::version::StaticVersion;
+
+use crate::{
+    error::Error,
+    rate_limit::{Bucket, RateLimit},
+};
+
+/// Identity shared by all clients submitting without one
+pub const ANONYMOUS_CLIENT: &str = "anonymous";
+
+/// Name of the API module serving the private mempool
+pub const PRIVATE_MEMPOOL_MODULE: &str = "txn_submit";
+
+/// Version of the private mempool API
+pub type PrivateMempoolApiVersion = StaticVersion<0, 1>;
+
+/// Definition of the private mempool API. Routes other than `limiter_metrics` are HotShot's
+/// `txn_submit` routes: HotShot's handlers can't be replaced once its API is loaded, and
+/// API extensions replace its `route` table instead of adding to it, so they're declared here.
+const PRIVATE_MEMPOOL_API: &str = r#"
+[route.submit_txn]
+PATH = ["/submit"]
+METHOD = "POST"
+DOC = "Submit a transaction to the private mempool, returning its commitment"
+
+[route.submit_batch]
+PATH = ["/batch"]
+METHOD = "POST"
+DOC = "Submit a batch of transactions to the private mempool, returning their commitments"
+
+[route.get_status]
+PATH = ["status/:transaction_hash"]
+":transaction_hash" = "TaggedBase64"
+METHOD = "GET"
+DOC = "Get the status of transaction `transaction_hash`"
+
+[route.limiter_metrics]
+PATH = ["limiter"]
+METHOD = "GET"
+DOC = "Get the number of tracked clients and counts of accepted and rejected submissions"
+"#;
+
+/// Number of clients tracked by default before idle ones are forgotten
+pub const DEFAULT_MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Limits on private mempool submissions. Every limit is optional, the default is no limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubmitLimits {
+    /// Limit on transactions submitted by a single client, each transaction counting as a request
+    pub transactions: Option<RateLimit>,
+    /// Limit on bytes submitted by a single client, each byte counting as a request
+    pub bytes: Option<RateLimit>,
+    /// Maximum number of transactions in a single submission
+    pub max_batch_size: Option<usize>,
+    /// Maximum total size of transactions in a single submission, in bytes
+    pub max_body_size: Option<u64>,
+    /// Number of clients tracked before ones with full allowance are forgotten.
+    /// Active clients are never forgotten, so this isn't a hard limit.
+    pub max_tracked_clients: usize,
+}
+
+impl Default for SubmitLimits {
+    fn default() -> Self {
+        Self {
+            transactions: None,
+            bytes: None,
+            max_batch_size: None,
+            max_body_size: None,
+            max_tracked_clients: DEFAULT_MAX_TRACKED_CLIENTS,
+        }
+    }
+}
+
+/// Snapshot of [`SubmitLimiter`] state
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmitLimiterMetrics {
+    /// Number of clients currently tracked
+    pub tracked_clients: usize,
+    /// Total number of submissions accepted
+    pub accepted: u64,
+    /// Total number of submissions rejected for exceeding transaction or byte rate
+    pub rate_limited: u64,
+    /// Total number of submissions rejected for exceeding batch or body size
+    pub oversized: u64,
+}
+
+/// Allowance of a single client, `None` for unlimited resources
+#[derive(Debug)]
+struct ClientBuckets {
+    transactions: Option<Bucket>,
+    bytes: Option<Bucket>,
+}
+
+/// Enforces [`SubmitLimits`], see [module documentation](self)
+#[derive(Debug)]
+pub struct SubmitLimiter {
+    limits: SubmitLimits,
+    clients: Mutex<HashMap<String, ClientBuckets>>,
+    accepted: AtomicU64,
+    rate_limited: AtomicU64,
+    oversized: AtomicU64,
+}
+
+impl Default for SubmitLimiter {
+    fn default() -> Self {
+        Self::new(SubmitLimits::default())
+    }
+}
+
+impl SubmitLimiter {
+    /// Create a new limiter enforcing `limits`
+    pub fn new(mut limits: SubmitLimits) -> Self {
+        // Treat unlimited rates the same as unset ones, so that no buckets are kept for them
+        limits.transactions = limits.transactions.filter(|limit| !limit.is_unlimited());
+        limits.bytes = limits.bytes.filter(|limit| !limit.is_unlimited());
+        Self {
+            limits,
+            clients: Mutex::new(HashMap::new()),
+            accepted: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            oversized: AtomicU64::new(0),
+        }
+    }
+
+    /// Limits enforced by this limiter
+    pub fn limits(&self) -> SubmitLimits {
+        self.limits
+    }
+
+    /// Account for a submission of `num_transactions` transactions of `num_bytes` bytes in total
+    /// by `client`. Fails with [`Error::BatchTooLarge`] or [`Error::BodyTooLarge`] if the submission
+    /// is too big, including if it exceeds the burst of a rate limit and so could never be
+    /// allowed, or with [`Error::RateLimited`] if it exceeds the client's allowance.
+    /// Rejected submissions don't use up any allowance.
+    pub fn check<Types: NodeType>(
+        &self,
+        client: &str,
+        num_transactions: usize,
+        num_bytes: u64,
+    ) -> Result<(), Error<Types>> {
+        let result = self.check_sizes(num_transactions, num_bytes).and_then(|_| {
+            self.check_rates(client, num_transactions, num_bytes)
+                .map_err(|retry_after| Error::RateLimited { retry_after })
+        });
+        let counter = match &result {
+            Ok(()) => &self.accepted,
+            Err(Error::RateLimited { .. }) => &self.rate_limited,
+            Err(_) => &self.oversized,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    /// Current state of the limiter
+    pub fn metrics(&self) -> SubmitLimiterMetrics {
+        SubmitLimiterMetrics {
+            tracked_clients: self.lock_clients().len(),
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            oversized: self.oversized.load(Ordering::Relaxed),
+        }
+    }
+
+    fn check_sizes<Types: NodeType>(
+        &self,
+        num_transactions: usize,
+        num_bytes: u64,
+    ) -> Result<(), Error<Types>> {
+        // Submissions costing more than a full bucket would never be allowed
+        let max_batch_size = [
+            self.limits.max_batch_size,
+            self.limits.transactions.map(|limit| limit.burst as usize),
+        ];
+        let max_body_size = [
+            self.limits.max_body_size,
+            self.limits.bytes.map(|limit| limit.burst.into()),
+        ];
+        if let Some(max) = max_batch_size.into_iter().flatten().min() {
+            if num_transactions > max {
+                return Err(Error::BatchTooLarge {
+                    len: num_transactions,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = max_body_size.into_iter().flatten().min() {
+            if num_bytes > max {
+                return Err(Error::BodyTooLarge {
+                    len: num_bytes,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_rates(
+        &self,
+        client: &str,
+        num_transactions: usize,
+        num_bytes: u64,
+    ) -> Result<(), Duration> {
+        if self.limits.transactions.is_none() && self.limits.bytes.is_none() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut clients = self.lock_clients();
+        if !clients.contains_key(client) && clients.len() >= self.limits.max_tracked_clients {
+            self.forget_idle(&mut clients, now);
+        }
+        let buckets = clients
+            .entry(client.to_owned())
+            .or_insert_with(|| ClientBuckets {
+                transactions: self
+                    .limits
+                    .transactions
+                    .map(|limit| Bucket::full(&limit, now)),
+                bytes: self.limits.bytes.map(|limit| Bucket::full(&limit, now)),
+            });
+
+        let mut checks = [
+            (
+                self.limits.transactions,
+                buckets.transactions.as_mut(),
+                u32::try_from(num_transactions).unwrap_or(u32::MAX),
+            ),
+            (
+                self.limits.bytes,
+                buckets.bytes.as_mut(),
+                u32::try_from(num_bytes).unwrap_or(u32::MAX),
+            ),
+        ];
+
+        let mut retry_after = Duration::ZERO;
+        for (limit, bucket, cost) in &mut checks {
+            if let (Some(limit), Some(bucket)) = (limit, bucket) {
+                bucket.replenish(limit, now);
+                retry_after = retry_after.max(bucket.wait_for(limit, *cost, now));
+            }
+        }
+        if !retry_after.is_zero() {
+            return Err(retry_after);
+        }
+
+        for (_, bucket, cost) in checks {
+            if let Some(bucket) = bucket {
+                bucket.take(cost);
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget clients that haven't submitted anything lately
+    fn forget_idle(&self, clients: &mut HashMap<String, ClientBuckets>, now: Instant) {
+        let is_idle = |limit: Option<RateLimit>, bucket: &mut Option<Bucket>| match (limit, bucket)
+        {
+            (Some(limit), Some(bucket)) => {
+                bucket.replenish(&limit, now);
+                bucket.is_full(&limit)
+            }
+            _ => true,
+        };
+        clients.retain(|_, buckets| {
+            !(is_idle(self.limits.transactions, &mut buckets.transactions)
+                && is_idle(self.limits.bytes, &mut buckets.bytes))
+        });
+    }
+
+    fn lock_clients(&self) -> MutexGuard<'_, HashMap<String, ClientBuckets>> {
+        // Poisoning isn't a concern, as buckets are always left in a consistent state
+        self.clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Identity of an API client connecting from `remote`, as reported by
+/// [`RequestParams::remote`](tide_disco::RequestParams::remote). Clients are identified
+/// by IP address alone, so that they don't get fresh allowance with every connection.
+/// Unknown addresses are attributed to [`ANONYMOUS_CLIENT`].
+pub fn remote_client(remote: Option<&str>) -> String {
+    match remote {
+        Some(remote) => remote
+            .parse::<SocketAddr>()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|_| remote.to_owned()),
+        None => ANONYMOUS_CLIENT.to_owned(),
+    }
+}
+
+/// State serving the private mempool API
+#[async_trait]
+pub trait PrivateMempoolDataSource<Types: NodeType> {
+    /// Submit `txns` to the private mempool on behalf of `client`, enforcing [`SubmitLimits`].
+    /// Batches are accepted or rejected as a whole.
+    async fn submit_private(
+        &self,
+        client: &str,
+        txns: Vec<Types::Transaction>,
+    ) -> Result<Vec<Commitment<Types::Transaction>>, Error<Types>>;
+
+    /// Status of transaction `txn_hash`
+    fn transaction_status(&self, txn_hash: &Commitment<Types::Transaction>) -> TransactionStatus;
+
+    /// Current state of the submission limiter
+    fn submit_limiter_metrics(&self) -> SubmitLimiterMetrics;
+}
+
+/// Define the private mempool API, to be registered in [`PRIVATE_MEMPOOL_MODULE`]
+pub fn define_api<State, Types, Error>(
+) -> Result<Api<State, Error, PrivateMempoolApiVersion>, ApiError>
+where
+    Types: NodeType,
+    State: 'static + Send + Sync + PrivateMempoolDataSource<Types>,
+    Error: 'static + tide_disco::Error + From<RequestError>,
+{
+    let mut api = Api::new(
+        toml::from_str::<toml::Value>(PRIVATE_MEMPOOL_API)
+            .expect("Private mempool API definition should be valid TOML"),
+    )?;
+    api.at("submit_txn", |req, state| {
+        async move {
+            let txn = req.body_auto::<Types::Transaction, PrivateMempoolApiVersion>(
+                PrivateMempoolApiVersion {},
+            )?;
+            let commitment = txn.commit();
+            state
+                .submit_private(&remote_client(req.remote()), vec![txn])
+                .await
+                .map_err(|err| err.into_api_error::<Error>())?;
+            Ok(commitment)
+        }
+        .boxed()
+    })?
+    .at("submit_batch", |req, state| {
+        async move {
+            let txns = req.body_auto::<Vec<Types::Transaction>, PrivateMempoolApiVersion>(
+                PrivateMempoolApiVersion {},
+            )?;
+            state
+                .submit_private(&remote_client(req.remote()), txns)
+                .await
+                .map_err(|err| err.into_api_error::<Error>())
+        }
+        .boxed()
+    })?
+    .at("get_status", |req, state| {
+        async move {
+            let txn_hash: Commitment<Types::Transaction> = req.blob_param("transaction_hash")?;
+            Ok(state.transaction_status(&txn_hash))
+        }
+        .boxed()
+    })?
+    .at("limiter_metrics", |_req, state| {
+        async move { Ok(state.submit_limiter_metrics()) }.boxed()
+    })?;
+    Ok(api)
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use hotshot_example_types::node_types::TestTypes;
+
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(100);
+
+    fn check(
+        limiter: &SubmitLimiter,
+        client: &str,
+        txns: usize,
+        bytes: u64,
+    ) -> Result<(), Error<TestTypes>> {
+        limiter.check(client, txns, bytes)
+    }
+
+    #[test]
+    fn test_sizes() {
+        let limiter = SubmitLimiter::new(SubmitLimits {
+            max_batch_size: Some(10),
+            max_body_size: Some(1000),
+            ..Default::default()
+        });
+
+        check(&limiter, ANONYMOUS_CLIENT, 10, 1000).unwrap();
+        assert!(matches!(
+            check(&limiter, ANONYMOUS_CLIENT, 11, 100),
+            Err(Error::BatchTooLarge { len: 11, max: 10 })
+        ));
+        assert!(matches!(
+            check(&limiter, ANONYMOUS_CLIENT, 1, 1001),
+            Err(Error::BodyTooLarge {
+                len: 1001,
+                max: 1000
+            })
+        ));
+
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.accepted, 1);
+        assert_eq!(metrics.oversized, 2);
+        // No rate limits, so no clients need to be tracked
+        assert_eq!(metrics.tracked_clients, 0);
+    }
+
+    #[test]
+    fn test_rates_per_client() {
+        let limiter = SubmitLimiter::new(SubmitLimits {
+            transactions: Some(RateLimit {
+                burst: 10,
+                replenish_period: PERIOD,
+            }),
+            bytes: Some(RateLimit {
+                burst: 100,
+                replenish_period: PERIOD / 10,
+            }),
+            ..Default::default()
+        });
+
+        check(&limiter, "alice", 5, 50).unwrap();
+        check(&limiter, "alice", 5, 10).unwrap();
+        let Err(Error::RateLimited { retry_after }) = check(&limiter, "alice", 1, 1) else {
+            panic!("Transaction rate should be exceeded");
+        };
+        assert!(retry_after <= PERIOD);
+
+        // Other clients have their own allowance
+        check(&limiter, "bob", 1, 100).unwrap();
+        let Err(Error::RateLimited { retry_after }) = check(&limiter, "bob", 1, 10) else {
+            panic!("Byte rate should be exceeded");
+        };
+        assert!(retry_after <= PERIOD);
+
+        // Rejected submission didn't use up transaction allowance
+        std::thread::sleep(PERIOD);
+        check(&limiter, "bob", 9, 10).unwrap();
+
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.tracked_clients, 2);
+        assert_eq!(metrics.accepted, 4);
+        assert_eq!(metrics.rate_limited, 2);
+    }
+
+    #[test]
+    fn test_forget_idle_clients() {
+        let limiter = SubmitLimiter::new(SubmitLimits {
+            transactions: Some(RateLimit {
+                burst: 1,
+                replenish_period: PERIOD,
+            }),
+            max_tracked_clients: 2,
+            ..Default::default()
+        });
+
+        check(&limiter, "alice", 1, 0).unwrap();
+        check(&limiter, "bob", 1, 0).unwrap();
+        // Both are still active, so they're kept
+        check(&limiter, "carol", 1, 0).unwrap();
+        assert_eq!(limiter.metrics().tracked_clients, 3);
+
+        std::thread::sleep(PERIOD);
+        check(&limiter, "dave", 1, 0).unwrap();
+        assert_eq!(limiter.metrics().tracked_clients, 1);
+    }
+
+    #[test]
+    fn test_cost_over_burst() {
+        let limiter = SubmitLimiter::new(SubmitLimits {
+            transactions: Some(RateLimit {
+                burst: 10,
+                replenish_period: PERIOD,
+            }),
+            bytes: Some(RateLimit {
+                burst: 1000,
+                replenish_period: PERIOD,
+            }),
+            ..Default::default()
+        });
+
+        // Bucket is full, but the submission costs more than it could ever hold
+        assert!(matches!(
+            check(&limiter, "alice", 11, 100),
+            Err(Error::BatchTooLarge { len: 11, max: 10 })
+        ));
+        assert!(matches!(
+            check(&limiter, "alice", 1, 1001),
+            Err(Error::BodyTooLarge {
+                len: 1001,
+                max: 1000
+            })
+        ));
+
+        // Allowance wasn't used up
+        check(&limiter, "alice", 10, 1000).unwrap();
+
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.accepted, 1);
+        assert_eq!(metrics.oversized, 2);
+        assert_eq!(metrics.rate_limited, 0);
+    }
+
+    #[test]
+    fn test_remote_client() {
+        assert_eq!(remote_client(Some("10.0.0.1:4242")), "10.0.0.1");
+        assert_eq!(remote_client(Some("10.0.0.1:4343")), "10.0.0.1");
+        assert_eq!(remote_client(Some("[::1]:4242")), "::1");
+        // Forwarded addresses may come without a port
+        assert_eq!(remote_client(Some("10.0.0.2")), "10.0.0.2");
+        assert_eq!(remote_client(None), ANONYMOUS_CLIENT);
+    }
+}
@@ -62,12 +62,6 @@ impl SimulatedChainState {
         let seed = [self.round.u64() as u8; 32];
         let (pub_key, private_key) = BLSPubKey::generated_from_seed_indexed(seed, self.round.u64());
 
-        let quorum_signature =
-            <TestTypes as hotshot_types::traits::node_implementation::NodeType>::SignatureKey::sign(
-                &private_key,
-                block_vid_commitment.as_ref(),
-            )
-            .expect("Failed to sign payload commitment while preparing Quorum proposal");
         let da_signature =
             <TestTypes as hotshot_types::traits::node_implementation::NodeType>::SignatureKey::sign(
                 &private_key,
@@ -127,6 +121,13 @@ impl SimulatedChainState {
             drb_result: INITIAL_DRB_RESULT,
         };
 
+        let quorum_signature =
+            <TestTypes as hotshot_types::traits::node_implementation::NodeType>::SignatureKey::sign(
+                &private_key,
+                Committable::commit(&Leaf2::from_quorum_proposal(&quorum_proposal)).as_ref(),
+            )
+            .expect("Failed to sign leaf commitment while preparing Quorum proposal");
+
         let quorum_proposal_event = EventType::QuorumProposal {
             proposal: Proposal {
                 data: quorum_proposal.clone(),
@@ -0,0 +1,446 @@
+//! Recording and replaying of HotShot event streams.
+//!
+//! Events from any stream can be recorded to a file with [`record_events`] and later
+//! fed into the builder's event loop with [`replay_events`], which makes it possible
+//! to reproduce the builder's behaviour offline.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, TrySendError},
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures::{stream::unfold, Stream, StreamExt};
+use hotshot::types::Event;
+use hotshot_types::traits::node_implementation::NodeType;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, time::sleep};
+use tracing::{error, warn};
+
+/// Default number of events [`EventRecorder`] queues for writing before dropping new ones
+pub const DEFAULT_RECORDING_CAPACITY: usize = 1024;
+
+/// Format of the file events are recorded to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// Sequence of bincode-encoded [`RecordedEvent`]s
+    Bincode,
+    /// One JSON-encoded [`RecordedEvent`] per line
+    JsonLines,
+}
+
+/// A HotShot event along with the time it was received at
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "Types: NodeType")]
+pub struct RecordedEvent<Types: NodeType> {
+    /// Time the event was received at
+    pub timestamp: DateTime<Utc>,
+    /// The event itself
+    pub event: Event<Types>,
+}
+
+/// Speed at which recorded events are replayed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplaySpeed {
+    /// Preserve original intervals between events
+    Original,
+    /// Divide original intervals between events by given factor
+    Accelerated(f64),
+    /// Replay events as fast as they're consumed
+    Unthrottled,
+}
+
+impl ReplaySpeed {
+    /// Scale an interval between two recorded events
+    fn scale(self, interval: Duration) -> Duration {
+        match self {
+            ReplaySpeed::Original => interval,
+            ReplaySpeed::Accelerated(factor) if factor > 0.0 => {
+                Duration::try_from_secs_f64(interval.as_secs_f64() / factor).unwrap_or(interval)
+            }
+            ReplaySpeed::Accelerated(_) | ReplaySpeed::Unthrottled => Duration::ZERO,
+        }
+    }
+}
+
+/// Writes events to a file on a dedicated thread, so that the caller
+/// isn't slowed down by disk I/O. Should writing fail, the error is logged
+/// and recording stops.
+///
+/// Up to a fixed number of events are queued for writing; if the disk can't keep up,
+/// new events are dropped with a warning rather than buffered without bound.
+/// Dropping the recorder doesn't wait for queued events to be written, they are
+/// written in the background. Use [`Self::finish`] to wait for that.
+#[derive(Debug)]
+pub struct EventRecorder<Types: NodeType> {
+    sender: mpsc::SyncSender<RecordedEvent<Types>>,
+    /// Resolves once the writer thread exits
+    done: oneshot::Receiver<()>,
+    dropped: AtomicU64,
+}
+
+impl<Types: NodeType> EventRecorder<Types> {
+    /// Start recording events to a file at `path`, queueing up to
+    /// [`DEFAULT_RECORDING_CAPACITY`] events. The file is truncated if it exists.
+    pub fn new(path: impl AsRef<Path>, format: RecordingFormat) -> io::Result<Self> {
+        Self::with_capacity(path, format, DEFAULT_RECORDING_CAPACITY)
+    }
+
+    /// Start recording events to a file at `path`, queueing up to `capacity` events.
+    /// The file is truncated if it exists.
+    pub fn with_capacity(
+        path: impl AsRef<Path>,
+        format: RecordingFormat,
+        capacity: usize,
+    ) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let (sender, receiver) = mpsc::sync_channel::<RecordedEvent<Types>>(capacity);
+        let (done_sender, done) = oneshot::channel();
+
+        std::thread::spawn(move || {
+            if let Err(err) = write_events(&mut writer, &receiver, format) {
+                error!(?err, "Failed to record event, recording stopped");
+            }
+            let _ = done_sender.send(());
+        });
+
+        Ok(Self {
+            sender,
+            done,
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// Record `event`, timestamped with current time
+    pub fn record(&self, event: &Event<Types>) {
+        let recorded_event = RecordedEvent {
+            timestamp: Utc::now(),
+            event: event.clone(),
+        };
+        match self.sender.try_send(recorded_event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                warn!(dropped, "Event recording queue is full, dropping event");
+            }
+            // If the writer thread has stopped, the error was already logged
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    /// Number of events dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stop recording and wait for queued events to be written
+    pub async fn finish(self) {
+        // Closing the channel makes the writer thread exit once it has written queued events
+        drop(self.sender);
+        if self.done.await.is_err() {
+            error!("Event recording thread panicked");
+        }
+    }
+}
+
+/// Wrap `stream`, writing every event it yields to a file at `path`.
+///
+/// The file is truncated if it exists. See [`EventRecorder`] for details on
+/// how events are written. Once the returned stream is dropped, events it
+/// yielded are still written in the background.
+pub fn record_events<Types, S>(
+    stream: S,
+    path: impl AsRef<Path>,
+    format: RecordingFormat,
+) -> io::Result<impl Stream<Item = Event<Types>> + Unpin>
+where
+    Types: NodeType,
+    S: Stream<Item = Event<Types>> + Unpin,
+{
+    let recorder = EventRecorder::new(path, format)?;
+    Ok(stream.inspect(move |event| recorder.record(event)))
+}
+
+/// Write events from `receiver` until it's closed
+fn write_events<Types: NodeType>(
+    writer: &mut impl Write,
+    receiver: &mpsc::Receiver<RecordedEvent<Types>>,
+    format: RecordingFormat,
+) -> anyhow::Result<()> {
+    while let Ok(recorded_event) = receiver.recv() {
+        write_event(writer, &recorded_event, format)?;
+        while let Ok(recorded_event) = receiver.try_recv() {
+            write_event(writer, &recorded_event, format)?;
+        }
+        // Flush whenever we've caught up, so that the recording is usable even if
+        // the builder crashes, without paying for a flush per event under load
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn write_event<Types: NodeType>(
+    writer: &mut impl Write,
+    recorded_event: &RecordedEvent<Types>,
+    format: RecordingFormat,
+) -> anyhow::Result<()> {
+    match format {
+        RecordingFormat::Bincode => bincode::serialize_into(&mut *writer, recorded_event)?,
+        RecordingFormat::JsonLines => {
+            serde_json::to_writer(&mut *writer, recorded_event)?;
+            writer.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Read events recorded to file at `path` by [`record_events`].
+///
+/// Events are read lazily as the returned iterator is advanced. It ends after
+/// yielding the first error, as the rest of the recording can't be trusted.
+pub fn read_recording<Types: NodeType>(
+    path: impl AsRef<Path>,
+    format: RecordingFormat,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<RecordedEvent<Types>>> + Send> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut failed = false;
+
+    Ok(std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let event = read_event(&mut reader, format).transpose();
+        failed = matches!(event, Some(Err(_)));
+        event
+    }))
+}
+
+/// Read next event from `reader`, `None` at the end of the recording
+fn read_event<Types: NodeType>(
+    reader: &mut impl BufRead,
+    format: RecordingFormat,
+) -> anyhow::Result<Option<RecordedEvent<Types>>> {
+    match format {
+        RecordingFormat::Bincode => {
+            if reader.fill_buf()?.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(bincode::deserialize_from(reader)?))
+        }
+        RecordingFormat::JsonLines => {
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                if !line.trim().is_empty() {
+                    return Ok(Some(serde_json::from_str(&line)?));
+                }
+            }
+        }
+    }
+}
+
+/// Replay recorded events at given `speed`. The first event is yielded immediately,
+/// every next one after the interval between the original events (scaled according
+/// to `speed`) has elapsed.
+///
+/// The resulting stream can be passed to `start_event_loop` of either builder service.
+pub fn replay_events<Types, I>(
+    events: I,
+    speed: ReplaySpeed,
+) -> impl Stream<Item = Event<Types>> + Unpin
+where
+    Types: NodeType,
+    I: IntoIterator<Item = RecordedEvent<Types>>,
+{
+    let state = (events.into_iter(), None::<DateTime<Utc>>);
+    Box::pin(unfold(state, move |(mut events, previous)| async move {
+        let recorded_event = events.next()?;
+
+        if let Some(previous) = previous {
+            let interval = (recorded_event.timestamp - previous)
+                .to_std()
+                .unwrap_or_default();
+            let interval = speed.scale(interval);
+            if !interval.is_zero() {
+                sleep(interval).await;
+            }
+        }
+
+        Some((
+            recorded_event.event,
+            (events, Some(recorded_event.timestamp)),
+        ))
+    }))
+}
+
+/// Convenience function combining [`read_recording`] and [`replay_events`].
+/// Replay ends early, with the error logged, if the recording turns out to be corrupted.
+pub fn replay_recording<Types: NodeType>(
+    path: impl AsRef<Path>,
+    format: RecordingFormat,
+    speed: ReplaySpeed,
+) -> anyhow::Result<impl Stream<Item = Event<Types>> + Unpin> {
+    let events = read_recording(path, format)?.map_while(|event| {
+        event
+            .inspect_err(|err| error!(?err, "Failed to read recorded event, replay stopped"))
+            .ok()
+    });
+    Ok(replay_events(events, speed))
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use futures::stream;
+    use hotshot::types::EventType;
+    use hotshot_example_types::node_types::TestTypes;
+    use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+    use rand::{distributions::Alphanumeric, thread_rng, Rng};
+    use tracing_test::traced_test;
+
+    use super::*;
+
+    fn mock_event(view: u64) -> Event<TestTypes> {
+        let view_number = ViewNumber::new(view);
+        Event {
+            view_number,
+            event: EventType::ViewFinished { view_number },
+        }
+    }
+
+    fn temp_path() -> std::path::PathBuf {
+        let name: String = thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+        std::env::temp_dir().join(format!("event-recording-{name}"))
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_record_and_read() {
+        const NUM_EVENTS: u64 = 10;
+
+        for format in [RecordingFormat::Bincode, RecordingFormat::JsonLines] {
+            let path = temp_path();
+
+            let recorder = EventRecorder::new(&path, format).unwrap();
+            for view in 0..NUM_EVENTS {
+                recorder.record(&mock_event(view));
+            }
+            assert_eq!(recorder.dropped(), 0);
+            recorder.finish().await;
+
+            let events = read_recording::<TestTypes>(&path, format)
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(
+                events
+                    .iter()
+                    .map(|recorded| *recorded.event.view_number)
+                    .collect::<Vec<_>>(),
+                (0..NUM_EVENTS).collect::<Vec<_>>(),
+                "{format:?}: events should be read back in order"
+            );
+            assert!(events
+                .windows(2)
+                .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_record_events() {
+        const NUM_EVENTS: u64 = 10;
+
+        let path = temp_path();
+        let format = RecordingFormat::JsonLines;
+
+        let recorded = record_events(stream::iter((0..NUM_EVENTS).map(mock_event)), &path, format)
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(recorded.len() as u64, NUM_EVENTS);
+
+        // Dropping the stream doesn't block, events are written in the background
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            let events = read_recording::<TestTypes>(&path, format)
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>();
+            if events.is_ok_and(|events| events.len() as u64 == NUM_EVENTS) {
+                break;
+            }
+            assert!(Instant::now() < deadline, "Events should be written");
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_corrupted_recording() {
+        let path = temp_path();
+        std::fs::write(&path, "not an event\n").unwrap();
+
+        let mut events = read_recording::<TestTypes>(&path, RecordingFormat::JsonLines).unwrap();
+        assert!(events.next().unwrap().is_err());
+        assert!(
+            events.next().is_none(),
+            "Reading should stop after an error"
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_replay_speed() {
+        const INTERVAL: Duration = Duration::from_millis(200);
+
+        let start = Utc::now();
+        let events = (0..3u32)
+            .map(|i| RecordedEvent {
+                timestamp: start + chrono::Duration::from_std(INTERVAL * i).unwrap(),
+                event: mock_event(i.into()),
+            })
+            .collect::<Vec<_>>();
+
+        let started = Instant::now();
+        let replayed = replay_events(events.clone(), ReplaySpeed::Original)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(replayed.len(), 3);
+        assert!(started.elapsed() >= INTERVAL * 2);
+
+        let started = Instant::now();
+        replay_events(events.clone(), ReplaySpeed::Accelerated(4.0))
+            .collect::<Vec<_>>()
+            .await;
+        assert!(started.elapsed() >= INTERVAL / 2);
+        assert!(started.elapsed() < INTERVAL * 2);
+
+        let started = Instant::now();
+        let replayed = replay_events(events, ReplaySpeed::Unthrottled)
+            .map(|event| *event.view_number)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(replayed, vec![0, 1, 2]);
+        assert!(started.elapsed() < INTERVAL);
+    }
+}
@@ -1,14 +1,23 @@
-use std::{future::Future, pin::Pin};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    future::Future,
+    hash::{DefaultHasher, Hash, Hasher},
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
 use std::time::Duration;
 
 use anyhow::Context;
+use committable::Committable;
 use either::Either::{self, Left, Right};
-use futures::stream::unfold;
+use futures::{future::join_all, stream::unfold};
 use futures::{Stream, StreamExt};
-use hotshot::types::Event;
+use hotshot::types::{Event, EventType};
 use hotshot_events_service::events::Error as EventStreamError;
 use hotshot_types::traits::node_implementation::NodeType;
+use rand::{thread_rng, Rng};
 use surf_disco::client::HealthStatus;
 use surf_disco::Client;
 use tokio::time::{sleep, timeout};
@@ -23,72 +32,540 @@ type EventServiceConnection<Types, ApiVer> = surf_disco::socket::Connection<
     ApiVer,
 >;
 
-type EventServiceReconnect<Types, ApiVer> = Pin<
-    Box<dyn Future<Output = anyhow::Result<EventServiceConnection<Types, ApiVer>>> + Send + Sync>,
->;
+type EventServiceReconnect<Types, ApiVer> =
+    Pin<Box<dyn Future<Output = anyhow::Result<Endpoint<Types, ApiVer>>> + Send + Sync>>;
+
+/// Default maximum period between events, once it elapsed we assume
+/// underlying connection silently went down and attempt to reconnect
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default delay between connection attempts
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Default ceiling on delay between connection attempts when backing off
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// Default time allotted to establishing a connection
+pub const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+/// Default time allotted to each endpoint to deliver its first event when
+/// choosing between multiple endpoints
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Callback invoked once [`EventServiceStream`] has (re)connected to the events API at given URL
+pub type ConnectCallback = Arc<dyn Fn(&Url) + Send + Sync>;
+
+/// Callback invoked once [`EventServiceStream`] has lost connection to the events API at given URL
+pub type DisconnectCallback = Arc<dyn Fn(&Url, DisconnectReason) + Send + Sync>;
+
+/// Callback invoked once [`EventServiceStream`] detects that events were lost while it was reconnecting
+pub type GapCallback = Arc<dyn Fn(EventGap) + Send + Sync>;
+
+/// Reason for [`EventServiceStream`] dropping its current connection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Events API closed the connection
+    StreamEnded,
+    /// No events were received for [`EventServiceStreamConfig::idle_timeout`]
+    IdleTimeout,
+}
+
+/// Views skipped by [`EventServiceStream`] while it was reconnecting.
+///
+/// Events for views in `(last_seen_view, resumed_view)` were emitted by HotShot
+/// while we were disconnected and will never be delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventGap {
+    /// Highest view we've seen an event for before losing connection
+    pub last_seen_view: u64,
+    /// View of the first event received after reconnecting
+    pub resumed_view: u64,
+}
+
+/// Kind of HotShot event, used to track the last seen view separately for each of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// [`EventType::Decide`]
+    Decide,
+    /// [`EventType::DaProposal`]
+    DaProposal,
+    /// [`EventType::QuorumProposal`]
+    QuorumProposal,
+    /// [`EventType::ViewFinished`]
+    ViewFinished,
+    /// [`EventType::Transactions`]
+    Transactions,
+    /// [`EventType::ViewTimeout`]
+    ViewTimeout,
+    /// Any other event
+    Other,
+}
+
+impl EventKind {
+    /// Classify a HotShot event
+    pub fn of<Types: NodeType>(event: &EventType<Types>) -> Self {
+        match event {
+            EventType::Decide { .. } => Self::Decide,
+            EventType::DaProposal { .. } => Self::DaProposal,
+            EventType::QuorumProposal { .. } => Self::QuorumProposal,
+            EventType::ViewFinished { .. } => Self::ViewFinished,
+            EventType::Transactions { .. } => Self::Transactions,
+            EventType::ViewTimeout { .. } => Self::ViewTimeout,
+            _ => Self::Other,
+        }
+    }
+
+    /// Whether HotShot emits at most one event of this kind per view
+    fn unique_per_view(self) -> bool {
+        matches!(
+            self,
+            Self::Decide | Self::DaProposal | Self::QuorumProposal | Self::ViewFinished
+        )
+    }
+}
+
+/// Fingerprint identifying events HotShot can emit multiple times per view,
+/// so that ones replayed from the last seen view can be told apart from new ones.
+/// `None` for events that can't be told apart.
+fn fingerprint<Types: NodeType>(event: &EventType<Types>) -> Option<u64> {
+    match event {
+        EventType::Transactions { transactions } => {
+            let mut hasher = DefaultHasher::new();
+            for transaction in transactions {
+                transaction.commit().hash(&mut hasher);
+            }
+            Some(hasher.finish())
+        }
+        _ => None,
+    }
+}
+
+/// Outcome of [`EventTracker::observe`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Observation {
+    /// Event was already delivered, it should be dropped
+    Duplicate,
+    /// Event should be delivered
+    New,
+    /// Event should be delivered, but events for some views were lost before it
+    AfterGap(EventGap),
+}
+
+/// Tracks last seen view per [`EventKind`] to suppress events replayed after
+/// reconnecting and detect views skipped while we were disconnected.
+///
+/// Events are only checked for duplicates while replaying, i.e. after reconnecting
+/// and until the first event we haven't seen before. Outside of that HotShot can
+/// legitimately emit events out of view order, so every event is delivered.
+#[derive(Debug, Default)]
+struct EventTracker {
+    last_seen: HashMap<EventKind, u64>,
+    /// Fingerprints of events seen for the last seen view of their kind, see [`fingerprint`]
+    seen_in_last_view: HashMap<EventKind, HashSet<u64>>,
+    highest_view: Option<u64>,
+    reconnected: bool,
+}
+
+impl EventTracker {
+    /// Register a new connection. Events will be checked for duplicates until the
+    /// first new one, which will be checked for a gap
+    fn reconnected(&mut self) {
+        self.reconnected = true;
+    }
+
+    fn observe(&mut self, kind: EventKind, view: u64, fingerprint: Option<u64>) -> Observation {
+        if self.reconnected && self.is_replayed(kind, view, fingerprint) {
+            return Observation::Duplicate;
+        }
+
+        let seen = self.seen_in_last_view.entry(kind).or_default();
+        match self.last_seen.get(&kind) {
+            Some(&last_seen) if view < last_seen => {}
+            Some(&last_seen) if view == last_seen => seen.extend(fingerprint),
+            _ => {
+                seen.clear();
+                seen.extend(fingerprint);
+                self.last_seen.insert(kind, view);
+            }
+        }
+
+        let gap = match self.highest_view {
+            Some(last_seen_view) if std::mem::take(&mut self.reconnected) => {
+                (view > last_seen_view + 1).then_some(EventGap {
+                    last_seen_view,
+                    resumed_view: view,
+                })
+            }
+            _ => None,
+        };
+
+        self.highest_view = Some(self.highest_view.map_or(view, |highest| highest.max(view)));
+
+        match gap {
+            Some(gap) => Observation::AfterGap(gap),
+            None => Observation::New,
+        }
+    }
+
+    /// Whether an event received after reconnecting was already delivered before
+    fn is_replayed(&self, kind: EventKind, view: u64, fingerprint: Option<u64>) -> bool {
+        match self.last_seen.get(&kind) {
+            Some(&last_seen) if view < last_seen => true,
+            Some(&last_seen) if view == last_seen => {
+                // Events of kinds that HotShot can emit multiple times per view
+                // (e.g. transactions) are duplicates if we've seen the same event before
+                kind.unique_per_view()
+                    || fingerprint.is_some_and(|fingerprint| {
+                        self.seen_in_last_view
+                            .get(&kind)
+                            .is_some_and(|seen| seen.contains(&fingerprint))
+                    })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Reconnection policy of [`EventServiceStream`].
+///
+/// Failed connection attempts are retried with exponential backoff: the delay starts at
+/// [`Self::initial_retry_delay`] and is multiplied by [`Self::backoff_multiplier`] after
+/// each attempt, up to [`Self::max_retry_delay`]. The default multiplier of `1.0` retries
+/// at a fixed period. Connections that are lost before delivering any events count as
+/// failed attempts, so the backoff only starts over once an event is received.
+#[derive(derive_more::Debug, Clone)]
+pub struct EventServiceStreamConfig {
+    /// Maximum period between events, once it elapsed we assume underlying
+    /// connection silently went down and attempt to reconnect.
+    /// `None` disables reconnection on idle connections.
+    pub idle_timeout: Option<Duration>,
+    /// Maximum time allotted to establishing a connection, including retries.
+    /// `None` means we'll be trying to connect until [`Self::max_attempts`] is exhausted.
+    pub connection_timeout: Option<Duration>,
+    /// Delay after the first failed connection attempt
+    pub initial_retry_delay: Duration,
+    /// Ceiling on the delay between connection attempts
+    pub max_retry_delay: Duration,
+    /// Factor the delay between connection attempts is multiplied by after each failure
+    pub backoff_multiplier: f64,
+    /// Random jitter applied to retry delays, as a fraction of the delay (`0.0..=1.0`)
+    pub jitter: f64,
+    /// Maximum number of consecutive connection attempts without receiving an event.
+    /// Once exhausted,
+    /// [`EventServiceStream::connect_with_config`] returns an error or, if we're
+    /// reconnecting, the stream ends. `None` retries forever.
+    pub max_attempts: Option<u32>,
+    /// Time allotted to each endpoint to deliver its first event when choosing
+    /// between multiple endpoints. Endpoints that don't deliver an event in time
+    /// are considered healthy, but are ranked below the ones that did.
+    pub probe_timeout: Duration,
+    /// Called each time a connection is established
+    #[debug(skip)]
+    pub on_connect: Option<ConnectCallback>,
+    /// Called each time an established connection is lost
+    #[debug(skip)]
+    pub on_disconnect: Option<DisconnectCallback>,
+    /// Called when views were skipped while reconnecting
+    #[debug(skip)]
+    pub on_gap: Option<GapCallback>,
+}
+
+impl Default for EventServiceStreamConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            connection_timeout: Some(DEFAULT_CONNECTION_TIMEOUT),
+            initial_retry_delay: DEFAULT_RETRY_DELAY,
+            max_retry_delay: DEFAULT_MAX_RETRY_DELAY,
+            backoff_multiplier: 1.0,
+            jitter: 0.0,
+            max_attempts: None,
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+            on_connect: None,
+            on_disconnect: None,
+            on_gap: None,
+        }
+    }
+}
+
+/// Tracks consecutive connection attempts that didn't result in receiving an event
+/// and computes delays between them
+#[derive(Debug)]
+struct Backoff {
+    initial_delay: Duration,
+    next_delay: Duration,
+    max_delay: Duration,
+    multiplier: f64,
+    jitter: f64,
+    attempts: u32,
+    max_attempts: Option<u32>,
+}
+
+impl Backoff {
+    fn new(config: &EventServiceStreamConfig) -> Self {
+        Self {
+            initial_delay: config.initial_retry_delay,
+            next_delay: config.initial_retry_delay,
+            max_delay: config.max_retry_delay,
+            multiplier: config.backoff_multiplier.max(1.0),
+            jitter: config.jitter.clamp(0.0, 1.0),
+            attempts: 0,
+            max_attempts: config.max_attempts,
+        }
+    }
+
+    /// Register a new attempt. Returns the delay to wait for before making it,
+    /// which is zero for the first attempt since [`Self::reset`],
+    /// or `None` if we're out of attempts.
+    fn next_attempt(&mut self) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| self.attempts >= max) {
+            return None;
+        }
+        self.attempts = self.attempts.saturating_add(1);
+        if self.attempts == 1 {
+            return Some(Duration::ZERO);
+        }
+
+        let delay = self.next_delay;
+        self.next_delay = Duration::try_from_secs_f64(delay.as_secs_f64() * self.multiplier)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
 
-/// A wrapper around event streaming API that provides auto-reconnection capability
+        if self.jitter == 0.0 {
+            return Some(delay);
+        }
+        let factor = 1.0 + thread_rng().gen_range(-self.jitter..=self.jitter);
+        Some(Duration::try_from_secs_f64(delay.as_secs_f64() * factor).unwrap_or(delay))
+    }
+
+    /// Start over after an event was received
+    fn reset(&mut self) {
+        self.attempts = 0;
+        self.next_delay = self.initial_delay;
+    }
+}
+
+/// Backoff shared between [`EventServiceStream`] and its reconnection attempts
+type SharedBackoff = Arc<Mutex<Backoff>>;
+
+fn lock_backoff(backoff: &SharedBackoff) -> MutexGuard<'_, Backoff> {
+    // Poisoning isn't a concern, as the lock is never held across anything that can panic
+    backoff
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Connection to one of the endpoints of [`EventServiceStream`]
+struct Endpoint<Types: NodeType, ApiVer: StaticVersionType> {
+    /// Index of the endpoint's URL in [`EventServiceStream::api_urls`]
+    index: usize,
+    connection: EventServiceConnection<Types, ApiVer>,
+    /// Event received while probing the endpoint, not yet delivered
+    pending: Option<Event<Types>>,
+}
+
+/// A wrapper around event streaming API that provides auto-reconnection capability.
+///
+/// The stream can be backed by multiple endpoints, in which case it will connect to
+/// the healthy one furthest ahead by view and fail over to another one on disconnect
+/// or idle timeout.
+///
+/// Events replayed by the API after reconnecting are deduplicated and skipped views
+/// are reported through [`EventServiceStreamConfig::on_gap`].
 pub struct EventServiceStream<Types: NodeType, V: StaticVersionType> {
-    api_url: Url,
-    connection: Either<EventServiceConnection<Types, V>, EventServiceReconnect<Types, V>>,
+    api_urls: Vec<Url>,
+    config: EventServiceStreamConfig,
+    tracker: EventTracker,
+    backoff: SharedBackoff,
+    connection: Either<Endpoint<Types, V>, EventServiceReconnect<Types, V>>,
 }
 
 impl<Types: NodeType, ApiVer: StaticVersionType + 'static> EventServiceStream<Types, ApiVer> {
-    /// Maximum period between events, once it elapsed we assume
-    /// udnerlying connection silently went down and attempt to reconnect
-    const MAX_WAIT_PERIOD: Duration = Duration::from_secs(10);
-    const RETRY_PERIOD: Duration = Duration::from_secs(1);
-    const CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
-
+    /// Connect to the best available endpoint, continuing `backoff`
+    /// from previous attempts that didn't result in receiving an event
     async fn connect_inner(
-        url: Url,
-    ) -> anyhow::Result<
-        surf_disco::socket::Connection<
-            Event<Types>,
-            surf_disco::socket::Unsupported,
-            EventStreamError,
-            ApiVer,
-        >,
-    > {
-        let client = Client::<hotshot_events_service::events::Error, ApiVer>::new(url.clone());
-
-        timeout(Self::CONNECTION_TIMEOUT, async {
+        api_urls: Vec<Url>,
+        config: EventServiceStreamConfig,
+        backoff: SharedBackoff,
+    ) -> anyhow::Result<Endpoint<Types, ApiVer>> {
+        let select = async {
             loop {
-                match client.healthcheck::<HealthStatus>().await {
-                    Ok(_) => break,
-                    Err(err) => {
-                        tracing::debug!(?err, "Healthcheck failed, retrying");
+                let Some(delay) = lock_backoff(&backoff).next_attempt() else {
+                    anyhow::bail!("Exhausted connection attempts");
+                };
+                if !delay.is_zero() {
+                    sleep(delay).await;
+                }
+                match Self::select_endpoint(&api_urls, config.probe_timeout).await {
+                    Some(endpoint) => break Ok::<_, anyhow::Error>(endpoint),
+                    None => {
+                        tracing::debug!("No healthy events API endpoint, retrying");
                     }
                 }
-                sleep(Self::RETRY_PERIOD).await;
             }
-        })
+        };
+
+        let endpoint = match config.connection_timeout {
+            Some(connection_timeout) => timeout(connection_timeout, select)
+                .await
+                .context("Couldn't connect to hotshot events API")??,
+            None => select
+                .await
+                .context("Couldn't connect to hotshot events API")?,
+        };
+
+        let url = &api_urls[endpoint.index];
+        tracing::info!(%url, "Builder client connected to the hotshot events API");
+
+        if let Some(on_connect) = &config.on_connect {
+            on_connect(url);
+        }
+
+        Ok(endpoint)
+    }
+
+    /// Probe all endpoints and pick the healthy one furthest ahead by view,
+    /// preferring endpoints listed first if there's a tie.
+    async fn select_endpoint(
+        api_urls: &[Url],
+        probe_timeout: Duration,
+    ) -> Option<Endpoint<Types, ApiVer>> {
+        // With a single endpoint there's nothing to choose from,
+        // so we don't need to wait for its first event
+        let probe_timeout = (api_urls.len() > 1).then_some(probe_timeout);
+
+        join_all(
+            api_urls
+                .iter()
+                .enumerate()
+                .map(|(index, url)| Self::probe(index, url.clone(), probe_timeout)),
+        )
         .await
-        .context("Couldn't connect to hotshot events API")?;
+        .into_iter()
+        .filter_map(|probe| {
+            probe
+                .inspect_err(|err| tracing::debug!(?err, "Events API endpoint probe failed"))
+                .ok()
+        })
+        .max_by_key(|endpoint| {
+            (
+                endpoint.pending.as_ref().map(|event| *event.view_number),
+                Reverse(endpoint.index),
+            )
+        })
+    }
+
+    /// Connect to endpoint at `url` and, if `probe_timeout` is set,
+    /// wait for its first event to learn how far ahead it is
+    async fn probe(
+        index: usize,
+        url: Url,
+        probe_timeout: Option<Duration>,
+    ) -> anyhow::Result<Endpoint<Types, ApiVer>> {
+        let client = Client::<EventStreamError, ApiVer>::new(url);
 
-        tracing::info!("Builder client connected to the hotshot events API");
+        client.healthcheck::<HealthStatus>().await?;
 
-        Ok(client
+        let mut connection = client
             .socket("hotshot-events/events")
             .subscribe::<Event<Types>>()
-            .await?)
+            .await?;
+
+        let pending = match probe_timeout {
+            Some(probe_timeout) => match timeout(probe_timeout, connection.next()).await {
+                Ok(Some(Ok(event))) => Some(event),
+                Ok(Some(Err(err))) => {
+                    warn!(?err, "Error in event stream");
+                    None
+                }
+                Ok(None) => anyhow::bail!("Event stream ended"),
+                Err(_) => None,
+            },
+            None => None,
+        };
+
+        Ok(Endpoint {
+            index,
+            connection,
+            pending,
+        })
     }
 
     /// Establish initial connection to the events service at `api_url`
     pub async fn connect(api_url: Url) -> anyhow::Result<impl Stream<Item = Event<Types>> + Unpin> {
-        let connection = Self::connect_inner(api_url.clone()).await?;
+        Self::connect_with_config(api_url, EventServiceStreamConfig::default()).await
+    }
+
+    /// Establish initial connection to the events service at `api_url`,
+    /// reconnecting according to `config`
+    pub async fn connect_with_config(
+        api_url: Url,
+        config: EventServiceStreamConfig,
+    ) -> anyhow::Result<impl Stream<Item = Event<Types>> + Unpin> {
+        Self::connect_any_with_config(vec![api_url], config).await
+    }
+
+    /// Establish initial connection to the best of the events services at `api_urls`
+    pub async fn connect_any(
+        api_urls: impl IntoIterator<Item = Url>,
+    ) -> anyhow::Result<impl Stream<Item = Event<Types>> + Unpin> {
+        Self::connect_any_with_config(api_urls, EventServiceStreamConfig::default()).await
+    }
+
+    /// Establish initial connection to the best of the events services at `api_urls`,
+    /// reconnecting according to `config`
+    pub async fn connect_any_with_config(
+        api_urls: impl IntoIterator<Item = Url>,
+        config: EventServiceStreamConfig,
+    ) -> anyhow::Result<impl Stream<Item = Event<Types>> + Unpin> {
+        let api_urls: Vec<Url> = api_urls.into_iter().collect();
+        anyhow::ensure!(!api_urls.is_empty(), "No events API URLs provided");
+
+        let backoff = Arc::new(Mutex::new(Backoff::new(&config)));
+        let endpoint =
+            Self::connect_inner(api_urls.clone(), config.clone(), Arc::clone(&backoff)).await?;
 
         let this = Self {
-            api_url,
-            connection: Left(connection),
+            api_urls,
+            config,
+            tracker: EventTracker::default(),
+            backoff,
+            connection: Left(endpoint),
         };
 
         let stream = unfold(this, |mut this| async move {
             loop {
                 match &mut this.connection {
-                    Left(connection) => {
-                        match tokio::time::timeout(Self::MAX_WAIT_PERIOD, connection.next()).await {
+                    Left(endpoint) => {
+                        let next = match endpoint.pending.take() {
+                            Some(event) => Ok(Some(Ok(event))),
+                            None => match this.config.idle_timeout {
+                                Some(idle_timeout) => {
+                                    timeout(idle_timeout, endpoint.connection.next()).await
+                                }
+                                None => Ok(endpoint.connection.next().await),
+                            },
+                        };
+                        match next {
                             Ok(Some(Ok(event))) => {
+                                // Connection works, even if the event turns out to be replayed
+                                lock_backoff(&this.backoff).reset();
+                                match this.tracker.observe(
+                                    EventKind::of(&event.event),
+                                    *event.view_number,
+                                    fingerprint(&event.event),
+                                ) {
+                                    Observation::Duplicate => {
+                                        tracing::debug!(
+                                            view = *event.view_number,
+                                            "Dropping duplicate event"
+                                        );
+                                        continue;
+                                    }
+                                    Observation::New => {}
+                                    Observation::AfterGap(gap) => {
+                                        warn!(?gap, "Views were skipped while reconnecting");
+                                        if let Some(on_gap) = &this.config.on_gap {
+                                            on_gap(gap);
+                                        }
+                                    }
+                                }
                                 return Some((event, this));
                             }
                             Ok(Some(Err(err))) => {
@@ -97,30 +574,34 @@ impl<Types: NodeType, ApiVer: StaticVersionType + 'static> EventServiceStream<Ty
                             }
                             Ok(None) => {
                                 warn!("Event stream ended, attempting reconnection");
-                                let fut = Self::connect_inner(this.api_url.clone());
-                                let _ =
-                                    std::mem::replace(&mut this.connection, Right(Box::pin(fut)));
+                                this.reconnect(DisconnectReason::StreamEnded);
                                 continue;
                             }
                             Err(_) => {
                                 // Timeout occurred, reconnect
                                 warn!("Timeout waiting for next event; reconnecting");
-                                let fut = Self::connect_inner(this.api_url.clone());
-                                let _ =
-                                    std::mem::replace(&mut this.connection, Right(Box::pin(fut)));
+                                this.reconnect(DisconnectReason::IdleTimeout);
                                 continue;
                             }
                         }
                     }
                     Right(reconnection) => match reconnection.await {
-                        Ok(connection) => {
-                            let _ = std::mem::replace(&mut this.connection, Left(connection));
+                        Ok(endpoint) => {
+                            this.tracker.reconnected();
+                            let _ = std::mem::replace(&mut this.connection, Left(endpoint));
                             continue;
                         }
                         Err(err) => {
+                            if this.config.max_attempts.is_some() {
+                                error!(?err, "Error while reconnecting, out of attempts");
+                                return None;
+                            }
                             error!(?err, "Error while reconnecting, will retry in a while");
-                            sleep(Self::RETRY_PERIOD).await;
-                            let fut = Self::connect_inner(this.api_url.clone());
+                            let fut = Self::connect_inner(
+                                this.api_urls.clone(),
+                                this.config.clone(),
+                                Arc::clone(&this.backoff),
+                            );
                             let _ = std::mem::replace(&mut this.connection, Right(Box::pin(fut)));
                             continue;
                         }
@@ -131,6 +612,21 @@ impl<Types: NodeType, ApiVer: StaticVersionType + 'static> EventServiceStream<Ty
 
         Ok(Box::pin(stream))
     }
+
+    /// Drop current connection and start reconnecting to the best available endpoint
+    fn reconnect(&mut self, reason: DisconnectReason) {
+        if let (Left(endpoint), Some(on_disconnect)) =
+            (&self.connection, &self.config.on_disconnect)
+        {
+            on_disconnect(&self.api_urls[endpoint.index], reason);
+        }
+        let fut = Self::connect_inner(
+            self.api_urls.clone(),
+            self.config.clone(),
+            Arc::clone(&self.backoff),
+        );
+        let _ = std::mem::replace(&mut self.connection, Right(Box::pin(fut)));
+    }
 }
 
 #[cfg_attr(coverage_nightly, coverage(off))]
@@ -159,12 +655,16 @@ mod tests {
     use url::Url;
     use vbs::version::StaticVersion;
 
+    use super::{
+        Backoff, EventGap, EventKind, EventServiceStreamConfig, EventTracker, Observation,
+        DEFAULT_RETRY_DELAY,
+    };
     use crate::utils::EventServiceStream;
 
     type MockVersion = StaticVersion<0, 1>;
 
     struct MockEventsSource {
-        counter: AtomicU64,
+        counter: Arc<AtomicU64>,
     }
 
     #[async_trait]
@@ -204,9 +704,11 @@ mod tests {
         }
     }
 
-    fn run_app(path: &'static str, bind_url: Url) -> JoinHandle<()> {
+    /// Spawn mock events API. `counter` is shared between restarts of the API,
+    /// so that the views continue where the previous instance left off.
+    fn run_app(path: &'static str, bind_url: Url, counter: &Arc<AtomicU64>) -> JoinHandle<()> {
         let source = MockEventsSource {
-            counter: AtomicU64::new(0),
+            counter: Arc::clone(counter),
         };
         let api = define_api::<MockEventsSource, _, MockVersion>(&Default::default()).unwrap();
 
@@ -228,8 +730,9 @@ mod tests {
         )
         .parse()
         .unwrap();
+        let counter = Arc::new(AtomicU64::new(0));
 
-        let app_handle = run_app("hotshot-events", url.clone());
+        let app_handle = run_app("hotshot-events", url.clone(), &counter);
 
         let mut stream = EventServiceStream::<TestTypes, MockVersion>::connect(url.clone())
             .await
@@ -246,7 +749,7 @@ mod tests {
             .await
             .expect_err("When mock event server is killed, stream should be in reconnecting state and never return");
 
-        let app_handle = run_app("hotshot-events", url.clone());
+        let app_handle = run_app("hotshot-events", url.clone(), &counter);
 
         timeout(TIMEOUT, stream.next())
             .await
@@ -255,7 +758,7 @@ mod tests {
 
         app_handle.abort();
 
-        run_app("wrong-path", url.clone());
+        run_app("wrong-path", url.clone(), &counter);
 
         timeout(TIMEOUT, stream.next())
             .await
@@ -272,8 +775,9 @@ mod tests {
         )
         .parse()
         .unwrap();
+        let counter = Arc::new(AtomicU64::new(0));
 
-        let app_handle = run_app("hotshot-events", url.clone());
+        let app_handle = run_app("hotshot-events", url.clone(), &counter);
 
         let mut stream = EventServiceStream::<TestTypes, MockVersion>::connect(url.clone())
             .await
@@ -287,24 +791,17 @@ mod tests {
 
         // Simulate idle timeout by stopping the server and waiting
         app_handle.abort();
-        tokio::time::sleep(
-            EventServiceStream::<TestTypes, MockVersion>::RETRY_PERIOD + Duration::from_millis(500),
-        )
-        .await; // Wait longer than idle timeout
-                // Check whether stream returns Err(_) after idle timeout
-        match timeout(
-            EventServiceStream::<TestTypes, MockVersion>::RETRY_PERIOD,
-            stream.next(),
-        )
-        .await
-        {
+        // Wait longer than idle timeout
+        tokio::time::sleep(DEFAULT_RETRY_DELAY + Duration::from_millis(500)).await;
+        // Check whether stream returns Err(_) after idle timeout
+        match timeout(DEFAULT_RETRY_DELAY, stream.next()).await {
             Ok(Some(_)) => panic!("Expected error after idle timeout but got an event"),
             Ok(None) => panic!("Expected error but got None"),
             Err(err) => debug!("Stream returned an error after idle timeout: {:?}", err),
         }
 
         // Stream should reconnect after idle timeout
-        let new_app_handle = run_app("hotshot-events", url.clone());
+        let new_app_handle = run_app("hotshot-events", url.clone(), &counter);
 
         timeout(TIMEOUT, stream.next())
             .await
@@ -314,4 +811,388 @@ mod tests {
         // Cleanup
         new_app_handle.abort();
     }
+
+    #[test]
+    fn test_backoff() {
+        let config = EventServiceStreamConfig {
+            initial_retry_delay: Duration::from_millis(100),
+            max_retry_delay: Duration::from_millis(350),
+            backoff_multiplier: 2.0,
+            max_attempts: Some(5),
+            ..Default::default()
+        };
+        let mut backoff = Backoff::new(&config);
+
+        assert_eq!(backoff.next_attempt(), Some(Duration::ZERO));
+        assert_eq!(backoff.next_attempt(), Some(Duration::from_millis(100)));
+        assert_eq!(backoff.next_attempt(), Some(Duration::from_millis(200)));
+        assert_eq!(backoff.next_attempt(), Some(Duration::from_millis(350)));
+        assert_eq!(backoff.next_attempt(), Some(Duration::from_millis(350)));
+        assert_eq!(backoff.next_attempt(), None);
+
+        // Receiving an event starts the backoff over
+        backoff.reset();
+        assert_eq!(backoff.next_attempt(), Some(Duration::ZERO));
+        assert_eq!(backoff.next_attempt(), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let config = EventServiceStreamConfig {
+            initial_retry_delay: Duration::from_millis(100),
+            jitter: 0.5,
+            ..Default::default()
+        };
+        let mut backoff = Backoff::new(&config);
+        assert_eq!(backoff.next_attempt(), Some(Duration::ZERO));
+
+        for _ in 0..100 {
+            let delay = backoff.next_attempt().unwrap();
+            assert!(delay >= Duration::from_millis(50));
+            assert!(delay <= Duration::from_millis(150));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_event_stream_wrapper_max_attempts() {
+        const TIMEOUT: Duration = Duration::from_secs(3);
+
+        let url: Url = format!(
+            "http://localhost:{}",
+            portpicker::pick_unused_port().unwrap()
+        )
+        .parse()
+        .unwrap();
+        let counter = Arc::new(AtomicU64::new(0));
+
+        let config = EventServiceStreamConfig {
+            initial_retry_delay: Duration::from_millis(50),
+            max_attempts: Some(3),
+            ..Default::default()
+        };
+
+        EventServiceStream::<TestTypes, MockVersion>::connect_with_config(
+            url.clone(),
+            config.clone(),
+        )
+        .await
+        .expect_err("Connection attempts should be exhausted without a server");
+
+        let connects = Arc::new(AtomicU64::new(0));
+        let disconnects = Arc::new(AtomicU64::new(0));
+        let config = EventServiceStreamConfig {
+            on_connect: Some({
+                let connects = Arc::clone(&connects);
+                Arc::new(move |_| {
+                    connects.fetch_add(1, Ordering::SeqCst);
+                })
+            }),
+            on_disconnect: Some({
+                let disconnects = Arc::clone(&disconnects);
+                Arc::new(move |_, _| {
+                    disconnects.fetch_add(1, Ordering::SeqCst);
+                })
+            }),
+            idle_timeout: Some(Duration::from_millis(500)),
+            ..config
+        };
+
+        let app_handle = run_app("hotshot-events", url.clone(), &counter);
+
+        let mut stream =
+            EventServiceStream::<TestTypes, MockVersion>::connect_with_config(url.clone(), config)
+                .await
+                .unwrap();
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+
+        timeout(TIMEOUT, stream.next())
+            .await
+            .expect("When mock event server is spawned, stream should work")
+            .unwrap();
+
+        app_handle.abort();
+
+        // Once reconnection attempts are exhausted, the stream should end
+        let next = timeout(TIMEOUT, async {
+            while let Some(_event) = stream.next().await {}
+        })
+        .await;
+        assert!(next.is_ok(), "Stream should end after exhausting attempts");
+        assert!(disconnects.load(Ordering::SeqCst) >= 1);
+    }
+
+    #[test]
+    fn test_event_tracker() {
+        let mut tracker = EventTracker::default();
+
+        assert_eq!(
+            tracker.observe(EventKind::ViewFinished, 1, None),
+            Observation::New
+        );
+        assert_eq!(
+            tracker.observe(EventKind::Transactions, 1, Some(1)),
+            Observation::New
+        );
+        assert_eq!(
+            tracker.observe(EventKind::ViewFinished, 2, None),
+            Observation::New
+        );
+        assert_eq!(
+            tracker.observe(EventKind::Transactions, 2, Some(2)),
+            Observation::New
+        );
+
+        // Without reconnecting, events are delivered even if out of view order
+        assert_eq!(tracker.observe(EventKind::Other, 3, None), Observation::New);
+        assert_eq!(
+            tracker.observe(EventKind::ViewTimeout, 2, None),
+            Observation::New
+        );
+        assert_eq!(tracker.observe(EventKind::Other, 2, None), Observation::New);
+
+        // Reconnect, events are replayed
+        tracker.reconnected();
+        assert_eq!(
+            tracker.observe(EventKind::Transactions, 1, Some(1)),
+            Observation::Duplicate
+        );
+        assert_eq!(
+            tracker.observe(EventKind::ViewFinished, 2, None),
+            Observation::Duplicate
+        );
+        assert_eq!(
+            tracker.observe(EventKind::ViewTimeout, 2, None),
+            Observation::Duplicate
+        );
+        // Replayed transaction events from the last seen view are dropped,
+        // but multiple different transaction events per view are fine
+        assert_eq!(
+            tracker.observe(EventKind::Transactions, 2, Some(2)),
+            Observation::Duplicate
+        );
+        assert_eq!(
+            tracker.observe(EventKind::Transactions, 2, Some(3)),
+            Observation::New
+        );
+        // Replay is over once a new event is delivered
+        assert_eq!(
+            tracker.observe(EventKind::Transactions, 2, Some(3)),
+            Observation::New
+        );
+        assert_eq!(
+            tracker.observe(EventKind::ViewFinished, 3, None),
+            Observation::New
+        );
+
+        // Reconnect, views are skipped
+        tracker.reconnected();
+        assert_eq!(
+            tracker.observe(EventKind::ViewFinished, 7, None),
+            Observation::AfterGap(EventGap {
+                last_seen_view: 3,
+                resumed_view: 7
+            })
+        );
+        // Gap is reported once
+        assert_eq!(
+            tracker.observe(EventKind::QuorumProposal, 8, None),
+            Observation::New
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_event_stream_wrapper_gap() {
+        const TIMEOUT: Duration = Duration::from_secs(3);
+        const SKIPPED_VIEWS: u64 = 10;
+
+        let url: Url = format!(
+            "http://localhost:{}",
+            portpicker::pick_unused_port().unwrap()
+        )
+        .parse()
+        .unwrap();
+        let counter = Arc::new(AtomicU64::new(0));
+
+        let gaps = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = EventServiceStreamConfig {
+            on_gap: Some({
+                let gaps = Arc::clone(&gaps);
+                Arc::new(move |gap| gaps.lock().unwrap().push(gap))
+            }),
+            ..Default::default()
+        };
+
+        let app_handle = run_app("hotshot-events", url.clone(), &counter);
+
+        let mut stream =
+            EventServiceStream::<TestTypes, MockVersion>::connect_with_config(url.clone(), config)
+                .await
+                .unwrap();
+
+        let last_seen_view = *timeout(TIMEOUT, stream.next())
+            .await
+            .expect("When mock event server is spawned, stream should work")
+            .unwrap()
+            .view_number;
+
+        app_handle.abort();
+        counter.fetch_add(SKIPPED_VIEWS, Ordering::SeqCst);
+        let app_handle = run_app("hotshot-events", url.clone(), &counter);
+
+        let resumed_view = *timeout(TIMEOUT, stream.next())
+            .await
+            .expect("When mock event server is restarted, stream should work again")
+            .unwrap()
+            .view_number;
+
+        assert!(resumed_view > last_seen_view + SKIPPED_VIEWS);
+        assert_eq!(
+            gaps.lock().unwrap().as_slice(),
+            &[EventGap {
+                last_seen_view,
+                resumed_view
+            }]
+        );
+
+        app_handle.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_event_stream_wrapper_failover() {
+        const TIMEOUT: Duration = Duration::from_secs(5);
+
+        let lagging_url: Url = format!(
+            "http://localhost:{}",
+            portpicker::pick_unused_port().unwrap()
+        )
+        .parse()
+        .unwrap();
+        let leading_url: Url = format!(
+            "http://localhost:{}",
+            portpicker::pick_unused_port().unwrap()
+        )
+        .parse()
+        .unwrap();
+        let lagging_counter = Arc::new(AtomicU64::new(0));
+        let leading_counter = Arc::new(AtomicU64::new(100));
+
+        let connected_to = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = EventServiceStreamConfig {
+            on_connect: Some({
+                let connected_to = Arc::clone(&connected_to);
+                Arc::new(move |url: &Url| connected_to.lock().unwrap().push(url.clone()))
+            }),
+            ..Default::default()
+        };
+
+        let lagging_handle = run_app("hotshot-events", lagging_url.clone(), &lagging_counter);
+        let leading_handle = run_app("hotshot-events", leading_url.clone(), &leading_counter);
+        // Give both servers time to start up, so that both are healthy during selection
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let mut stream = EventServiceStream::<TestTypes, MockVersion>::connect_any_with_config(
+            [lagging_url.clone(), leading_url.clone()],
+            config,
+        )
+        .await
+        .unwrap();
+
+        let event = timeout(TIMEOUT, stream.next())
+            .await
+            .expect("When mock event servers are spawned, stream should work")
+            .unwrap();
+        assert_eq!(
+            *event.view_number, 100,
+            "Stream should pick the endpoint furthest ahead"
+        );
+        assert_eq!(
+            connected_to.lock().unwrap().as_slice(),
+            &[leading_url.clone()]
+        );
+
+        // Kill the leading endpoint, the stream should fail over to the other one,
+        // dropping events the leading endpoint has already delivered
+        leading_handle.abort();
+        lagging_counter.store(150, Ordering::SeqCst);
+
+        let event = timeout(TIMEOUT, stream.next())
+            .await
+            .expect("Stream should fail over to the remaining endpoint")
+            .unwrap();
+        assert!(*event.view_number >= 150);
+        assert_eq!(
+            connected_to.lock().unwrap().last(),
+            Some(&lagging_url),
+            "Stream should be connected to the remaining endpoint"
+        );
+
+        lagging_handle.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_event_stream_wrapper_failover_replay() {
+        const TIMEOUT: Duration = Duration::from_secs(5);
+
+        let lagging_url: Url = format!(
+            "http://localhost:{}",
+            portpicker::pick_unused_port().unwrap()
+        )
+        .parse()
+        .unwrap();
+        let leading_url: Url = format!(
+            "http://localhost:{}",
+            portpicker::pick_unused_port().unwrap()
+        )
+        .parse()
+        .unwrap();
+        let lagging_counter = Arc::new(AtomicU64::new(0));
+        let leading_counter = Arc::new(AtomicU64::new(100));
+
+        let gaps = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = EventServiceStreamConfig {
+            on_gap: Some({
+                let gaps = Arc::clone(&gaps);
+                Arc::new(move |gap| gaps.lock().unwrap().push(gap))
+            }),
+            ..Default::default()
+        };
+
+        let lagging_handle = run_app("hotshot-events", lagging_url.clone(), &lagging_counter);
+        let leading_handle = run_app("hotshot-events", leading_url.clone(), &leading_counter);
+        // Give both servers time to start up, so that both are healthy during selection
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let mut stream = EventServiceStream::<TestTypes, MockVersion>::connect_any_with_config(
+            [lagging_url.clone(), leading_url.clone()],
+            config,
+        )
+        .await
+        .unwrap();
+
+        let event = timeout(TIMEOUT, stream.next())
+            .await
+            .expect("When mock event servers are spawned, stream should work")
+            .unwrap();
+        assert_eq!(*event.view_number, 100);
+
+        // Kill the leading endpoint. The remaining one is behind and replays views
+        // 95 to 100, which overlap with ones already delivered and should be dropped.
+        leading_handle.abort();
+        lagging_counter.store(95, Ordering::SeqCst);
+
+        let event = timeout(TIMEOUT, stream.next())
+            .await
+            .expect("Stream should fail over to the remaining endpoint")
+            .unwrap();
+        assert_eq!(
+            *event.view_number, 101,
+            "Views already delivered by the previous endpoint should be dropped"
+        );
+        assert!(
+            gaps.lock().unwrap().is_empty(),
+            "Replayed views aren't a gap"
+        );
+
+        lagging_handle.abort();
+    }
 }
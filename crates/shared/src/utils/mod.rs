@@ -18,8 +18,20 @@ use tokio::{spawn, sync::Notify};
 pub mod rotating_set;
 pub use rotating_set::RotatingSet;
 
+pub mod event_recording;
+pub use event_recording::{
+    read_recording, record_events, replay_events, replay_recording, EventRecorder, RecordedEvent,
+    RecordingFormat, ReplaySpeed, DEFAULT_RECORDING_CAPACITY,
+};
+
+pub mod retry_after;
+pub use retry_after::RetryAfterListener;
+
 pub mod event_serivce_wrapper;
-pub use event_serivce_wrapper::EventServiceStream;
+pub use event_serivce_wrapper::{
+    DisconnectReason, EventGap, EventKind, EventServiceStream, EventServiceStreamConfig,
+    GapCallback,
+};
 
 /// A convenience type alias for a tuple of builder keys
 /// `(public_key, private_key)`
@@ -0,0 +1,95 @@
+//! `Retry-After` headers for rate-limited API requests.
+//!
+//! `tide_disco` handlers can only return a body or an error with a status, so
+//! [`Error::into_api_error`](crate::error::Error::into_api_error) reports the delay of
+//! [`Error::RateLimited`](crate::error::Error::RateLimited) to the request being handled,
+//! and a middleware installed by [`RetryAfterListener`] turns it into a header.
+
+use std::{
+    cell::Cell,
+    fmt::{self, Display},
+    io,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tide::{
+    listener::{ListenInfo, Listener, ToListener},
+    Middleware, Next, Request, Server,
+};
+
+tokio::task_local! {
+    /// Delay after which the request currently being handled can be retried, if it was rate-limited
+    static RETRY_AFTER: Cell<Option<Duration>>;
+}
+
+/// Report that the request currently being handled was rate-limited and can be retried
+/// after `retry_after`. Does nothing outside of a server bound by [`RetryAfterListener`].
+pub(crate) fn report_retry_after(retry_after: Duration) {
+    let _ = RETRY_AFTER.try_with(|cell| cell.set(Some(retry_after)));
+}
+
+/// Wraps a listener, e.g. an address, so that the server it's bound to adds a `Retry-After`
+/// header to responses of rate-limited requests. Builders' apps should be served with it,
+/// e.g. `app.serve(RetryAfterListener(url), version)`.
+#[derive(Debug)]
+pub struct RetryAfterListener<L>(pub L);
+
+impl<State, L> ToListener<State> for RetryAfterListener<L>
+where
+    State: Clone + Send + Sync + 'static,
+    L: ToListener<State>,
+{
+    type Listener = RetryAfterListener<L::Listener>;
+
+    fn to_listener(self) -> io::Result<Self::Listener> {
+        Ok(RetryAfterListener(self.0.to_listener()?))
+    }
+}
+
+#[async_trait]
+impl<State, L> Listener<State> for RetryAfterListener<L>
+where
+    State: Clone + Send + Sync + 'static,
+    L: Listener<State>,
+{
+    async fn bind(&mut self, mut app: Server<State>) -> io::Result<()> {
+        app.with(RetryAfterMiddleware);
+        self.0.bind(app).await
+    }
+
+    async fn accept(&mut self) -> io::Result<()> {
+        self.0.accept().await
+    }
+
+    fn info(&self) -> Vec<ListenInfo> {
+        self.0.info()
+    }
+}
+
+impl<L: Display> Display for RetryAfterListener<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Adds a `Retry-After` header with the delay reported by [`report_retry_after`], in whole seconds
+#[derive(Debug)]
+struct RetryAfterMiddleware;
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for RetryAfterMiddleware {
+    async fn handle(&self, request: Request<State>, next: Next<'_, State>) -> tide::Result {
+        let (mut response, retry_after) = RETRY_AFTER
+            .scope(Cell::new(None), async {
+                let response = next.run(request).await;
+                (response, RETRY_AFTER.with(Cell::get))
+            })
+            .await;
+        if let Some(retry_after) = retry_after {
+            let seconds = retry_after.as_millis().div_ceil(1000);
+            response.insert_header("Retry-After", seconds.to_string());
+        }
+        Ok(response)
+    }
+}